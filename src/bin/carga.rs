@@ -0,0 +1,445 @@
+extern crate chat;
+extern crate clap;
+
+use chat::cliente::{ClienteChat, ErrorCliente};
+use chat::red::{notificacion::Notificacion, nucleo::Nucleo, servidor::Servidor};
+use clap::{Arg, App, ArgMatches};
+use std::collections::BTreeMap;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Barrier};
+use std::time::{Duration, Instant};
+use std::{process, thread};
+
+/// Tiempo que los clientes esperan las últimas notificaciones antes de desconectarse.
+const PLAZO_DRENADO: u64 = 1000;
+
+/// Número máximo de usuarios por cada comando `INVITE`, para no exceder el tamaño máximo
+/// de un mensaje.
+const INVITADOS_POR_COMANDO: usize = 100;
+
+/// Parámetros de la prueba de carga.
+struct Opciones {
+    clientes: usize,
+    salas: usize,
+    duracion: Duration,
+    publicos: f64,
+    privados: f64,
+    en_sala: f64,
+}
+
+#[derive(Clone, Copy)]
+/// Tipos de mensaje que envían los clientes sintéticos.
+enum TipoMensaje {
+    Publico,
+    Privado,
+    Sala,
+}
+
+#[derive(Default)]
+/// Mediciones de uno o varios clientes sintéticos.
+struct Medicion {
+    conexiones: Vec<Duration>,
+    respuestas: Vec<Duration>,
+    entregas: Vec<Duration>,
+    enviados: u64,
+    recibidos: u64,
+    errores: BTreeMap<String, u64>,
+}
+
+impl Medicion {
+
+    /// Registra el resultado de un comando que comenzó en `inicio`, regresando si tuvo éxito.
+    fn registrar<T>(&mut self, inicio: Instant, resultado: Result<T, ErrorCliente>) -> bool {
+        match resultado {
+            Ok(_) => {
+                self.respuestas.push(inicio.elapsed());
+                true
+            },
+            Err(error) => {
+                self.error(tipo_error(&error));
+                false
+            },
+        }
+    }
+
+    /// Cuenta un error del tipo indicado.
+    fn error(&mut self, tipo: String) {
+        *self.errores.entry(tipo).or_insert(0) += 1;
+    }
+
+    /// Agrega las mediciones de otro cliente a éstas.
+    fn combinar(&mut self, otra: Medicion) {
+        self.conexiones.extend(otra.conexiones);
+        self.respuestas.extend(otra.respuestas);
+        self.entregas.extend(otra.entregas);
+        self.enviados += otra.enviados;
+        self.recibidos += otra.recibidos;
+        for (tipo, cuenta) in otra.errores {
+            *self.errores.entry(tipo).or_insert(0) += cuenta;
+        }
+    }
+}
+
+/// Regresa la clave con la que se cuenta un error: el código de respuesta si el servidor
+/// rechazó el comando, o el tipo de falla de la conexión.
+fn tipo_error(error: &ErrorCliente) -> String {
+    match *error {
+        ErrorCliente::Rechazo(ref respuesta) => respuesta.get_codigo().to_string(),
+        ErrorCliente::Transporte(ref error) => format!("transporte ({:?})", error.kind()),
+        ErrorCliente::Desconectado => String::from("desconectado"),
+    }
+}
+
+/// Nombre de usuario del cliente sintético con el índice.
+fn nombre_cliente(indice: usize) -> String {
+    format!("carga{}", indice)
+}
+
+/// Nombre de la sala con el índice.
+fn nombre_sala(indice: usize) -> String {
+    format!("sala{}", indice)
+}
+
+/// Regresa los microsegundos de una duración.
+fn micros(duracion: Duration) -> u64 {
+    duracion.as_secs() * 1_000_000 + u64::from(duracion.subsec_micros())
+}
+
+/// Lee las notificaciones de un cliente hasta que su conexión termina, midiendo el tiempo
+/// de entrega de los mensajes, que llevan en su texto el instante en que se enviaron.
+fn escuchar(cliente: &ClienteChat, origen: Instant) -> Medicion {
+    let mut medicion = Medicion::default();
+    for notificacion in cliente.eventos() {
+        let texto = match notificacion {
+            Notificacion::Message { texto, .. } |
+            Notificacion::PublicMessage { texto, .. } |
+            Notificacion::RoomMessage { texto, .. } => texto,
+            _ => continue,
+        };
+        medicion.recibidos += 1;
+        let enviado = texto.rsplit(' ').next().and_then(|marca| marca.parse::<u64>().ok());
+        if let Some(enviado) = enviado {
+            let ahora = micros(origen.elapsed());
+            medicion.entregas.push(Duration::from_micros(ahora.saturating_sub(enviado)));
+        }
+    }
+    medicion
+}
+
+/// Ejecuta una fase de un cliente sintético. Un pánico en la fase se cuenta como error y
+/// regresa `None`, para que el cliente siga llegando a las barreras de las fases
+/// siguientes en lugar de dejar esperando a los demás.
+fn fase<T, F: FnOnce(&mut Medicion) -> T>(medicion: &mut Medicion, trabajo: F) -> Option<T> {
+    match panic::catch_unwind(AssertUnwindSafe(|| trabajo(&mut *medicion))) {
+        Ok(resultado) => Some(resultado),
+        Err(_) => {
+            medicion.error(String::from("pánico del cliente"));
+            None
+        },
+    }
+}
+
+/// Ejecuta un cliente sintético: se conecta e identifica, crea o se une a su sala, envía
+/// mensajes a las tasas indicadas durante la prueba y se desconecta. Todos los clientes
+/// pasan por la misma barrera entre cada fase, aunque no hayan podido conectarse o una
+/// fase anterior haya entrado en pánico, en cuyo caso omiten las fases restantes.
+fn simular(indice: usize, direccion: SocketAddr, opciones: &Opciones, barrera: &Barrier,
+    origen: Instant) -> Medicion {
+    let mut medicion = Medicion::default();
+    let cliente = fase(&mut medicion, |medicion| {
+        let inicio = Instant::now();
+        let cliente = ClienteChat::conectar(direccion).map_err(ErrorCliente::from)
+            .and_then(|cliente| cliente.identificar(&nombre_cliente(indice)).map(|_| cliente));
+        match cliente {
+            Ok(cliente) => {
+                medicion.conexiones.push(inicio.elapsed());
+                Some(Arc::new(cliente))
+            },
+            Err(error) => {
+                medicion.error(format!("conexión: {}", tipo_error(&error)));
+                None
+            },
+        }
+    }).and_then(|cliente| cliente);
+    let escucha = cliente.as_ref().map(|cliente| {
+        let cliente = Arc::clone(cliente);
+        thread::spawn(move || escuchar(&cliente, origen))
+    });
+    let mut activo = cliente.is_some();
+
+    barrera.wait();
+    let sala = if opciones.salas > 0 { Some(nombre_sala(indice % opciones.salas)) } else { None };
+    if let (true, Some(cliente), Some(sala)) = (activo, cliente.as_ref(), sala.as_ref()) {
+        if indice < opciones.salas {
+            activo = fase(&mut medicion, |medicion| {
+                let inicio = Instant::now();
+                let creada = cliente.crear_sala(sala);
+                if medicion.registrar(inicio, creada) {
+                    let invitados: Vec<String> = (indice + opciones.salas..opciones.clientes)
+                        .step_by(opciones.salas).map(nombre_cliente).collect();
+                    for grupo in invitados.chunks(INVITADOS_POR_COMANDO) {
+                        let grupo: Vec<&str> = grupo.iter().map(String::as_str).collect();
+                        let inicio = Instant::now();
+                        let invitacion = cliente.invitar(sala, &grupo);
+                        medicion.registrar(inicio, invitacion);
+                    }
+                }
+            }).is_some();
+        }
+    }
+    barrera.wait();
+    if let (true, Some(cliente), Some(sala)) = (activo, cliente.as_ref(), sala.as_ref()) {
+        if indice >= opciones.salas {
+            activo = fase(&mut medicion, |medicion| {
+                let inicio = Instant::now();
+                let union = cliente.unirse(sala);
+                medicion.registrar(inicio, union);
+            }).is_some();
+        }
+    }
+    barrera.wait();
+
+    if let (true, Some(cliente)) = (activo, cliente.as_ref()) {
+        fase(&mut medicion, |medicion| {
+            enviar_mensajes(cliente, indice, opciones, sala.as_deref(), origen, medicion);
+        });
+    }
+    barrera.wait();
+    thread::sleep(Duration::from_millis(PLAZO_DRENADO));
+    if let Some(cliente) = cliente {
+        if let Err(error) = cliente.desconectar() {
+            medicion.error(tipo_error(&error));
+        }
+    }
+    if let Some(escucha) = escucha {
+        match escucha.join() {
+            Ok(recepcion) => medicion.combinar(recepcion),
+            Err(_) => medicion.error(String::from("pánico del cliente")),
+        }
+    }
+    medicion
+}
+
+/// Envía mensajes públicos, privados y a la sala del cliente durante la prueba, cada tipo
+/// a su propia tasa por segundo. Si los comandos tardan más que el intervalo, el cliente
+/// no intenta recuperar los mensajes atrasados.
+fn enviar_mensajes(cliente: &ClienteChat, indice: usize, opciones: &Opciones, sala: Option<&str>,
+    origen: Instant, medicion: &mut Medicion) {
+    let comienzo = Instant::now();
+    let fin = comienzo + opciones.duracion;
+    let desfase = indice as f64 / opciones.clientes as f64;
+    let mut tipos: Vec<(TipoMensaje, Duration, Instant)> = Vec::new();
+    for &(tipo, tasa) in &[(TipoMensaje::Publico, opciones.publicos),
+        (TipoMensaje::Privado, opciones.privados), (TipoMensaje::Sala, opciones.en_sala)] {
+        let disponible = match tipo {
+            TipoMensaje::Publico => true,
+            TipoMensaje::Privado => opciones.clientes > 1,
+            TipoMensaje::Sala => sala.is_some(),
+        };
+        if disponible && tasa > 0.0 {
+            let intervalo = Duration::from_micros((1_000_000.0 / tasa) as u64);
+            let primero = comienzo + Duration::from_micros((micros(intervalo) as f64 * desfase) as u64);
+            tipos.push((tipo, intervalo, primero));
+        }
+    }
+
+    let mut enviados = 0;
+    loop {
+        let siguiente = match tipos.iter_mut().min_by_key(|&&mut (_, _, proximo)| proximo) {
+            Some(siguiente) => siguiente,
+            None => return,
+        };
+        if siguiente.2 >= fin {
+            return;
+        }
+        let ahora = Instant::now();
+        if siguiente.2 > ahora {
+            thread::sleep(siguiente.2 - ahora);
+        }
+        let texto = format!("mensaje {}", micros(origen.elapsed()));
+        let inicio = Instant::now();
+        let resultado = match siguiente.0 {
+            TipoMensaje::Publico => cliente.mensaje_publico(&texto),
+            TipoMensaje::Privado => {
+                let destinatario = (indice + 1 + enviados % (opciones.clientes - 1)) % opciones.clientes;
+                cliente.mensaje_privado(&nombre_cliente(destinatario), &texto)
+            },
+            TipoMensaje::Sala => cliente.mensaje_sala(sala.unwrap_or(""), &texto),
+        };
+        enviados += 1;
+        medicion.enviados += 1;
+        medicion.registrar(inicio, resultado);
+        siguiente.2 = (siguiente.2 + siguiente.1).max(Instant::now());
+    }
+}
+
+/// Regresa el percentil indicado de unas duraciones ya ordenadas.
+fn percentil(duraciones: &[Duration], percentil: f64) -> Duration {
+    if duraciones.is_empty() {
+        return Duration::from_secs(0);
+    }
+    let posicion = (percentil / 100.0 * (duraciones.len() - 1) as f64).round() as usize;
+    duraciones[posicion]
+}
+
+/// Regresa una duración en milisegundos, con tres decimales.
+fn milisegundos(duracion: Duration) -> String {
+    format!("{:.3} ms", micros(duracion) as f64 / 1000.0)
+}
+
+/// Imprime los percentiles de unas duraciones.
+fn reportar_latencias(titulo: &str, duraciones: &mut [Duration]) {
+    duraciones.sort();
+    println!("{:<28} n={:<8} p50 {:>12}  p90 {:>12}  p99 {:>12}  máx {:>12}", titulo, duraciones.len(),
+        milisegundos(percentil(duraciones, 50.0)), milisegundos(percentil(duraciones, 90.0)),
+        milisegundos(percentil(duraciones, 99.0)), milisegundos(percentil(duraciones, 100.0)));
+}
+
+/// Obtiene un número de los argumentos, terminando el programa si no es válido.
+fn numero<T: std::str::FromStr>(matches: &ArgMatches, argumento: &str) -> T {
+    match matches.value_of(argumento).unwrap().parse::<T>() {
+        Ok(valor) => valor,
+        Err(_) => {
+            eprintln!("El valor de --{} no es válido", argumento);
+            process::exit(2);
+        },
+    }
+}
+
+fn main() {
+    let matches = App::new("chat-carga")
+                    .version("1.0")
+                    .about("Genera carga sobre un servidor de chat y reporta su desempeño")
+                    .arg(Arg::with_name("servidor")
+                        .long("server")
+                        .value_name("DIRECCION")
+                        .help("La dirección del servidor; si se omite, se levanta uno en este proceso")
+                        .takes_value(true))
+                    .arg(Arg::with_name("nucleo")
+                        .short("n")
+                        .long("nucleo")
+                        .value_name("NUCLEO")
+                        .help("El núcleo del servidor que se levanta en este proceso")
+                        .possible_values(&["hilos", "reactor"])
+                        .default_value("hilos"))
+                    .arg(Arg::with_name("clientes")
+                        .short("c")
+                        .long("clientes")
+                        .value_name("N")
+                        .help("El número de clientes sintéticos")
+                        .default_value("50"))
+                    .arg(Arg::with_name("salas")
+                        .long("salas")
+                        .value_name("N")
+                        .help("El número de salas entre las que se reparten los clientes")
+                        .default_value("5"))
+                    .arg(Arg::with_name("duracion")
+                        .short("d")
+                        .long("duracion")
+                        .value_name("SEGUNDOS")
+                        .help("La duración del envío de mensajes")
+                        .default_value("10"))
+                    .arg(Arg::with_name("publicos")
+                        .long("publicos")
+                        .value_name("TASA")
+                        .help("Mensajes públicos por segundo de cada cliente")
+                        .default_value("0.2"))
+                    .arg(Arg::with_name("privados")
+                        .long("privados")
+                        .value_name("TASA")
+                        .help("Mensajes privados por segundo de cada cliente")
+                        .default_value("2"))
+                    .arg(Arg::with_name("en_sala")
+                        .long("sala")
+                        .value_name("TASA")
+                        .help("Mensajes a su sala por segundo de cada cliente")
+                        .default_value("1"))
+                    .get_matches();
+
+    let clientes: usize = numero(&matches, "clientes");
+    let opciones = Arc::new(Opciones {
+        clientes,
+        salas: numero::<usize>(&matches, "salas").min(clientes),
+        duracion: Duration::from_millis((numero::<f64>(&matches, "duracion") * 1000.0) as u64),
+        publicos: numero(&matches, "publicos"),
+        privados: numero(&matches, "privados"),
+        en_sala: numero(&matches, "en_sala"),
+    });
+    if opciones.clientes == 0 {
+        eprintln!("Se necesita al menos un cliente");
+        process::exit(2);
+    }
+
+    let mut control = None;
+    let direccion = match matches.value_of("servidor") {
+        Some(direccion) => match direccion.to_socket_addrs().ok().and_then(|mut todas| todas.next()) {
+            Some(direccion) => direccion,
+            None => {
+                eprintln!("La dirección {} no es válida", direccion);
+                process::exit(2);
+            },
+        },
+        None => {
+            let mut servidor = Servidor::con_direccion("127.0.0.1:0".parse().unwrap());
+            servidor.set_nucleo(matches.value_of("nucleo").unwrap().parse::<Nucleo>().unwrap());
+            let iniciado = match servidor.iniciar() {
+                Ok(iniciado) => iniciado,
+                Err(error) => {
+                    eprintln!("No fue posible levantar el servidor: {}", error);
+                    process::exit(1);
+                },
+            };
+            let direccion = iniciado.get_direccion_local()
+                .expect("El servidor escucha en una dirección TCP");
+            control = Some(iniciado);
+            direccion
+        },
+    };
+
+    println!("Servidor: {}", direccion);
+    println!("Clientes: {}, salas: {}, duración: {:?}", opciones.clientes, opciones.salas,
+        opciones.duracion);
+    println!("Tasas por cliente: {} públicos/s, {} privados/s, {} a sala/s", opciones.publicos,
+        opciones.privados, opciones.en_sala);
+
+    let origen = Instant::now();
+    let barrera = Arc::new(Barrier::new(opciones.clientes));
+    let hilos: Vec<thread::JoinHandle<Medicion>> = (0..opciones.clientes).map(|indice| {
+        let opciones = Arc::clone(&opciones);
+        let barrera = Arc::clone(&barrera);
+        thread::Builder::new().name(nombre_cliente(indice)).stack_size(256 * 1024)
+            .spawn(move || simular(indice, direccion, &opciones, &barrera, origen))
+            .expect("Error al crear el hilo del cliente")
+    }).collect();
+
+    let mut total = Medicion::default();
+    for hilo in hilos {
+        match hilo.join() {
+            Ok(medicion) => total.combinar(medicion),
+            Err(_) => total.error(String::from("pánico del cliente")),
+        }
+    }
+    let segundos = opciones.duracion.as_secs() as f64 + f64::from(opciones.duracion.subsec_nanos()) / 1e9;
+
+    println!();
+    println!("Clientes conectados: {} de {}", total.conexiones.len(), opciones.clientes);
+    reportar_latencias("Conexión (TCP + IDENTIFY)", &mut total.conexiones);
+    reportar_latencias("Respuesta a comandos", &mut total.respuestas);
+    reportar_latencias("Entrega de mensajes", &mut total.entregas);
+    println!("Mensajes enviados: {} ({:.1}/s)", total.enviados, total.enviados as f64 / segundos);
+    println!("Mensajes recibidos: {} ({:.1}/s)", total.recibidos, total.recibidos as f64 / segundos);
+    let errores: u64 = total.errores.values().sum();
+    println!("Errores: {}", errores);
+    for (tipo, cuenta) in &total.errores {
+        println!("  {}: {}", tipo, cuenta);
+    }
+
+    if let Some(control) = control {
+        control.detener();
+        control.esperar();
+    }
+    if errores > 0 {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,158 @@
+extern crate chat;
+extern crate gtk;
+extern crate glib;
+
+use chat::cliente::ClienteChat;
+use chat::red::comando::Comando;
+use std::thread;
+use std::cell::RefCell;
+use std::sync::{mpsc, mpsc::Receiver, mpsc::Sender, Arc, Mutex};
+use gtk::prelude::*;
+use std::path::Path;
+
+/// Muestra las notificaciones que recibe el cliente hasta que la conexión termina.
+fn escuchar_eventos(cliente: Arc<ClienteChat>, tx: Sender<String>) {
+    for notificacion in cliente.eventos() {
+        if tx.send(notificacion.descripcion()).is_err() {
+            break;
+        }
+        glib::idle_add(recibir);
+    }
+}
+
+thread_local!(
+    static GLOBAL: RefCell<Option<(gtk::TextBuffer, Receiver<String>)>> = const { RefCell::new(None) };
+);
+
+fn recibir() -> glib::ControlFlow {
+    GLOBAL.with(|global| {
+        if let Some((ref buffer, ref rx)) = *global.borrow() {
+            if let Ok(mensaje) = rx.try_recv() {
+                if let Some(historial) = buffer.text(&buffer.start_iter(), &buffer.end_iter(), true) {
+                    let texto = if historial.len() > 1 {
+                        format!("{}\n{}", historial, mensaje)
+                    } else {
+                        mensaje
+                    };
+                    buffer.set_text(&texto);
+                }
+            }
+        }
+    });
+    glib::ControlFlow::Break
+}
+
+fn main() {
+    if gtk::init().is_err() {
+        println!("Error al inicializar GTK.");
+        return;
+    }
+
+    gtk::Window::set_default_icon_from_file(Path::new("./src/ui/rust_logo.png")).unwrap();
+    let dialog_glade = include_str!("../ui/dialog.glade");
+    let builder = gtk::Builder::from_string(dialog_glade);
+
+    let dialogo: gtk::Dialog = builder.object("dialog").unwrap();
+    let boton_conectar: gtk::Button = builder.object("boton_conectar").unwrap();
+    let input_direccion: gtk::Entry = builder.object("input_direccion").unwrap();
+
+    let dialogo_error_glade = include_str!("../ui/dialog_error.glade");
+    let builder = gtk::Builder::from_string(dialogo_error_glade);
+    let dialog_error: gtk::Dialog = builder.object("dialog_error").unwrap();
+
+    let boton_reconectar: gtk::Button = builder.object("boton_reconectar").unwrap();
+
+    let chat_glade = include_str!("../ui/chat.glade");
+    let builder = gtk::Builder::from_string(chat_glade);
+    let window: gtk::Window = builder.object("ventana_chat").unwrap();
+
+    let boton_enviar: gtk::Button = builder.object("boton_enviar").unwrap();
+    let input_mensaje: gtk::Entry = builder.object("input_mensaje").unwrap();
+
+    window.connect_delete_event(|_, _| {
+        gtk::main_quit();
+        glib::Propagation::Proceed
+    });
+    dialogo.show();
+
+    dialogo.connect_delete_event(|_, _| {
+        gtk::main_quit();
+        glib::Propagation::Proceed
+    });
+
+    let (tx, rx) = mpsc::channel();
+    let lista_mensajes: gtk::TextView = builder.object("sala_principal_mensajes").unwrap();
+    GLOBAL.with(|global| {
+        *global.borrow_mut() = Some((lista_mensajes.buffer().expect("Error al obtener buffer del text view"),
+                                    rx))
+    });
+
+    let cliente: Arc<Mutex<Option<Arc<ClienteChat>>>> = Arc::new(Mutex::new(None));
+    let cliente_ref = Arc::clone(&cliente);
+
+    let boton_conectar_clon = boton_conectar.clone();
+    input_direccion.connect_activate(move |_| {
+        boton_conectar_clon.emit_activate();
+    });
+
+    let dialog_error_clon = dialog_error.clone();
+    let tx_eventos = tx.clone();
+    boton_conectar.connect_clicked(move |_| {
+        let direccion = input_direccion.text();
+        match ClienteChat::conectar(direccion.as_str()) {
+            Ok(conectado) => {
+                let conectado = Arc::new(conectado);
+                let escucha = Arc::clone(&conectado);
+                let tx_eventos = tx_eventos.clone();
+                thread::spawn(move || escuchar_eventos(escucha, tx_eventos));
+                *cliente_ref.lock().unwrap() = Some(conectado);
+                dialogo.hide();
+                window.show_all();
+            },
+            Err(_) => {
+                dialog_error_clon.show();
+                input_direccion.set_text("");
+            }
+        }
+    });
+
+    let dialog_error_clon = dialog_error.clone();
+    boton_reconectar.connect_clicked(move |_| {
+        dialog_error_clon.hide();
+    });
+
+    let boton_enviar_clon = boton_enviar.clone();
+    input_mensaje.connect_activate(move |_| {
+        boton_enviar_clon.emit_activate();
+    });
+
+    let cliente_ref = Arc::clone(&cliente);
+    boton_enviar.connect_clicked(move |_| {
+        let conectado = match *cliente_ref.lock().unwrap() {
+            Some(ref conectado) => Arc::clone(conectado),
+            None => return,
+        };
+        let linea = input_mensaje.text();
+        input_mensaje.set_text("");
+        let tx = tx.clone();
+        match linea.parse::<Comando>() {
+            Ok(comando) => {
+                thread::spawn(move || {
+                    let texto = match conectado.ejecutar(comando) {
+                        Ok(respuesta) => respuesta.get_texto().to_owned(),
+                        Err(error) => error.to_string(),
+                    };
+                    if tx.send(texto).is_ok() {
+                        glib::idle_add(recibir);
+                    }
+                });
+            },
+            Err(error) => {
+                if tx.send(error.to_string()).is_ok() {
+                    glib::idle_add(recibir);
+                }
+            },
+        }
+    });
+    gtk::main();
+}
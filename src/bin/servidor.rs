@@ -0,0 +1,88 @@
+extern crate chat;
+extern crate simplelog;
+extern crate clap;
+
+use simplelog::{Level, LevelFilter, WriteLogger, Config};
+use std::fs::File;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::process;
+use chat::red::{nucleo::Nucleo, servidor::Servidor};
+use clap::{Arg, App};
+
+fn main() {
+
+    let matches = App::new("chat")
+                    .version("1.0")
+                    .author("Adrián G. <adrian.garcia04@ciencias.unam.mx>")
+                    .about("Chat para el curso de Modelado y Programación")
+                    .arg(Arg::with_name("direccion")
+                        .value_name("DIRECCION")
+                        .help("La dirección donde se escuchan las peticiones, como 127.0.0.1:1234 o \
+                            [::1]:1234, o sólo el puerto para escuchar en todas las interfaces; \
+                            con el puerto 0 el sistema asigna uno libre")
+                        .index(1)
+                        .required(true))
+                    .arg(Arg::with_name("salida")
+                        .short("o")
+                        .long("output")
+                        .value_name("ARCHIVO")
+                        .help("El archivo de log")
+                        .takes_value(true))
+                    .arg(Arg::with_name("nucleo")
+                        .short("n")
+                        .long("nucleo")
+                        .value_name("NUCLEO")
+                        .help("El núcleo con el que se atienden las conexiones")
+                        .possible_values(&["hilos", "reactor"])
+                        .default_value("hilos"))
+                    .arg(Arg::with_name("v")
+                        .short("v")
+                        .multiple(true)
+                        .help("Indica el nivel de log"))
+                    .get_matches();
+
+    let direccion = matches.value_of("direccion").unwrap();
+    let direccion = match direccion.parse::<SocketAddr>() {
+        Ok(direccion) => direccion,
+        Err(_) => match direccion.parse::<u16>() {
+            Ok(puerto) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), puerto),
+            Err(_) => {
+                eprintln!("La dirección {} no es válida", direccion);
+                process::exit(2);
+            },
+        },
+    };
+    let nucleo = matches.value_of("nucleo").unwrap().parse::<Nucleo>().unwrap();
+    let nombre_archivo = matches.value_of("salida").unwrap_or("servidor.log");
+    
+    let log_level = match matches.occurrences_of("v") {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Warn,
+        _ => LevelFilter::max(),
+    };
+
+    let config = Config {
+        time: Some(Level::Error),
+        level: Some(Level::Error),
+        target: Some(Level::Error),
+        location: Some(Level::Trace),
+        time_format: Some("%r"),
+    };
+
+    let archivo_log = File::create(nombre_archivo).unwrap();
+    WriteLogger::init(log_level, config, archivo_log).unwrap();
+    let mut servidor = Servidor::con_direccion(direccion);
+    servidor.set_nucleo(nucleo);
+    match servidor.iniciar() {
+        Ok(control) => {
+            println!("Servidor escuchando en: {}", control.get_direccion_local()
+                .expect("El servidor escucha en una dirección TCP"));
+            control.esperar();
+        },
+        Err(error) => {
+            eprintln!("No fue posible levantar el servidor en {}: {}", direccion, error);
+            process::exit(1);
+        },
+    }
+}
@@ -0,0 +1,245 @@
+extern crate chat;
+extern crate clap;
+extern crate termion;
+
+use chat::cliente::ClienteChat;
+use chat::red::{comando::Comando, eventoconexion::EventoConexion};
+use clap::{Arg, App};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{mpsc, Arc};
+use std::{process, thread};
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
+use termion::{clear, cursor};
+
+/// Líneas que se desplaza el historial con RePág y AvPág.
+const DESPLAZAMIENTO: usize = 10;
+
+/// Número máximo de líneas que guarda el historial. Al excederlo se descartan las más
+/// antiguas.
+const HISTORIAL_MAXIMO: usize = 1000;
+
+/// Eventos que atiende el ciclo principal del cliente.
+enum Evento {
+    /// El usuario presionó una tecla.
+    Tecla(Key),
+    /// Una línea para agregar al historial: una notificación, una respuesta o un error.
+    Linea(String),
+}
+
+/// Estado de la pantalla: el historial de mensajes arriba y la línea de entrada abajo.
+struct Pantalla {
+    historial: VecDeque<String>,
+    entrada: String,
+    desplazamiento: usize,
+}
+
+impl Pantalla {
+
+    fn new() -> Pantalla {
+        Pantalla {
+            historial: VecDeque::new(),
+            entrada: String::new(),
+            desplazamiento: 0,
+        }
+    }
+
+    /// Agrega una línea al final del historial, descartando la más antigua si el historial
+    /// excede [`HISTORIAL_MAXIMO`](constant.HISTORIAL_MAXIMO.html) líneas.
+    fn agregar(&mut self, linea: String) {
+        self.historial.push_back(linea);
+        if self.historial.len() > HISTORIAL_MAXIMO {
+            self.historial.pop_front();
+        }
+    }
+
+    /// Dibuja el historial, una línea separadora y la línea de entrada, ajustándose al
+    /// tamaño actual de la terminal. El desplazamiento se limita al inicio del historial.
+    fn dibujar<W: Write>(&mut self, salida: &mut W) -> io::Result<()> {
+        let (ancho, alto) = termion::terminal_size()?;
+        let (ancho, alto) = (ancho.max(1) as usize, alto.max(3) as usize);
+        let filas = alto - 2;
+        let lineas: Vec<String> = self.historial.iter()
+            .flat_map(|linea| envolver(linea, ancho))
+            .collect();
+        self.desplazamiento = self.desplazamiento.min(lineas.len().saturating_sub(filas));
+        let fin = lineas.len() - self.desplazamiento;
+        let inicio = fin.saturating_sub(filas);
+
+        write!(salida, "{}", clear::All)?;
+        for (fila, linea) in lineas[inicio..fin].iter().enumerate() {
+            write!(salida, "{}{}", cursor::Goto(1, fila as u16 + 1), linea)?;
+        }
+        write!(salida, "{}{}", cursor::Goto(1, filas as u16 + 1), "─".repeat(ancho))?;
+        let entrada: String = self.entrada.chars().rev().take(ancho.saturating_sub(2)).collect::<Vec<char>>()
+            .into_iter().rev().collect();
+        write!(salida, "{}> {}", cursor::Goto(1, alto as u16), entrada)?;
+        salida.flush()
+    }
+}
+
+/// Divide una línea en renglones de a lo más `ancho` caracteres.
+fn envolver(linea: &str, ancho: usize) -> Vec<String> {
+    let caracteres: Vec<char> = linea.chars().collect();
+    if caracteres.is_empty() {
+        return vec![String::new()];
+    }
+    caracteres.chunks(ancho).map(|renglon| renglon.iter().collect()).collect()
+}
+
+/// Regresa el evento del protocolo que corresponde a un comando con diagonal.
+fn evento_de(nombre: &str) -> Option<EventoConexion> {
+    match nombre {
+        "msg" => Some(EventoConexion::MESSAGE),
+        "join" => Some(EventoConexion::JOINROOM),
+        "invite" => Some(EventoConexion::INVITE),
+        "status" => Some(EventoConexion::STATUS),
+        "users" => Some(EventoConexion::USERS),
+        "create" => Some(EventoConexion::CREATEROOM),
+        "room" => Some(EventoConexion::ROOMESSAGE),
+        "quit" => Some(EventoConexion::DISCONNECT),
+        _ => None,
+    }
+}
+
+/// Convierte una línea escrita por el usuario en un comando. Las líneas que inician con
+/// una diagonal son comandos como `/msg usuario hola`, cuyos argumentos siguen las reglas
+/// del protocolo; las demás se envían como mensajes públicos.
+fn analizar_linea(linea: &str) -> Result<Comando, String> {
+    if !linea.starts_with('/') {
+        return Ok(Comando::PublicMessage { texto: linea.to_owned() });
+    }
+    let mut partes = linea[1..].splitn(2, ' ');
+    let nombre = partes.next().unwrap_or("");
+    let argumentos = partes.next().unwrap_or("");
+    match evento_de(nombre) {
+        Some(evento) => format!("{} {}", evento, argumentos).trim_end().parse::<Comando>()
+            .map_err(|error| error.to_string()),
+        None => Err(format!("Comando desconocido: /{}. Comandos: /msg /join /invite /status \
+            /users /create /room /quit", nombre)),
+    }
+}
+
+/// Atiende los eventos del cliente, redibujando la pantalla después de cada uno, hasta que
+/// el usuario sale. Regresa un error si no fue posible dibujar la pantalla.
+fn atender<W: Write>(cliente: &Arc<ClienteChat>, tx: &mpsc::Sender<Evento>, rx: &mpsc::Receiver<Evento>,
+    pantalla: &mut Pantalla, salida: &mut W) -> io::Result<()> {
+    pantalla.dibujar(salida)?;
+
+    for evento in rx.iter() {
+        match evento {
+            Evento::Linea(linea) => pantalla.agregar(linea),
+            Evento::Tecla(Key::Char('\n')) if pantalla.entrada.trim().is_empty() => pantalla.entrada.clear(),
+            Evento::Tecla(Key::Char('\n')) => {
+                let linea: String = pantalla.entrada.drain(..).collect();
+                pantalla.desplazamiento = 0;
+                match analizar_linea(&linea) {
+                    Ok(Comando::Disconnect) => break,
+                    Ok(comando) => {
+                        let cliente = Arc::clone(cliente);
+                        let tx = tx.clone();
+                        thread::spawn(move || {
+                            let texto = match cliente.ejecutar(comando) {
+                                Ok(respuesta) => respuesta.get_texto().to_owned(),
+                                Err(error) => error.to_string(),
+                            };
+                            let _ = tx.send(Evento::Linea(texto));
+                        });
+                    },
+                    Err(error) => pantalla.agregar(error),
+                }
+            },
+            Evento::Tecla(Key::Char(caracter)) => pantalla.entrada.push(caracter),
+            Evento::Tecla(Key::Backspace) => {
+                pantalla.entrada.pop();
+            },
+            Evento::Tecla(Key::PageUp) => pantalla.desplazamiento += DESPLAZAMIENTO,
+            Evento::Tecla(Key::PageDown) =>
+                pantalla.desplazamiento = pantalla.desplazamiento.saturating_sub(DESPLAZAMIENTO),
+            Evento::Tecla(Key::Ctrl('c')) | Evento::Tecla(Key::Ctrl('d')) | Evento::Tecla(Key::Esc) => break,
+            Evento::Tecla(_) => {},
+        }
+        pantalla.dibujar(salida)?;
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let matches = App::new("chat-terminal")
+                    .version("1.0")
+                    .about("Cliente de terminal para el chat")
+                    .arg(Arg::with_name("servidor")
+                        .long("server")
+                        .value_name("DIRECCION")
+                        .help("La dirección del servidor, como 127.0.0.1:1234")
+                        .takes_value(true)
+                        .required(true))
+                    .arg(Arg::with_name("nombre")
+                        .long("name")
+                        .value_name("NOMBRE")
+                        .help("El nombre de usuario con el que se identifica el cliente")
+                        .takes_value(true)
+                        .required(true))
+                    .get_matches();
+
+    let direccion = matches.value_of("servidor").unwrap();
+    let nombre = matches.value_of("nombre").unwrap();
+
+    let cliente = match ClienteChat::conectar(direccion) {
+        Ok(cliente) => Arc::new(cliente),
+        Err(error) => {
+            eprintln!("No fue posible conectarse a {}: {}", direccion, error);
+            process::exit(1);
+        },
+    };
+    if let Err(error) = cliente.identificar(nombre) {
+        eprintln!("No fue posible identificarse como {}: {}", nombre, error);
+        process::exit(1);
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let tx_teclas = tx.clone();
+    thread::spawn(move || {
+        for tecla in io::stdin().keys() {
+            match tecla {
+                Ok(tecla) => if tx_teclas.send(Evento::Tecla(tecla)).is_err() {
+                    break;
+                },
+                Err(_) => break,
+            }
+        }
+    });
+    let escucha = Arc::clone(&cliente);
+    let tx_eventos = tx.clone();
+    thread::spawn(move || {
+        for notificacion in escucha.eventos() {
+            if tx_eventos.send(Evento::Linea(notificacion.descripcion())).is_err() {
+                return;
+            }
+        }
+        let _ = tx_eventos.send(Evento::Linea(String::from("La conexión con el servidor terminó")));
+    });
+
+    let salida = match io::stdout().into_raw_mode() {
+        Ok(salida) => salida,
+        Err(error) => {
+            eprintln!("No fue posible preparar la terminal: {}", error);
+            process::exit(1);
+        },
+    };
+    let mut salida = AlternateScreen::from(salida);
+    let mut pantalla = Pantalla::new();
+    pantalla.agregar(format!("Conectado a {} como {}. Escribe /quit para salir.", direccion, nombre));
+    let resultado = atender(&cliente, &tx, &rx, &mut pantalla, &mut salida);
+
+    let _ = cliente.desconectar();
+    drop(salida);
+    if let Err(error) = resultado {
+        eprintln!("No fue posible dibujar la pantalla: {}", error);
+        process::exit(1);
+    }
+}
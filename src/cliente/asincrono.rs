@@ -0,0 +1,260 @@
+use super::{saludo, ErrorCliente, PLAZO_RESPUESTA_POR_OMISION, TAMANO_MAXIMO_MENSAJE_SERVIDOR};
+use crate::red::{comando::{Comando, IdPeticion}, estadocliente::EstadoCliente, formato::Formato,
+    mensajeservidor::MensajeServidor, notificacion::Notificacion, respuesta::Respuesta};
+use futures::{SinkExt, Stream, StreamExt};
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time;
+use tokio_util::codec::{FramedRead, FramedWrite, LinesCodec, LinesCodecError};
+
+type Pendientes = Arc<Mutex<Option<HashMap<IdPeticion, oneshot::Sender<Result<Respuesta, ErrorCliente>>>>>>;
+
+#[derive(Clone)]
+/// Cliente asíncrono del protocolo de chat, para usarse dentro de un runtime de
+/// [tokio](https://docs.rs/tokio/1). Cada comando es un método `async` que se resuelve
+/// cuando llega la respuesta con el identificador de petición del comando, por lo que
+/// pueden enviarse varios comandos sin esperar sus respuestas. Las notificaciones del
+/// servidor se reciben en el flujo de [`Eventos`](struct.Eventos.html) que se crea junto
+/// con el cliente.
+/// Las copias del cliente comparten la conexión. Cuando se eliminan todas, el cliente
+/// cierra su sentido de escritura de la conexión.
+pub struct ClienteAsincrono {
+    emisor: mpsc::UnboundedSender<String>,
+    pendientes: Pendientes,
+    siguiente_id: Arc<AtomicUsize>,
+    plazo_respuesta: Duration,
+}
+
+impl ClienteAsincrono {
+
+    /// Se conecta por TCP al servidor en la dirección recibida, regresando el cliente y el
+    /// flujo de sus notificaciones.
+    pub async fn conectar<A: ToSocketAddrs>(direccion: A) -> Result<(ClienteAsincrono, Eventos), io::Error> {
+        let socket = TcpStream::connect(direccion).await?;
+        Ok(ClienteAsincrono::con_flujo(socket))
+    }
+
+    /// Crea un cliente que se comunica con el servidor a través del flujo recibido, y el
+    /// flujo de sus notificaciones. Lanza en el runtime actual las tareas que escriben los
+    /// comandos y leen los mensajes del servidor, por lo que debe llamarse desde el
+    /// contexto de un runtime. El primer mensaje que envía es el saludo que solicita las
+    /// capacidades `CODES` e `IDS`.
+    /// Si el servidor envía un mensaje que excede el
+    /// [tamaño máximo](../constant.TAMANO_MAXIMO_MENSAJE_SERVIDOR.html) o la lectura falla,
+    /// la lectura termina y los comandos pendientes fallan con un error de
+    /// [`Transporte`](../enum.ErrorCliente.html#variant.Transporte).
+    pub fn con_flujo<T>(flujo: T) -> (ClienteAsincrono, Eventos)
+        where T: AsyncRead + AsyncWrite + Send + 'static {
+        let (lectura, escritura) = tokio::io::split(flujo);
+        let mut lectura = FramedRead::new(lectura,
+            LinesCodec::new_with_max_length(TAMANO_MAXIMO_MENSAJE_SERVIDOR));
+        let mut escritura = FramedWrite::new(escritura, LinesCodec::new());
+        let (emisor, mut receptor) = mpsc::unbounded_channel();
+        let _ = emisor.send(Formato::TEXT.formatear_peticion(None, &saludo()));
+        let (emisor_eventos, receptor_eventos) = mpsc::unbounded_channel();
+        let pendientes: Pendientes = Arc::new(Mutex::new(Some(HashMap::new())));
+
+        tokio::spawn(async move {
+            while let Some(linea) = receptor.recv().await {
+                if escritura.send(linea).await.is_err() {
+                    return;
+                }
+            }
+            let _ = SinkExt::<String>::close(&mut escritura).await;
+        });
+
+        let pendientes_lector = Arc::clone(&pendientes);
+        tokio::spawn(async move {
+            let error = loop {
+                match lectura.next().await {
+                    Some(Ok(linea)) =>
+                        ClienteAsincrono::despachar(&linea, &pendientes_lector, &emisor_eventos),
+                    Some(Err(error)) => break Some(ClienteAsincrono::error_de_lectura(error)),
+                    None => break None,
+                }
+            };
+            let pendientes = pendientes_lector.lock().unwrap().take();
+            if let (Some(error), Some(pendientes)) = (error, pendientes) {
+                for (_, emisor) in pendientes {
+                    let copia = io::Error::new(error.kind(), error.to_string());
+                    let _ = emisor.send(Err(ErrorCliente::Transporte(copia)));
+                }
+            }
+        });
+
+        let cliente = ClienteAsincrono {
+            emisor,
+            pendientes,
+            siguiente_id: Arc::new(AtomicUsize::new(1)),
+            plazo_respuesta: Duration::from_millis(PLAZO_RESPUESTA_POR_OMISION),
+        };
+        (cliente, Eventos { receptor: receptor_eventos })
+    }
+
+    /// Traduce los errores de lectura de líneas. Una línea que excede el tamaño máximo se
+    /// reporta como datos inválidos, y los errores de E/S se regresan sin cambios.
+    fn error_de_lectura(error: LinesCodecError) -> io::Error {
+        match error {
+            LinesCodecError::MaxLineLengthExceeded =>
+                io::Error::new(ErrorKind::InvalidData, "El mensaje del servidor excede el tamaño máximo"),
+            LinesCodecError::Io(error) => error,
+        }
+    }
+
+    /// Entrega una línea recibida del servidor: las respuestas a quien espera el comando con
+    /// su identificador y las notificaciones al flujo de eventos.
+    fn despachar(linea: &str, pendientes: &Pendientes, eventos: &mpsc::UnboundedSender<Notificacion>) {
+        match Formato::TEXT.analizar_mensaje(linea) {
+            Ok(MensajeServidor::Respuesta(respuesta)) => {
+                let emisor = respuesta.get_id().and_then(|id| {
+                    pendientes.lock().unwrap().as_mut().and_then(|mapa| mapa.remove(&id))
+                });
+                if let Some(emisor) = emisor {
+                    let _ = emisor.send(Ok(respuesta));
+                }
+            },
+            Ok(MensajeServidor::Notificacion(notificacion)) => {
+                let _ = eventos.send(notificacion);
+            },
+            Err(_) => {},
+        }
+    }
+
+    /// Regresa el tiempo que el cliente espera la respuesta a un comando.
+    pub fn get_plazo_respuesta(&self) -> Duration {
+        self.plazo_respuesta
+    }
+
+    /// Define el tiempo que el cliente espera la respuesta a un comando.
+    pub fn set_plazo_respuesta(&mut self, plazo: Duration) {
+        self.plazo_respuesta = plazo;
+    }
+
+    /// Envía un comando al servidor y espera su respuesta. Regresa la respuesta si el
+    /// comando se realizó correctamente, o un error si el servidor lo rechazó, la conexión
+    /// terminó antes de recibir la respuesta o la respuesta no llegó dentro del plazo. Debe
+    /// esperarse dentro de un runtime de tokio con el temporizador habilitado. El comando
+    /// `DISCONNECT` no tiene respuesta, por lo que se realiza con
+    /// [`desconectar`](#method.desconectar).
+    pub async fn ejecutar(&self, comando: Comando) -> Result<Respuesta, ErrorCliente> {
+        if comando == Comando::Disconnect {
+            self.desconectar()?;
+            return Ok(Respuesta::ok("Desconectado del servidor"));
+        }
+        let id = self.siguiente_id.fetch_add(1, Ordering::SeqCst) as IdPeticion;
+        let (emisor, receptor) = oneshot::channel();
+        match *self.pendientes.lock().unwrap() {
+            Some(ref mut pendientes) => pendientes.insert(id, emisor),
+            None => return Err(ErrorCliente::Desconectado),
+        };
+        let linea = Formato::TEXT.formatear_peticion(Some(id), &comando);
+        if self.emisor.send(linea).is_err() {
+            self.olvidar(id);
+            return Err(ErrorCliente::Desconectado);
+        }
+        match time::timeout(self.plazo_respuesta, receptor).await {
+            Ok(Ok(Ok(ref respuesta))) if respuesta.get_codigo().es_exito() => Ok(respuesta.clone()),
+            Ok(Ok(Ok(respuesta))) => Err(ErrorCliente::Rechazo(respuesta)),
+            Ok(Ok(Err(error))) => Err(error),
+            Ok(Err(_)) => Err(ErrorCliente::Desconectado),
+            Err(_) => {
+                self.olvidar(id);
+                Err(ErrorCliente::Transporte(io::Error::new(ErrorKind::TimedOut,
+                    "El servidor no respondió a tiempo")))
+            },
+        }
+    }
+
+    /// Deja de esperar la respuesta de la petición con el identificador.
+    fn olvidar(&self, id: IdPeticion) {
+        if let Some(ref mut pendientes) = *self.pendientes.lock().unwrap() {
+            pendientes.remove(&id);
+        }
+    }
+
+    /// Se identifica ante el servidor con un nombre de usuario.
+    pub async fn identificar(&self, nombre: &str) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::Identify { nombre: nombre.to_owned() }).await.map(|_| ())
+    }
+
+    /// Cambia el estado del usuario.
+    pub async fn estado(&self, estado: EstadoCliente) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::Status { estado }).await.map(|_| ())
+    }
+
+    /// Regresa los nombres de los usuarios identificados en el servidor.
+    pub async fn usuarios(&self) -> Result<Vec<String>, ErrorCliente> {
+        let respuesta = self.ejecutar(Comando::Users).await?;
+        Ok(respuesta.get_texto().split_whitespace().map(String::from).collect())
+    }
+
+    /// Envía un mensaje privado a un usuario.
+    pub async fn mensaje_privado(&self, destinatario: &str, texto: &str) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::Message {
+            destinatario: destinatario.to_owned(),
+            texto: texto.to_owned(),
+        }).await.map(|_| ())
+    }
+
+    /// Envía un mensaje público a todos los usuarios.
+    pub async fn mensaje_publico(&self, texto: &str) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::PublicMessage { texto: texto.to_owned() }).await.map(|_| ())
+    }
+
+    /// Crea una sala, de la que el usuario es propietario.
+    pub async fn crear_sala(&self, sala: &str) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::CreateRoom { sala: sala.to_owned() }).await.map(|_| ())
+    }
+
+    /// Invita a unos usuarios a una sala de la que el usuario es propietario.
+    pub async fn invitar(&self, sala: &str, usuarios: &[&str]) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::Invite {
+            sala: sala.to_owned(),
+            usuarios: usuarios.iter().map(|usuario| usuario.to_string()).collect(),
+        }).await.map(|_| ())
+    }
+
+    /// Se une a una sala a la que el usuario fue invitado.
+    pub async fn unirse(&self, sala: &str) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::JoinRoom { sala: sala.to_owned() }).await.map(|_| ())
+    }
+
+    /// Envía un mensaje a una sala de la que el usuario es miembro.
+    pub async fn mensaje_sala(&self, sala: &str, texto: &str) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::RoomMessage {
+            sala: sala.to_owned(),
+            texto: texto.to_owned(),
+        }).await.map(|_| ())
+    }
+
+    /// Avisa al servidor que el cliente se desconecta. Los comandos pendientes y los
+    /// posteriores fallan con [`Desconectado`](../enum.ErrorCliente.html#variant.Desconectado),
+    /// y el flujo de eventos termina cuando el servidor cierra la conexión.
+    pub fn desconectar(&self) -> Result<(), ErrorCliente> {
+        self.pendientes.lock().unwrap().take();
+        let linea = Formato::TEXT.formatear_peticion(None, &Comando::Disconnect);
+        self.emisor.send(linea).map_err(|_| ErrorCliente::Desconectado)
+    }
+}
+
+/// Flujo de las notificaciones que recibe un [`ClienteAsincrono`](struct.ClienteAsincrono.html),
+/// en el orden en que llegaron. Termina cuando la conexión con el servidor termina.
+pub struct Eventos {
+    receptor: mpsc::UnboundedReceiver<Notificacion>,
+}
+
+impl Stream for Eventos {
+    type Item = Notificacion;
+
+    fn poll_next(mut self: Pin<&mut Self>, contexto: &mut Context<'_>) -> Poll<Option<Notificacion>> {
+        self.receptor.poll_recv(contexto)
+    }
+}
@@ -0,0 +1,330 @@
+/// Contiene el cliente asíncrono, para usarse con tokio.
+pub mod asincrono;
+
+use crate::red::{capacidad::{self, Capacidad}, comando::{Comando, IdPeticion}, conexion::Conexion,
+    estadocliente::EstadoCliente,
+    formato::Formato, lector::LectorMensajes, mensajeservidor::MensajeServidor,
+    notificacion::Notificacion, respuesta::Respuesta, util};
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use std::{error, fmt, thread};
+
+/// Tiempo por omisión (en milisegundos) que el cliente espera la respuesta a un comando.
+pub const PLAZO_RESPUESTA_POR_OMISION: u64 = 10000;
+
+/// Tamaño máximo (en bytes) de los mensajes del servidor que aceptan los clientes. Es mucho
+/// mayor que el de los mensajes que acepta el servidor, porque respuestas como la de `USERS`
+/// crecen con el número de usuarios conectados.
+pub const TAMANO_MAXIMO_MENSAJE_SERVIDOR: usize = 1024 * 1024;
+
+/// Regresa el saludo con el que los clientes solicitan las respuestas con código e
+/// identificador de petición, de las que dependen para relacionar cada respuesta con su comando.
+fn saludo() -> Comando {
+    Comando::Hello {
+        version: capacidad::VERSION_PROTOCOLO,
+        capacidades: vec![Capacidad::CODES.to_string(), Capacidad::IDS.to_string()],
+    }
+}
+
+type Pendientes = Arc<Mutex<Option<HashMap<IdPeticion, mpsc::Sender<Result<Respuesta, ErrorCliente>>>>>>;
+
+#[derive(Debug)]
+/// Errores al realizar un comando desde un [`ClienteChat`](struct.ClienteChat.html) o un
+/// [`ClienteAsincrono`](asincrono/struct.ClienteAsincrono.html).
+pub enum ErrorCliente {
+    /// El servidor respondió al comando con un código de error.
+    Rechazo(Respuesta),
+    /// La conexión con el servidor falló, o la respuesta no llegó a tiempo.
+    Transporte(io::Error),
+    /// La conexión con el servidor ya terminó.
+    Desconectado,
+}
+
+impl fmt::Display for ErrorCliente {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorCliente::Rechazo(ref respuesta) => write!(f, "{}", respuesta),
+            ErrorCliente::Transporte(ref error) => write!(f, "Error en la conexión: {}", error),
+            ErrorCliente::Desconectado => write!(f, "La conexión con el servidor terminó"),
+        }
+    }
+}
+
+impl error::Error for ErrorCliente {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ErrorCliente::Transporte(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ErrorCliente {
+    fn from(error: io::Error) -> ErrorCliente {
+        ErrorCliente::Transporte(error)
+    }
+}
+
+/// Conexión hacia el servidor junto con el identificador de la siguiente petición.
+struct Escritor {
+    conexion: Box<dyn Conexion>,
+    siguiente_id: IdPeticion,
+}
+
+/// Cliente síncrono del protocolo de chat. Cada comando se envía con un identificador de
+/// petición y el método que lo envía bloquea hasta recibir la respuesta con el mismo
+/// identificador. Un hilo propio lee los mensajes del servidor: entrega cada respuesta a
+/// quien espera su comando y guarda las notificaciones, que se obtienen en orden con
+/// [`eventos`](#method.eventos). Todos los métodos reciben `&self`, por lo que el cliente
+/// puede compartirse entre hilos, por ejemplo para leer los eventos en uno y enviar
+/// comandos en otro.
+/// Al eliminarse el cliente, su conexión se cierra.
+pub struct ClienteChat {
+    escritor: Mutex<Escritor>,
+    pendientes: Pendientes,
+    eventos: Mutex<mpsc::Receiver<Notificacion>>,
+    plazo_respuesta: Duration,
+}
+
+impl ClienteChat {
+
+    /// Se conecta por TCP al servidor en la dirección recibida.
+    pub fn conectar<A: ToSocketAddrs>(direccion: A) -> Result<ClienteChat, io::Error> {
+        let socket = TcpStream::connect(direccion)?;
+        ClienteChat::con_conexion(Box::new(socket))
+    }
+
+    /// Crea un cliente que se comunica con el servidor a través de la conexión recibida,
+    /// como un socket de Unix o un extremo de una
+    /// [`tuberia`](../red/conexion/fn.tuberia.html), y lanza el hilo que lee sus mensajes.
+    /// Antes de cualquier otro comando envía el saludo que solicita las capacidades
+    /// `CODES` e `IDS`, sin esperar su respuesta.
+    /// Si el servidor envía un mensaje que excede el
+    /// [tamaño máximo](constant.TAMANO_MAXIMO_MENSAJE_SERVIDOR.html) o que no es UTF-8
+    /// válido, la lectura termina y los comandos pendientes fallan con un error de
+    /// [`Transporte`](enum.ErrorCliente.html#variant.Transporte).
+    pub fn con_conexion(mut conexion: Box<dyn Conexion>) -> Result<ClienteChat, io::Error> {
+        util::enviar_mensaje(&mut conexion, Formato::TEXT.formatear_peticion(None, &saludo()))?;
+        let lector = LectorMensajes::con_tamano_maximo(conexion.duplicar()?,
+            TAMANO_MAXIMO_MENSAJE_SERVIDOR);
+        let pendientes: Pendientes = Arc::new(Mutex::new(Some(HashMap::new())));
+        let (emisor, receptor) = mpsc::channel();
+        let pendientes_lector = Arc::clone(&pendientes);
+        thread::spawn(move || ClienteChat::leer(lector, &pendientes_lector, &emisor));
+        Ok(ClienteChat {
+            escritor: Mutex::new(Escritor {
+                conexion,
+                siguiente_id: 1,
+            }),
+            pendientes,
+            eventos: Mutex::new(receptor),
+            plazo_respuesta: Duration::from_millis(PLAZO_RESPUESTA_POR_OMISION),
+        })
+    }
+
+    /// Lee los mensajes del servidor hasta que la conexión termina, entregando las
+    /// respuestas a quienes las esperan y las notificaciones al receptor de eventos. Al
+    /// terminar, los comandos pendientes y los siguientes fallan. Un mensaje inválido no
+    /// puede relacionarse con su comando, por lo que termina la lectura y los comandos
+    /// pendientes fallan de inmediato con el error del lector.
+    fn leer(mut lector: LectorMensajes<Box<dyn Conexion>>, pendientes: &Pendientes,
+        eventos: &mpsc::Sender<Notificacion>) {
+        loop {
+            let linea = match lector.siguiente_mensaje() {
+                Ok(linea) => linea,
+                Err(ref error) if error.kind() == ErrorKind::InvalidData => {
+                    if let Some(pendientes) = pendientes.lock().unwrap().take() {
+                        for (_, emisor) in pendientes {
+                            let copia = io::Error::new(error.kind(), error.to_string());
+                            let _ = emisor.send(Err(ErrorCliente::Transporte(copia)));
+                        }
+                    }
+                    break;
+                },
+                Err(_) => break,
+            };
+            match Formato::TEXT.analizar_mensaje(&linea) {
+                Ok(MensajeServidor::Respuesta(respuesta)) => {
+                    let id = match respuesta.get_id() {
+                        Some(id) => id,
+                        None => continue,
+                    };
+                    let emisor = pendientes.lock().unwrap().as_mut().and_then(|mapa| mapa.remove(&id));
+                    if let Some(emisor) = emisor {
+                        let _ = emisor.send(Ok(respuesta));
+                    }
+                },
+                Ok(MensajeServidor::Notificacion(notificacion)) => {
+                    let _ = eventos.send(notificacion);
+                },
+                Err(_) => {},
+            }
+        }
+        *pendientes.lock().unwrap() = None;
+    }
+
+    /// Regresa el tiempo que el cliente espera la respuesta a un comando.
+    pub fn get_plazo_respuesta(&self) -> Duration {
+        self.plazo_respuesta
+    }
+
+    /// Define el tiempo que el cliente espera la respuesta a un comando.
+    pub fn set_plazo_respuesta(&mut self, plazo: Duration) {
+        self.plazo_respuesta = plazo;
+    }
+
+    /// Envía un comando al servidor y espera su respuesta. Regresa la respuesta si el
+    /// comando se realizó correctamente, o un error si el servidor lo rechazó, la conexión
+    /// falló o la respuesta no llegó dentro del plazo. El comando `DISCONNECT` no tiene
+    /// respuesta, por lo que se realiza con [`desconectar`](#method.desconectar).
+    pub fn ejecutar(&self, comando: Comando) -> Result<Respuesta, ErrorCliente> {
+        if comando == Comando::Disconnect {
+            self.desconectar()?;
+            return Ok(Respuesta::ok("Desconectado del servidor"));
+        }
+        let (emisor, receptor) = mpsc::channel();
+        let id = {
+            let mut escritor = self.escritor.lock().unwrap();
+            let id = escritor.siguiente_id;
+            escritor.siguiente_id += 1;
+            match *self.pendientes.lock().unwrap() {
+                Some(ref mut pendientes) => pendientes.insert(id, emisor),
+                None => return Err(ErrorCliente::Desconectado),
+            };
+            let linea = Formato::TEXT.formatear_peticion(Some(id), &comando);
+            if let Err(error) = util::enviar_mensaje(&mut escritor.conexion, linea) {
+                self.olvidar(id);
+                return Err(ErrorCliente::Transporte(error));
+            }
+            id
+        };
+        match receptor.recv_timeout(self.plazo_respuesta) {
+            Ok(Ok(ref respuesta)) if respuesta.get_codigo().es_exito() => Ok(respuesta.clone()),
+            Ok(Ok(respuesta)) => Err(ErrorCliente::Rechazo(respuesta)),
+            Ok(Err(error)) => Err(error),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                self.olvidar(id);
+                Err(ErrorCliente::Transporte(io::Error::new(ErrorKind::TimedOut,
+                    "El servidor no respondió a tiempo")))
+            },
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(ErrorCliente::Desconectado),
+        }
+    }
+
+    /// Deja de esperar la respuesta de la petición con el identificador.
+    fn olvidar(&self, id: IdPeticion) {
+        if let Some(ref mut pendientes) = *self.pendientes.lock().unwrap() {
+            pendientes.remove(&id);
+        }
+    }
+
+    /// Se identifica ante el servidor con un nombre de usuario.
+    pub fn identificar(&self, nombre: &str) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::Identify { nombre: nombre.to_owned() }).map(|_| ())
+    }
+
+    /// Cambia el estado del usuario.
+    pub fn estado(&self, estado: EstadoCliente) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::Status { estado }).map(|_| ())
+    }
+
+    /// Regresa los nombres de los usuarios identificados en el servidor.
+    pub fn usuarios(&self) -> Result<Vec<String>, ErrorCliente> {
+        let respuesta = self.ejecutar(Comando::Users)?;
+        Ok(respuesta.get_texto().split_whitespace().map(String::from).collect())
+    }
+
+    /// Envía un mensaje privado a un usuario.
+    pub fn mensaje_privado(&self, destinatario: &str, texto: &str) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::Message {
+            destinatario: destinatario.to_owned(),
+            texto: texto.to_owned(),
+        }).map(|_| ())
+    }
+
+    /// Envía un mensaje público a todos los usuarios.
+    pub fn mensaje_publico(&self, texto: &str) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::PublicMessage { texto: texto.to_owned() }).map(|_| ())
+    }
+
+    /// Crea una sala, de la que el usuario es propietario.
+    pub fn crear_sala(&self, sala: &str) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::CreateRoom { sala: sala.to_owned() }).map(|_| ())
+    }
+
+    /// Invita a unos usuarios a una sala de la que el usuario es propietario.
+    pub fn invitar(&self, sala: &str, usuarios: &[&str]) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::Invite {
+            sala: sala.to_owned(),
+            usuarios: usuarios.iter().map(|usuario| usuario.to_string()).collect(),
+        }).map(|_| ())
+    }
+
+    /// Se une a una sala a la que el usuario fue invitado.
+    pub fn unirse(&self, sala: &str) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::JoinRoom { sala: sala.to_owned() }).map(|_| ())
+    }
+
+    /// Envía un mensaje a una sala de la que el usuario es miembro.
+    pub fn mensaje_sala(&self, sala: &str, texto: &str) -> Result<(), ErrorCliente> {
+        self.ejecutar(Comando::RoomMessage {
+            sala: sala.to_owned(),
+            texto: texto.to_owned(),
+        }).map(|_| ())
+    }
+
+    /// Avisa al servidor que el cliente se desconecta y cierra la conexión. Los comandos
+    /// posteriores fallan, y el iterador de eventos termina después de entregar las
+    /// notificaciones pendientes.
+    pub fn desconectar(&self) -> Result<(), ErrorCliente> {
+        let mut escritor = self.escritor.lock().unwrap();
+        let linea = Formato::TEXT.formatear_peticion(None, &Comando::Disconnect);
+        let resultado = util::enviar_mensaje(&mut escritor.conexion, linea);
+        let _ = escritor.conexion.cerrar();
+        resultado.map_err(ErrorCliente::from)
+    }
+
+    /// Regresa un iterador bloqueante sobre las notificaciones recibidas del servidor, en
+    /// el orden en que llegaron. El iterador termina cuando la conexión termina. Sólo un
+    /// iterador puede existir a la vez.
+    pub fn eventos<'a>(&'a self) -> Eventos<'a> {
+        Eventos {
+            receptor: self.eventos.lock().unwrap(),
+        }
+    }
+}
+
+impl Drop for ClienteChat {
+    fn drop(&mut self) {
+        let _ = self.escritor.lock().unwrap().conexion.cerrar();
+    }
+}
+
+/// Iterador sobre las notificaciones que recibe un [`ClienteChat`](struct.ClienteChat.html).
+pub struct Eventos<'a> {
+    receptor: MutexGuard<'a, mpsc::Receiver<Notificacion>>,
+}
+
+impl<'a> Eventos<'a> {
+
+    /// Regresa la siguiente notificación, esperándola a lo más el plazo recibido.
+    pub fn siguiente_con_plazo(&mut self, plazo: Duration) -> Option<Notificacion> {
+        self.receptor.recv_timeout(plazo).ok()
+    }
+
+    /// Regresa la siguiente notificación si ya se recibió, sin esperar.
+    pub fn pendiente(&mut self) -> Option<Notificacion> {
+        self.receptor.try_recv().ok()
+    }
+}
+
+impl<'a> Iterator for Eventos<'a> {
+    type Item = Notificacion;
+
+    fn next(&mut self) -> Option<Notificacion> {
+        self.receptor.recv().ok()
+    }
+}
@@ -2,32 +2,429 @@
 
 #[macro_use]
 extern crate log;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate mio;
+extern crate futures;
+extern crate tokio;
+extern crate tokio_util;
 
+/// Módulo con un cliente del protocolo de chat, para aplicaciones, pruebas y scripts.
+pub mod cliente;
 /// Módulo para la creación de un servidor TCP.
 pub mod red;
 
 #[cfg(test)]
 mod tests {
-    use red::{servidor::Servidor, eventoservidor::EventoServidor, eventoconexion:: EventoConexion,
-            util};
+    use crate::red::{util, lector::{LectorMensajes, ErrorMensaje},
+            comando::{Comando, ErrorComando, Argumento},
+            estadocliente::EstadoCliente, respuesta::{Respuesta, CodigoRespuesta},
+            comando, notificacion::Notificacion, mensajeservidor::MensajeServidor,
+            formato::Formato, capacidad::Capacidad, cliente::Cliente, sala::Sala,
+            registro::{RegistroClientes, RegistroSalas}, colasalida::ColaSalida,
+            conexion::{self, Conexion}, error::ChatError, servidor::Servidor, escuchas::Escuchas,
+            eventoservidor::EventoServidor};
     use std::thread;
-    use std::net::{TcpStream};
+    use std::io::{self, Read, Write, ErrorKind};
+    use std::sync::mpsc;
+    use std::time::Duration;
 
     #[test]
+    #[allow(deprecated)]
     fn test_mensaje_de_buffer() {
         let mut buffer: [u8; 180] = [0; 180];
         assert_eq!("", util::mensaje_de_buffer(&buffer));
 
         let mut mensaje = String::new();
-        for i in 0..180 {
-            mensaje = mensaje + "a";
-            buffer[i] = b'a';
+        for byte in buffer.iter_mut() {
+            mensaje += "a";
+            *byte = b'a';
         }
         assert_eq!(mensaje, util::mensaje_de_buffer(&buffer));
 
-        for i in 0..180 {
-            buffer[i] = util::CHAR_NULL;
+        for byte in buffer.iter_mut() {
+            *byte = util::CHAR_NULL;
         }
         assert_eq!("", util::mensaje_de_buffer(&buffer));
+
+        buffer[0] = b'a';
+        buffer[1] = "ñ".as_bytes()[0];
+        assert_eq!("a\u{FFFD}", util::mensaje_de_buffer(&buffer));
+    }
+
+    #[test]
+    fn test_lector_separa_mensajes() {
+        let mut lector = LectorMensajes::new(&b"IDENTIFY cliente\r\n\nUSERS\n"[..]);
+        assert_eq!("IDENTIFY cliente", lector.siguiente_mensaje().unwrap());
+        assert_eq!("USERS", lector.siguiente_mensaje().unwrap());
+        assert_eq!(ErrorKind::ConnectionAborted, lector.siguiente_mensaje().unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_lector_reensambla_mensajes() {
+        let flujo = (&b"PUBLICMESSAGE ho"[..]).chain(&b"la\nUSE"[..]).chain(&b"RS\n"[..]);
+        let mut lector = LectorMensajes::new(flujo);
+        assert_eq!("PUBLICMESSAGE hola", lector.siguiente_mensaje().unwrap());
+        assert_eq!("USERS", lector.siguiente_mensaje().unwrap());
+    }
+
+    #[test]
+    fn test_lector_tamano_maximo() {
+        let flujo = (&b"PUBLICMESSAGE "[..]).chain(&b"mensaje largo\nUSERS\n"[..]);
+        let mut lector = LectorMensajes::con_tamano_maximo(flujo, 10);
+        assert_eq!(ErrorKind::InvalidData, lector.siguiente_mensaje().unwrap_err().kind());
+        assert_eq!("USERS", lector.siguiente_mensaje().unwrap());
+
+        let mut lector = LectorMensajes::con_tamano_maximo(&b"PUBLICMESSAGE hola\nUSERS\n"[..], 10);
+        assert_eq!(ErrorKind::InvalidData, lector.siguiente_mensaje().unwrap_err().kind());
+        assert_eq!("USERS", lector.siguiente_mensaje().unwrap());
+
+        let flujo = (&b"0123456789\r"[..]).chain(&b"\nUSERS\n"[..]);
+        let mut lector = LectorMensajes::con_tamano_maximo(flujo, 10);
+        assert_eq!("0123456789", lector.siguiente_mensaje().unwrap());
+        assert_eq!("USERS", lector.siguiente_mensaje().unwrap());
+    }
+
+    /// Flujo cuyas lecturas siempre agotan su plazo.
+    struct FlujoAgotado;
+
+    impl Read for FlujoAgotado {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(ErrorKind::TimedOut, "Se agotó el plazo de lectura"))
+        }
+    }
+
+    #[test]
+    fn test_lector_conserva_errores_del_flujo() {
+        let mut lector = LectorMensajes::new(FlujoAgotado);
+        assert_eq!(ErrorKind::TimedOut, lector.siguiente_mensaje().unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_lector_codificacion_invalida() {
+        let mut lector = LectorMensajes::new(&b"IDENTIFY \xC3\x28\nUSERS\n"[..]);
+        let error = lector.siguiente_mensaje().unwrap_err();
+        assert_eq!(Some(ErrorMensaje::CodificacionInvalida), ErrorMensaje::de_error(&error));
+        assert_eq!("USERS", lector.siguiente_mensaje().unwrap());
+    }
+
+    #[test]
+    fn test_analiza_comandos() {
+        assert_eq!(Ok(Comando::Identify { nombre: "cliente".to_string() }),
+            "IDENTIFY cliente".parse::<Comando>());
+        assert_eq!(Ok(Comando::Status { estado: EstadoCliente::AWAY }),
+            "STATUS AWAY".parse::<Comando>());
+        assert_eq!(Ok(Comando::Message { destinatario: "cliente".to_string(),
+            texto: "hola a todos".to_string() }),
+            "MESSAGE cliente hola a todos".parse::<Comando>());
+        assert_eq!(Ok(Comando::Invite { sala: "S1".to_string(),
+            usuarios: vec!["cliente1".to_string(), "cliente2".to_string()] }),
+            "INVITE S1 cliente1 cliente2".parse::<Comando>());
+        assert_eq!(Ok(Comando::Disconnect), "DISCONNECT".parse::<Comando>());
+    }
+
+    #[test]
+    fn test_mensajes_sin_argumento_final() {
+        assert_eq!(Ok(Comando::Message { destinatario: "bob".to_string(),
+            texto: r"C:\tmp\nuevo".to_string() }),
+            r"MESSAGE bob C:\tmp\nuevo".parse::<Comando>());
+        assert_eq!(Ok(Comando::PublicMessage { texto: r#"he said "hi"#.to_string() }),
+            r#"PUBLICMESSAGE he said "hi"#.parse::<Comando>());
+        assert_eq!(Ok(Comando::RoomMessage { sala: "sala 1".to_string(),
+            texto: r#"dijo  "hola\" y se fue"#.to_string() }),
+            r#"ROOMESSAGE "sala 1" dijo  "hola\" y se fue"#.parse::<Comando>());
+        assert_eq!(Ok(Comando::PublicMessage { texto: ":)".to_string() }),
+            "PUBLICMESSAGE ::)".parse::<Comando>());
+        assert_eq!(Err(ErrorComando::ComillasSinCerrar), r#"MESSAGE "bob hola"#.parse::<Comando>());
+    }
+
+    #[test]
+    fn test_errores_de_comandos() {
+        assert_eq!(Err(ErrorComando::EventoInvalido), "HOLA".parse::<Comando>());
+        assert_eq!(Err(ErrorComando::EventoInvalido), "INVALID".parse::<Comando>());
+        assert_eq!(Err(ErrorComando::FaltaArgumento(Argumento::Nombre)),
+            "IDENTIFY".parse::<Comando>());
+        assert_eq!(Err(ErrorComando::ArgumentoInvalido(Argumento::Nombre)),
+            "IDENTIFY un_nombre_demasiado_largo".parse::<Comando>());
+        assert_eq!(Err(ErrorComando::ArgumentoInvalido(Argumento::Estado)),
+            "STATUS DORMIDO".parse::<Comando>());
+        assert_eq!(Err(ErrorComando::FaltaArgumento(Argumento::Mensaje)),
+            "ROOMESSAGE S1".parse::<Comando>());
+        assert_eq!(Err(ErrorComando::FaltaArgumento(Argumento::Usuarios)),
+            "INVITE S1".parse::<Comando>());
+        assert_eq!(Err(ErrorComando::ArgumentoInvalido(Argumento::Mensaje)),
+            "PUBLICMESSAGE hola\u{1b}[2K".parse::<Comando>());
+        assert_eq!(Err(ErrorComando::ArgumentoInvalido(Argumento::Nombre)),
+            "IDENTIFY admin\u{202E}".parse::<Comando>());
+
+        let error = "INVITE S1 ana \"luis perez\"".parse::<Comando>().unwrap_err();
+        assert_eq!(ErrorComando::ArgumentoInvalido(Argumento::Usuarios), error);
+        assert_eq!("Los usuarios a invitar deben ser nombres sin espacios ni caracteres de control",
+            error.to_string());
+    }
+
+    #[test]
+    fn test_comando_a_cadena() {
+        let linea = "ROOMESSAGE S1 hola a todos";
+        assert_eq!(linea, linea.parse::<Comando>().unwrap().to_string());
+    }
+
+    #[test]
+    fn test_respuestas() {
+        let respuesta = Respuesta::new(CodigoRespuesta::ERR_NO_SUCH_ROOM, "La sala no existe");
+        assert_eq!("ERR_NO_SUCH_ROOM La sala no existe", respuesta.to_string());
+        assert_eq!(Ok(respuesta.clone()), "ERR_NO_SUCH_ROOM La sala no existe".parse::<Respuesta>());
+        let error = ChatError::SalaInexistente("S1".to_string());
+        assert_eq!(CodigoRespuesta::ERR_NO_SUCH_ROOM, Respuesta::from(&error).get_codigo());
+        assert!(!error.es_transporte());
+        let error = ChatError::from(io::Error::new(ErrorKind::BrokenPipe, "Conexión cerrada"));
+        assert_eq!(CodigoRespuesta::ERR_INTERNAL, Respuesta::from(&error).get_codigo());
+        assert!(error.es_transporte());
+        let error = ChatError::from(ErrorComando::FaltaArgumento(Argumento::Sala));
+        assert_eq!(CodigoRespuesta::ERR_MISSING_ARGUMENT, Respuesta::from(&error).get_codigo());
+        assert_eq!("No se especificó el nombre de la sala", error.to_string());
+        assert!(!error.es_transporte());
+        let error = ChatError::from(Comando::Identify { nombre: "dos palabras".to_string() }
+            .validar().unwrap_err());
+        assert_eq!(CodigoRespuesta::ERR_INVALID_ARGUMENT, error.get_codigo());
+        assert_eq!(Ok(Respuesta::ok("")), "OK".parse::<Respuesta>());
+        assert_eq!(Err(()), "Nombre cambiado a: cliente".parse::<Respuesta>());
+        assert_eq!(CodigoRespuesta::ERR_MISSING_ARGUMENT,
+            Respuesta::from(&ErrorComando::FaltaArgumento(Argumento::Sala)).get_codigo());
+    }
+
+    #[test]
+    fn test_identificadores_de_peticion() {
+        assert_eq!((Some(7), Ok(Comando::Users)), comando::analizar_peticion("#7 USERS"));
+        assert_eq!((None, Ok(Comando::Users)), comando::analizar_peticion("USERS"));
+        assert_eq!((None, Err(ErrorComando::ArgumentoInvalido(Argumento::Id))),
+            comando::analizar_peticion("#siete USERS"));
+        assert_eq!("#7 JOINROOM S1",
+            comando::formatear_peticion(7, &Comando::JoinRoom { sala: "S1".to_string() }));
+    }
+
+    #[test]
+    fn test_mensajes_del_servidor() {
+        let mut respuesta = Respuesta::ok("Nombre cambiado a: cliente");
+        respuesta.set_id(Some(3));
+        let linea = "REPLY 3 OK Nombre cambiado a: cliente";
+        assert_eq!(linea, MensajeServidor::Respuesta(respuesta.clone()).to_string());
+        assert_eq!(Ok(MensajeServidor::Respuesta(respuesta)), linea.parse::<MensajeServidor>());
+        assert_eq!("REPLY * OK", MensajeServidor::Respuesta(Respuesta::ok("")).to_string());
+
+        let notificacion = Notificacion::RoomMessage {
+            sala: "S1".to_string(),
+            remitente: "cliente".to_string(),
+            texto: "hola a todos".to_string(),
+        };
+        let linea = "EVENT ROOMESSAGE S1 cliente hola a todos";
+        assert_eq!(linea, MensajeServidor::Notificacion(notificacion.clone()).to_string());
+        assert_eq!(Ok(MensajeServidor::Notificacion(notificacion)), linea.parse::<MensajeServidor>());
+        let notificacion = Notificacion::Shutdown { texto: "El servidor se está deteniendo".to_string() };
+        let linea = "EVENT SHUTDOWN El servidor se está deteniendo";
+        assert_eq!(linea, MensajeServidor::Notificacion(notificacion.clone()).to_string());
+        assert_eq!(Ok(MensajeServidor::Notificacion(notificacion)), linea.parse::<MensajeServidor>());
+        assert_eq!(Err(()), "OK Nombre cambiado a: cliente".parse::<MensajeServidor>());
+    }
+
+    #[test]
+    fn test_formato_json() {
+        let linea = r#"{"id":4,"comando":"ROOMESSAGE","sala":"S1","texto":"hola a todos"}"#;
+        let comando = Comando::RoomMessage { sala: "S1".to_string(), texto: "hola a todos".to_string() };
+        assert_eq!((Some(4), Ok(comando.clone())), Formato::JSON.analizar_peticion(linea));
+        assert_eq!((Some(4), Ok(comando.clone())),
+            Formato::JSON.analizar_peticion(&Formato::JSON.formatear_peticion(Some(4), &comando)));
+        assert_eq!((None, Err(ErrorComando::EventoInvalido)),
+            Formato::JSON.analizar_peticion("ROOMESSAGE S1 hola"));
+        assert_eq!((None, Err(ErrorComando::ArgumentoInvalido(Argumento::Nombre))),
+            Formato::JSON.analizar_peticion(r#"{"comando":"IDENTIFY","nombre":"un cliente"}"#));
+
+        let mut respuesta = Respuesta::ok("Formato cambiado a: JSON");
+        respuesta.set_id(Some(1));
+        let mensaje = MensajeServidor::Respuesta(respuesta);
+        assert_eq!(r#"{"tipo":"REPLY","id":1,"codigo":"OK","texto":"Formato cambiado a: JSON"}"#,
+            Formato::JSON.formatear_mensaje(&mensaje));
+        let mensaje = MensajeServidor::Notificacion(Notificacion::JoinRoom {
+            sala: "S1".to_string(),
+            usuario: "cliente".to_string(),
+        });
+        assert_eq!(r#"{"tipo":"EVENT","evento":"JOINROOM","sala":"S1","usuario":"cliente"}"#,
+            Formato::JSON.formatear_mensaje(&mensaje));
+        assert_eq!(Ok(mensaje.clone()),
+            Formato::JSON.analizar_mensaje(&Formato::JSON.formatear_mensaje(&mensaje)));
+    }
+
+    #[test]
+    fn test_saludo() {
+        let capacidades = vec!["JSON".to_string(), "TLS".to_string(), "EMOJI".to_string()];
+        assert_eq!(Ok(Comando::Hello { version: 2, capacidades: capacidades.clone() }),
+            "HELLO 2 JSON TLS EMOJI".parse::<Comando>());
+        assert_eq!(Err(ErrorComando::ArgumentoInvalido(Argumento::Version)),
+            "HELLO 0 JSON".parse::<Comando>());
+        assert_eq!(vec![Capacidad::JSON], Capacidad::negociar(&capacidades));
+        assert_eq!((None, Ok(Comando::Hello { version: 1, capacidades: Vec::new() })),
+            Formato::JSON.analizar_peticion(r#"{"comando":"HELLO","version":1}"#));
+    }
+
+    #[test]
+    fn test_separar_argumentos() {
+        assert_eq!(Ok(vec!["MESSAGE".to_string(), "cliente".to_string(), "hola".to_string()]),
+            util::separar_argumentos("MESSAGE  cliente hola "));
+        assert_eq!(Ok(vec!["JOINROOM".to_string(), "sala \"1\"".to_string()]),
+            util::separar_argumentos(r#"JOINROOM "sala \"1\"""#));
+        assert_eq!(Ok(vec!["PUBLICMESSAGE".to_string(), " IDENTIFY  \"x\"".to_string()]),
+            util::separar_argumentos(r#"PUBLICMESSAGE : IDENTIFY  "x""#));
+        assert_eq!(Err(ErrorComando::ComillasSinCerrar), util::separar_argumentos(r#"JOINROOM "sala"#));
+
+        let comando = Comando::RoomMessage { sala: "sala 1".to_string(), texto: "hola  :)".to_string() };
+        assert_eq!(r#"ROOMESSAGE "sala 1" :hola  :)"#, comando.to_string());
+        assert_eq!(Ok(comando.clone()), comando.to_string().parse::<Comando>());
+    }
+
+    #[test]
+    fn test_tuberia() {
+        let (mut cliente, servidor) = conexion::tuberia();
+        let mut copia = servidor.duplicar().unwrap();
+        util::enviar_mensaje(&mut cliente, "hola".to_string()).unwrap();
+        assert_eq!("hola", util::obtener_mensaje_conexion(&mut copia).unwrap());
+
+        drop(servidor);
+        util::enviar_mensaje(&mut copia, "adiós".to_string()).unwrap();
+        assert_eq!("adiós", util::obtener_mensaje_conexion(&mut cliente).unwrap());
+
+        drop(copia);
+        let mut buffer = [0; 8];
+        assert_eq!(0, cliente.read(&mut buffer).unwrap());
+        assert_eq!(ErrorKind::BrokenPipe, cliente.write(b"x").unwrap_err().kind());
+
+        let (mut lleno, _otro) = conexion::tuberia();
+        lleno.set_plazo_escritura(Some(Duration::from_millis(50))).unwrap();
+        let datos = vec![0; conexion::CAPACIDAD_TUBERIA + 1];
+        assert_eq!(ErrorKind::TimedOut, lleno.write_all(&datos).unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_registros() {
+        let mut clientes = RegistroClientes::new();
+        for id in 0..2 {
+            clientes.agregar(Cliente::new(id, None, Box::new(conexion::tuberia().0)).unwrap());
+        }
+        assert!(clientes.asignar_nombre(1, "cliente"));
+        assert!(!clientes.asignar_nombre(0, "cliente"));
+        assert!(clientes.asignar_nombre(0, "otro"));
+        assert_eq!(vec!["otro".to_string(), "cliente".to_string()], clientes.nombres());
+        clientes.eliminar(1);
+        assert_eq!(None, clientes.id_de("cliente"));
+        assert!(clientes.asignar_nombre(0, "cliente"));
+        assert_eq!(None, clientes.id_de("otro"));
+
+        let mut salas = RegistroSalas::new();
+        assert!(salas.crear(Sala::new("S1", 0)));
+        assert!(!salas.crear(Sala::new("S1", 1)));
+        assert!(salas.obtener("S1").unwrap().es_propietario(0));
+
+        let sala = salas.obtener_mut("S1").unwrap();
+        sala.invitar_miembro(1);
+        sala.agregar_miembro(2);
+        assert!(sala.cliente_es_miembro(2));
+        salas.eliminar_cliente(2);
+        let sala = salas.obtener("S1").unwrap();
+        assert!(sala.es_propietario(0));
+        assert!(sala.cliente_es_invitado(1));
+        assert!(!sala.cliente_es_miembro(2));
+
+        let sala = salas.obtener_mut("S1").unwrap();
+        sala.agregar_miembro(3);
+        sala.agregar_miembro(1);
+        salas.eliminar_cliente(0);
+        assert!(salas.obtener("S1").unwrap().es_propietario(1));
+        salas.eliminar_cliente(1);
+        assert!(salas.obtener("S1").unwrap().es_propietario(3));
+        salas.eliminar_cliente(3);
+        assert!(salas.obtener("S1").is_none());
+        assert!(salas.is_empty());
+    }
+
+    #[test]
+    fn test_igualdad_clientes() {
+        let cliente = |id| Cliente::new(id, None, Box::new(conexion::tuberia().0)).unwrap();
+        assert!(cliente(0) != cliente(1));
+        assert!(cliente(0) == cliente(0));
+        let uno = cliente(1);
+        assert!(uno.clone() == uno);
+
+        let mut clientes = RegistroClientes::new();
+        let mut salas = RegistroSalas::new();
+        let mut extremos = Vec::new();
+        for id in 0..3 {
+            let (servidor, extremo) = conexion::tuberia();
+            clientes.agregar(Cliente::new(id, None, Box::new(servidor)).unwrap());
+            extremos.push(extremo);
+        }
+        salas.crear(Sala::new("S1", 0));
+        salas.obtener_mut("S1").unwrap().agregar_miembro(1);
+        salas.obtener_mut("S1").unwrap().agregar_miembro(2);
+        let escuchas = Escuchas::new();
+        let eventos = escuchas.suscribir();
+
+        let desconectado = clientes.obtener(1).unwrap().clone();
+        Servidor::desconectar_cliente(&desconectado, &mut clientes, &mut salas, &escuchas);
+        assert_eq!(Ok(EventoServidor::ClienteDesconectado { id: 1, nombre: None }), eventos.try_recv());
+        assert_eq!(2, clientes.len());
+        assert!(clientes.obtener(0).is_some());
+        assert!(clientes.obtener(1).is_none());
+        assert!(clientes.obtener(2).is_some());
+        let sala = salas.obtener("S1").unwrap();
+        assert!(!sala.cliente_es_miembro(1));
+        assert!(sala.cliente_es_miembro(2));
+
+        let mut buffer = [0; 8];
+        assert_eq!(0, extremos[1].read(&mut buffer).unwrap());
+        assert!(extremos[0].write(b"x").is_ok());
+        assert!(extremos[2].write(b"x").is_ok());
+    }
+
+    /// Escritor que se bloquea hasta que se elimina el emisor de su canal, simulando un
+    /// cliente que dejó de leer.
+    struct EscritorBloqueado(mpsc::Receiver<()>);
+
+    impl Write for EscritorBloqueado {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            let _ = self.0.recv();
+            Err(io::Error::new(ErrorKind::BrokenPipe, "Escritor cerrado"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_cola_salida() {
+        let (desbloquear, bloqueo) = mpsc::channel::<()>();
+        let cola = ColaSalida::new(EscritorBloqueado(bloqueo), 1, Duration::from_millis(100));
+        assert!(cola.encolar("primero").is_ok());
+        thread::sleep(Duration::from_millis(50));
+        assert!(cola.encolar("segundo").is_ok());
+        assert_eq!(ErrorKind::WouldBlock, cola.encolar("tercero").unwrap_err().kind());
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(ErrorKind::TimedOut, cola.encolar("cuarto").unwrap_err().kind());
+
+        drop(desbloquear);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(ErrorKind::BrokenPipe, cola.encolar("quinto").unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_cola_salida_con_aviso() {
+        let (aviso, avisos) = mpsc::channel();
+        let (mut cola, receptor) = ColaSalida::con_receptor(1, Duration::from_millis(100));
+        cola.set_aviso(7, aviso);
+        assert!(cola.encolar("primero").is_ok());
+        assert_eq!(ErrorKind::WouldBlock, cola.clone().encolar("segundo").unwrap_err().kind());
+        assert_eq!(vec![7], avisos.try_iter().collect::<Vec<_>>());
+        assert_eq!(b"primero\n".to_vec(), receptor.recv().unwrap());
     }
 }
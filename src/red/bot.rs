@@ -0,0 +1,175 @@
+use super::{cliente::{Cliente, IdCliente}, colasalida::ColaSalida, comando::Comando,
+    escuchas::Escuchas, filtro::Filtros, mensajeservidor::MensajeServidor,
+    notificacion::Notificacion, registro::{RegistroClientes, RegistroSalas}, servidor::Servidor};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+/// Número máximo de veces que el servidor entrega a los bots los mensajes que provocó un
+/// comando, incluidos los que provocan los comandos de los propios bots. Evita que dos bots
+/// que se responden entre sí bloqueen al servidor.
+pub const RONDAS_MAXIMAS: usize = 8;
+
+/// Participante del chat que vive dentro del servidor, sin conexión. Cada bot se registra
+/// como un [`Cliente`](../cliente/struct.Cliente.html) virtual con su nombre, por lo que
+/// aparece en `USERS`, puede ser invitado a salas y recibir mensajes privados como
+/// cualquier otro usuario.
+/// Cada método recibe una notificación dirigida al bot y regresa los comandos con los que
+/// el bot reacciona, los cuales el servidor realiza en su nombre; por omisión el bot no
+/// reacciona. Los métodos se llaman mientras el servidor procesa el comando que provocó la
+/// notificación, por lo que no deben bloquear.
+pub trait Bot: Send {
+
+    /// Regresa el nombre con el que el bot se identifica.
+    fn get_nombre(&self) -> &str;
+
+    /// Reacciona a un mensaje privado.
+    fn on_message(&mut self, _remitente: &str, _texto: &str) -> Vec<Comando> {
+        Vec::new()
+    }
+
+    /// Reacciona a una invitación para unirse a una sala.
+    fn on_invite(&mut self, _sala: &str, _anfitrion: &str) -> Vec<Comando> {
+        Vec::new()
+    }
+
+    /// Reacciona a la llegada de un usuario a una sala de la que el bot es miembro.
+    fn on_join(&mut self, _sala: &str, _usuario: &str) -> Vec<Comando> {
+        Vec::new()
+    }
+
+    /// Reacciona a un mensaje enviado a una sala de la que el bot es miembro.
+    fn on_room_message(&mut self, _sala: &str, _remitente: &str, _texto: &str) -> Vec<Comando> {
+        Vec::new()
+    }
+}
+
+/// Bot agregado a un servidor, junto con su cliente virtual y el receptor de su cola de
+/// salida una vez que el servidor lo conecta.
+struct BotRegistrado {
+    bot: Box<dyn Bot>,
+    conexion: Option<(Cliente, mpsc::Receiver<MensajeServidor>)>,
+}
+
+/// Lista de bots de un servidor. Las copias de la lista comparten los mismos bots.
+#[derive(Clone)]
+pub struct Bots {
+    bots: Arc<Mutex<Vec<BotRegistrado>>>,
+}
+
+impl Default for Bots {
+    fn default() -> Bots {
+        Bots::new()
+    }
+}
+
+impl Bots {
+
+    /// Crea una lista de bots vacía.
+    pub fn new() -> Bots {
+        Bots {
+            bots: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Agrega un bot a la lista. El bot participa en el chat a partir de que se conecta,
+    /// al iniciar el servidor.
+    pub fn agregar<B: Bot + 'static>(&self, bot: B) {
+        self.bots.lock().unwrap().push(BotRegistrado {
+            bot: Box::new(bot),
+            conexion: None,
+        });
+    }
+
+    /// Conecta los bots que aún no se conectan: crea un cliente virtual para cada uno, con un
+    /// identificador a partir de `siguiente_id` y una cola de salida con la capacidad y el
+    /// plazo de saturación recibidos, y lo agrega al registro de clientes con el nombre del
+    /// bot. Los bots cuyo nombre ya está en uso se descartan.
+    pub fn conectar(&self, clientes: &mut RegistroClientes, siguiente_id: &mut IdCliente,
+        capacidad: usize, plazo_saturacion: Duration) {
+        let mut bots = self.bots.lock().unwrap();
+        bots.retain(|registrado| registrado.conexion.is_some() ||
+            clientes.id_de(registrado.bot.get_nombre()).is_none());
+        for registrado in bots.iter_mut().filter(|registrado| registrado.conexion.is_none()) {
+            let id = *siguiente_id;
+            *siguiente_id += 1;
+            let (cola, receptor) = ColaSalida::de_mensajes(capacidad, plazo_saturacion);
+            let cliente = Cliente::sin_conexion(id, None, cola);
+            clientes.agregar(cliente.clone());
+            clientes.asignar_nombre(id, registrado.bot.get_nombre());
+            info!(target: "Servidor", "Bot {} conectado como el cliente {}",
+                registrado.bot.get_nombre(), id);
+            registrado.conexion = Some((cliente, receptor));
+        }
+    }
+
+    /// Entrega a cada bot las notificaciones pendientes en su cola de salida, sin formatear,
+    /// y realiza los comandos con los que reacciona, con
+    /// [`Servidor::ejecutar_comando`](../servidor/struct.Servidor.html#method.ejecutar_comando).
+    /// Repite mientras los bots reciban mensajes, a lo más
+    /// [`RONDAS_MAXIMAS`](constant.RONDAS_MAXIMAS.html) veces. Las respuestas a los comandos
+    /// de los bots se descartan. Un bot que envía `DISCONNECT`, o cuyo comando falla por su
+    /// cola de salida, se elimina del registro de clientes y no se realizan sus comandos
+    /// restantes ni recibe más mensajes.
+    pub fn atender(&self, clientes: &mut RegistroClientes, salas: &mut RegistroSalas,
+        escuchas: &Escuchas, filtros: &Filtros) {
+        let mut bots = self.bots.lock().unwrap();
+        for _ in 0..RONDAS_MAXIMAS {
+            let mut recibieron = false;
+            for registrado in bots.iter_mut() {
+                let mut desconectar = false;
+                if let Some((ref mut cliente, ref receptor)) = registrado.conexion {
+                    while let Ok(mensaje) = receptor.try_recv() {
+                        recibieron = true;
+                        let comandos = match mensaje {
+                            MensajeServidor::Notificacion(notificacion) =>
+                                Bots::reaccionar(&mut *registrado.bot, notificacion),
+                            _ => continue,
+                        };
+                        for comando in comandos {
+                            if Servidor::ejecutar_comando(cliente, clientes, salas, escuchas, filtros,
+                                None, comando).is_err() {
+                                desconectar = true;
+                                break;
+                            }
+                        }
+                        if desconectar {
+                            info!(target: "Servidor", "El bot {} se desconectó",
+                                registrado.bot.get_nombre());
+                            Servidor::desconectar_cliente(cliente, clientes, salas, escuchas);
+                            break;
+                        }
+                    }
+                }
+                if desconectar {
+                    registrado.conexion = None;
+                }
+            }
+            if !recibieron {
+                return;
+            }
+        }
+        warn!(target: "Servidor", "Los bots excedieron el número máximo de rondas");
+    }
+
+    /// Llama al método del bot correspondiente a una notificación, regresando sus comandos.
+    fn reaccionar(bot: &mut dyn Bot, notificacion: Notificacion) -> Vec<Comando> {
+        match notificacion {
+            Notificacion::Message { remitente, texto } => bot.on_message(&remitente, &texto),
+            Notificacion::Invite { sala, anfitrion } => bot.on_invite(&sala, &anfitrion),
+            Notificacion::JoinRoom { sala, usuario } => bot.on_join(&sala, &usuario),
+            Notificacion::RoomMessage { sala, remitente, texto } =>
+                bot.on_room_message(&sala, &remitente, &texto),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Regresa el número de bots.
+    pub fn len(&self) -> usize {
+        self.bots.lock().unwrap().len()
+    }
+
+    /// Determina si la lista no tiene bots.
+    pub fn is_empty(&self) -> bool {
+        self.bots.lock().unwrap().is_empty()
+    }
+}
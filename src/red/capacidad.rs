@@ -0,0 +1,80 @@
+use std::str::FromStr;
+use std::fmt;
+
+/// Versión más reciente del protocolo que implementa el servidor.
+pub const VERSION_PROTOCOLO: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Capacidades opcionales del protocolo que un cliente puede solicitar con el comando
+/// `HELLO`. El servidor responde con las capacidades que acepta y las registra en el
+/// [`Cliente`](../cliente/struct.Cliente.html); las respuestas que recibe el cliente
+/// dependen de ellas, por lo que los clientes que no realizan el saludo siguen recibiendo
+/// las respuestas de texto plano de la primera versión del protocolo.
+pub enum Capacidad {
+    /// Respuestas de la forma `REPLY <id> <código> <texto>`, con códigos estables. Sin
+    /// ella, las respuestas en texto son sólo el texto legible.
+    CODES,
+    /// Identificadores de petición repetidos en las respuestas. Sin ella, las respuestas
+    /// en texto no llevan el identificador que el cliente adjuntó al comando.
+    IDS,
+    /// Mensajes en formato JSON, cuyas respuestas siempre llevan su código y el
+    /// identificador de petición, aún sin `CODES` ni `IDS`.
+    JSON,
+    /// Historial de los mensajes de las salas.
+    HISTORY,
+    /// Conexiones cifradas con TLS.
+    TLS,
+}
+
+impl Capacidad {
+
+    /// Determina si el servidor implementa la capacidad.
+    pub fn es_soportada(&self) -> bool {
+        match *self {
+            Capacidad::CODES | Capacidad::IDS | Capacidad::JSON => true,
+            Capacidad::HISTORY | Capacidad::TLS => false,
+        }
+    }
+
+    /// Regresa las capacidades de los clientes que no realizan el saludo: ninguna, pues
+    /// corresponden a la primera versión del protocolo.
+    pub fn por_omision() -> Vec<Capacidad> {
+        Vec::new()
+    }
+
+    /// Regresa las capacidades que el servidor acepta de entre las solicitadas. Las
+    /// capacidades desconocidas o no soportadas se ignoran, para que los clientes puedan
+    /// solicitar capacidades de versiones más recientes del protocolo.
+    pub fn negociar(solicitadas: &[String]) -> Vec<Capacidad> {
+        let mut aceptadas = Vec::new();
+        for solicitada in solicitadas.iter() {
+            if let Ok(capacidad) = solicitada.parse::<Capacidad>() {
+                if capacidad.es_soportada() && !aceptadas.contains(&capacidad) {
+                    aceptadas.push(capacidad);
+                }
+            }
+        }
+        aceptadas
+    }
+}
+
+impl FromStr for Capacidad {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "CODES" => Ok(Capacidad::CODES),
+            "IDS" => Ok(Capacidad::IDS),
+            "JSON" => Ok(Capacidad::JSON),
+            "HISTORY" => Ok(Capacidad::HISTORY),
+            "TLS" => Ok(Capacidad::TLS),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Capacidad {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
@@ -1,33 +1,101 @@
-use std::net::{TcpStream, SocketAddr, Shutdown};
-use red::estadocliente::EstadoCliente;
-use std::io::{Error, Write};
+use crate::red::{conexion::Conexion, estadocliente::EstadoCliente, formato::Formato,
+    mensajeservidor::MensajeServidor, capacidad::{self, Capacidad}, colasalida::{self, ColaSalida}};
+use std::io::{Error, ErrorKind, Write};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Identificador único que el servidor asigna a cada conexión aceptada.
+pub type IdCliente = u64;
 
 /// Representación abstracta de los clientes conectados al servidor.
-/// Los clientes tienen un nombre asociado único, un socket de comunicación
-/// [`TcpStream`](https://doc.rust-lang.org/std/net/struct.TcpStream.html),
-/// una dirección IP [`SocketAddr`](https://doc.rust-lang.org/std/net/struct.SocketAddr.html)
-/// y un estado [`EstadoCliente`](../estadocliente/enum.EstadoCliente.html).
+/// Los clientes tienen un identificador único asignado por el servidor, con el que se
+/// distinguen entre sí, un nombre asociado único, una [`Conexion`](../conexion/trait.Conexion.html)
+/// de comunicación (salvo los clientes virtuales, como los [`Bot`](../bot/trait.Bot.html)s,
+/// que no tienen conexión), la dirección del otro extremo de la conexión, un estado [`EstadoCliente`](../estadocliente/enum.EstadoCliente.html),
+/// el [`Formato`](../formato/enum.Formato.html) de los mensajes de su conexión, y la versión
+/// del protocolo y las [`Capacidad`](../capacidad/enum.Capacidad.html)es acordadas con él.
+/// Los mensajes hacia el cliente pasan por su [`ColaSalida`](../colasalida/struct.ColaSalida.html),
+/// compartida por todas las copias del cliente, al igual que su conexión.
+#[derive(Clone)]
 pub struct Cliente {
+    id: IdCliente,
     nombre: Option<String>,
-    socket: TcpStream,
-    direccion: SocketAddr,
+    conexion: Option<Arc<dyn Conexion>>,
+    direccion: String,
     estado: EstadoCliente,
+    formato: Formato,
+    version: u32,
+    capacidades: Vec<Capacidad>,
+    cola: ColaSalida,
 }
 
 impl Cliente {
 
-    /// Crea una nueva instancia de un cliente, con un socket y dirección IP.
+    /// Crea una nueva instancia de un cliente, con un identificador y una conexión, de la
+    /// que obtiene su dirección.
     /// El estado por omisión de todos los clientes es
-    /// [`ACTIVE`](../estadocliente/enum.EstadoCliente.html#variant.ACTIVE).
-    pub fn new(nombre: Option<String>, socket: TcpStream, direccion: SocketAddr) -> Cliente {
+    /// [`ACTIVE`](../estadocliente/enum.EstadoCliente.html#variant.ACTIVE), su formato
+    /// por omisión es [`TEXT`](../formato/enum.Formato.html#variant.TEXT), y su protocolo
+    /// el de los clientes que no realizan el saludo. Su cola de salida tiene la capacidad y
+    /// el plazo de saturación por omisión.
+    /// Regresa un error si no se pudo duplicar la conexión para la cola de salida.
+    pub fn new(id: IdCliente, nombre: Option<String>, conexion: Box<dyn Conexion>)
+        -> Result<Cliente, Error> {
+        Cliente::con_cola(id, nombre, conexion, colasalida::CAPACIDAD_POR_OMISION,
+            Duration::from_millis(colasalida::PLAZO_SATURACION_POR_OMISION))
+    }
+
+    /// Crea una nueva instancia de un cliente cuya cola de salida admite a lo más
+    /// `capacidad` mensajes pendientes, y que se desconecta si su cola permanece llena por
+    /// más de `plazo_saturacion`, o si su conexión no acepta datos durante ese plazo
+    /// aunque no se le envíen más mensajes. La cola se vacía en un hilo escritor propio,
+    /// como se describe en [`ColaSalida::new`](../colasalida/struct.ColaSalida.html#method.new).
+    /// Regresa un error si no se pudo duplicar la conexión para la cola de salida o
+    /// definir su plazo de escritura.
+    pub fn con_cola(id: IdCliente, nombre: Option<String>, conexion: Box<dyn Conexion>,
+        capacidad: usize, plazo_saturacion: Duration) -> Result<Cliente, Error> {
+        let escritor = conexion.duplicar()?;
+        let plazo_escritura = Some(plazo_saturacion).filter(|plazo| *plazo > Duration::from_secs(0));
+        escritor.set_plazo_escritura(plazo_escritura)?;
+        let cola = ColaSalida::new(EscritorCliente(escritor), capacidad, plazo_saturacion);
+        Ok(Cliente::con_cola_salida(id, nombre, conexion, cola))
+    }
+
+    /// Crea una nueva instancia de un cliente virtual, sin conexión, cuyos mensajes se
+    /// agregan a la cola de salida recibida. Su dirección es `virtual`.
+    pub fn sin_conexion(id: IdCliente, nombre: Option<String>, cola: ColaSalida) -> Cliente {
+        Cliente::crear(id, nombre, None, String::from("virtual"), cola)
+    }
+
+    /// Crea una nueva instancia de un cliente cuyos mensajes se agregan a la cola de salida
+    /// recibida, la cual es responsable de escribirlos en la conexión.
+    pub fn con_cola_salida(id: IdCliente, nombre: Option<String>, conexion: Box<dyn Conexion>,
+        cola: ColaSalida) -> Cliente {
+        let direccion = conexion.direccion_remota();
+        Cliente::crear(id, nombre, Some(Arc::from(conexion)), direccion, cola)
+    }
+
+    /// Crea una nueva instancia de un cliente con todos sus campos.
+    fn crear(id: IdCliente, nombre: Option<String>, conexion: Option<Arc<dyn Conexion>>,
+        direccion: String, cola: ColaSalida) -> Cliente {
         Cliente {
-            nombre: nombre,
-            socket: socket,
-            direccion: direccion,
-            estado: EstadoCliente::ACTIVE
+            id,
+            nombre,
+            conexion,
+            direccion,
+            estado: EstadoCliente::ACTIVE,
+            formato: Formato::TEXT,
+            version: capacidad::VERSION_PROTOCOLO,
+            capacidades: Capacidad::por_omision(),
+            cola,
         }
     }
 
+    /// Regresa el identificador del cliente.
+    pub fn get_id(&self) -> IdCliente {
+        self.id
+    }
+
     /// Regresa el nombre del cliente, el cual puede no estar definido.
     pub fn get_nombre(&self) -> &Option<String> {
         &self.nombre
@@ -38,24 +106,29 @@ impl Cliente {
         self.nombre = Some(nuevo_nombre.to_owned());
     }
 
-    /// Regresa el socket de comunicación.
-    pub fn get_socket(&self) -> &TcpStream {
-        &self.socket
+    /// Regresa la conexión de comunicación, si el cliente la tiene.
+    pub fn get_conexion(&self) -> Option<&dyn Conexion> {
+        self.conexion.as_deref()
     }
 
-    /// Define el socket de comunicación.
-    pub fn set_socket(&mut self, socket: TcpStream) {
-        self.socket = socket;
+    /// Define la conexión de comunicación.
+    pub fn set_conexion(&mut self, conexion: Box<dyn Conexion>) {
+        self.conexion = Some(Arc::from(conexion));
     }
 
-    /// Regresa la dirección IP del cliente.
-    pub fn get_direccion(&self) -> SocketAddr {
-        self.direccion
+    /// Determina si el cliente es virtual, es decir, si no tiene conexión.
+    pub fn es_virtual(&self) -> bool {
+        self.conexion.is_none()
     }
 
-    /// Define la dirección IP del cliente.
-    pub fn set_direccion(&mut self, direccion: SocketAddr) {
-        self.direccion = direccion
+    /// Regresa la dirección del cliente.
+    pub fn get_direccion(&self) -> &str {
+        &self.direccion
+    }
+
+    /// Define la dirección del cliente.
+    pub fn set_direccion(&mut self, direccion: &str) {
+        self.direccion = direccion.to_owned()
     }
 
     /// Regresa el estado del cliente.
@@ -68,40 +141,127 @@ impl Cliente {
         self.estado = estado;
     }
 
-    /// Permite enviar un mensaje a través de la conexión.
+    /// Regresa el formato de los mensajes de la conexión.
+    pub fn get_formato(&self) -> Formato {
+        self.formato
+    }
+
+    /// Define el formato de los mensajes de la conexión.
+    pub fn set_formato(&mut self, formato: Formato) {
+        self.formato = formato;
+    }
+
+    /// Regresa la versión del protocolo acordada con el cliente.
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    /// Define la versión del protocolo acordada con el cliente.
+    pub fn set_version(&mut self, version: u32) {
+        self.version = version;
+    }
+
+    /// Regresa las capacidades acordadas con el cliente.
+    pub fn get_capacidades(&self) -> &[Capacidad] {
+        &self.capacidades[..]
+    }
+
+    /// Define las capacidades acordadas con el cliente.
+    pub fn set_capacidades(&mut self, capacidades: Vec<Capacidad>) {
+        self.capacidades = capacidades;
+    }
+
+    /// Determina si el cliente acordó una capacidad.
+    pub fn tiene_capacidad(&self, capacidad: Capacidad) -> bool {
+        self.capacidades.contains(&capacidad)
+    }
+
+    /// Permite enviar un mensaje a través de la conexión, terminado en un salto de línea.
+    /// El mensaje se agrega a la cola de salida del cliente sin esperar a que se escriba.
+    /// Si la cola está llena, el mensaje se descarta y se regresa un error de tipo
+    /// [`WouldBlock`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.WouldBlock);
+    /// con cualquier otro error, como exceder el plazo de saturación, la conexión se cierra.
     pub fn enviar_mensaje(&mut self, mensaje: &str) -> Result<(), Error> {
-        let mensaje = mensaje.as_bytes();
-        self.socket.write(&mensaje[..])?;
-        self.socket.flush()?;
-        Ok(())
+        let resultado = self.cola.encolar(mensaje);
+        self.verificar_envio(resultado)
     }
 
-    /// Provoca que el socket de comunicación se cierre. Eso no implica que el
-    /// cliente ya no esté en memoria.
-    pub fn detener(&mut self) {
-        self.socket.shutdown(Shutdown::Both).expect("Error al cerrar el socket");
+    /// Envía una respuesta o una notificación a través de la conexión, en el formato
+    /// de la misma. Los clientes de texto que no acordaron la capacidad
+    /// [`CODES`](../capacidad/enum.Capacidad.html#variant.CODES), como los que no realizan
+    /// el saludo, reciben su [`texto_plano`](../mensajeservidor/enum.MensajeServidor.html#method.texto_plano).
+    /// Los errores son los mismos que los de [`enviar_mensaje`](#method.enviar_mensaje).
+    pub fn enviar(&mut self, mensaje: &MensajeServidor) -> Result<(), Error> {
+        let resultado = if self.recibe_texto_plano() {
+            self.cola.encolar(&mensaje.texto_plano())
+        } else {
+            self.cola.encolar_mensaje(mensaje, self.formato)
+        };
+        self.verificar_envio(resultado)
     }
-}
 
-impl Clone for Cliente {
-     fn clone(&self) -> Self {
-        Cliente {
-            nombre: self.nombre.clone(),
-            socket: self.socket.try_clone().expect("Error al clonar"),
-            direccion: self.direccion.clone(),
-            estado: self.estado.clone()
+    /// Determina si el cliente recibe los mensajes del servidor como texto plano. Los
+    /// clientes virtuales siempre los reciben sin formatear.
+    fn recibe_texto_plano(&self) -> bool {
+        !self.es_virtual() && self.formato == Formato::TEXT && !self.tiene_capacidad(Capacidad::CODES)
+    }
+
+    /// Cierra la conexión si el envío falló por una razón distinta a que la cola esté llena.
+    fn verificar_envio(&mut self, resultado: Result<(), Error>) -> Result<(), Error> {
+        if let Err(ref error) = resultado {
+            if error.kind() != ErrorKind::WouldBlock {
+                self.detener();
+            }
         }
+        resultado
     }
-}
 
+    /// Provoca que la conexión de comunicación se cierre. Eso no implica que el
+    /// cliente ya no esté en memoria. Cerrar una conexión ya cerrada, o detener a un cliente
+    /// virtual, no tiene efecto.
+    pub fn detener(&mut self) {
+        if let Some(ref conexion) = self.conexion {
+            if let Err(error) = conexion.cerrar() {
+                debug!(target: "Cliente", "La conexión del cliente {} ya estaba cerrada: {}", self.id, error);
+            }
+        }
+    }
+}
 
 impl PartialEq for Cliente {
 
+    /// Dos clientes son iguales si tienen el mismo identificador.
     fn eq(&self, other: &Cliente) -> bool {
-        self.direccion == other.direccion || self.nombre == other.nombre
+        self.id == other.id
+    }
+}
+
+/// Escritor de la cola de salida de un cliente. Si una escritura falla, como cuando se
+/// agota el plazo de escritura porque el cliente dejó de leer, cierra la conexión para que
+/// el hilo que atiende al cliente lo desconecte.
+struct EscritorCliente(Box<dyn Conexion>);
+
+impl Write for EscritorCliente {
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, Error> {
+        let resultado = self.0.write(buffer);
+        self.verificar(resultado)
     }
 
-    fn ne(&self, other: &Cliente) -> bool {
-        self.direccion != other.direccion && self.nombre != other.nombre
+    fn flush(&mut self) -> Result<(), Error> {
+        let resultado = self.0.flush();
+        self.verificar(resultado)
+    }
+}
+
+impl EscritorCliente {
+
+    /// Cierra la conexión si la operación falló por una razón distinta a una interrupción.
+    fn verificar<T>(&self, resultado: Result<T, Error>) -> Result<T, Error> {
+        if let Err(ref error) = resultado {
+            if error.kind() != ErrorKind::Interrupted {
+                let _ = self.0.cerrar();
+            }
+        }
+        resultado
     }
 }
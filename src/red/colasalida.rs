@@ -0,0 +1,157 @@
+use super::{cliente::IdCliente, formato::Formato, mensajeservidor::MensajeServidor, util};
+use std::io::{Error, ErrorKind, Write};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::thread;
+
+/// Número de mensajes por omisión que puede acumular la cola de un cliente.
+pub const CAPACIDAD_POR_OMISION: usize = 256;
+
+/// Tiempo por omisión (en milisegundos) que la cola de un cliente puede permanecer llena
+/// antes de que el cliente se considere caído.
+pub const PLAZO_SATURACION_POR_OMISION: u64 = 5000;
+
+/// Cola acotada de mensajes salientes de una conexión, vaciada por un hilo escritor propio.
+/// Encolar un mensaje nunca bloquea, por lo que un cliente lento o caído no detiene a quien
+/// le envía mensajes. Si la cola está llena el mensaje se descarta, y si permanece llena por
+/// más del plazo de saturación, la cola reporta que el cliente debe desconectarse.
+/// Las copias de la cola comparten el mismo hilo escritor, el cual termina cuando se
+/// eliminan todas las copias o falla una escritura; para que falle aunque no se encolen más
+/// mensajes, el escritor debe tener un plazo de escritura, como el que le da
+/// [`Cliente::con_cola`](../cliente/struct.Cliente.html#method.con_cola).
+/// La cola también puede crearse sin hilo escritor, para que quien tenga su receptor
+/// escriba los mensajes, o para que lo consuma sin formatearlos, como los
+/// [`Bot`](../bot/trait.Bot.html)s. En ese caso puede definirse un aviso, por el que la
+/// cola envía el identificador de su cliente cada vez que recibe un mensaje, para que quien
+/// la consume sepa qué colas tienen mensajes sin revisarlas todas.
+#[derive(Clone)]
+pub struct ColaSalida {
+    emisor: Emisor,
+    saturada_desde: Arc<Mutex<Option<Instant>>>,
+    plazo_saturacion: Duration,
+    aviso: Option<(IdCliente, mpsc::Sender<IdCliente>)>,
+}
+
+/// Extremo de envío de una cola, según lo que recibe quien la consume.
+#[derive(Clone)]
+enum Emisor {
+    /// Los mensajes formateados y terminados en un salto de línea.
+    Bytes(mpsc::SyncSender<Vec<u8>>),
+    /// Los mensajes del servidor, sin formatear.
+    Mensajes(mpsc::SyncSender<MensajeServidor>),
+}
+
+impl ColaSalida {
+
+    /// Crea una nueva cola con la capacidad (en mensajes) y el plazo de saturación
+    /// recibidos, y lanza el hilo que escribe sus mensajes en el escritor.
+    /// Cada cola creada así cuesta un hilo del sistema operativo mientras viva, con su
+    /// propia pila; con el núcleo [`Hilos`](../nucleo/enum.Nucleo.html#variant.Hilos) cada
+    /// cliente conectado ocupa dos hilos, el que lo lee y el que le escribe. Para muchas
+    /// conexiones es preferible [`con_receptor`](#method.con_receptor), como lo hace el
+    /// núcleo [`Reactor`](../nucleo/enum.Nucleo.html#variant.Reactor).
+    pub fn new<W: Write + Send + 'static>(mut escritor: W, capacidad: usize,
+        plazo_saturacion: Duration) -> ColaSalida {
+        let (cola, receptor) = ColaSalida::con_receptor(capacidad, plazo_saturacion);
+        thread::spawn(move || {
+            for mensaje in receptor.iter() {
+                if escritor.write_all(&mensaje[..]).and_then(|_| escritor.flush()).is_err() {
+                    break;
+                }
+            }
+        });
+        cola
+    }
+
+    /// Crea una nueva cola con la capacidad (en mensajes) y el plazo de saturación
+    /// recibidos, sin hilo escritor. Regresa la cola junto con el receptor del que deben
+    /// extraerse los mensajes, ya terminados en un salto de línea, para escribirlos.
+    pub fn con_receptor(capacidad: usize, plazo_saturacion: Duration)
+        -> (ColaSalida, mpsc::Receiver<Vec<u8>>) {
+        let (emisor, receptor) = mpsc::sync_channel::<Vec<u8>>(capacidad);
+        (ColaSalida::crear(Emisor::Bytes(emisor), plazo_saturacion), receptor)
+    }
+
+    /// Crea una nueva cola con la capacidad (en mensajes) y el plazo de saturación
+    /// recibidos, sin hilo escritor, cuyo receptor obtiene los mensajes del servidor sin
+    /// formatear. Los mensajes de texto plano agregados con [`encolar`](#method.encolar) se
+    /// descartan.
+    pub fn de_mensajes(capacidad: usize, plazo_saturacion: Duration)
+        -> (ColaSalida, mpsc::Receiver<MensajeServidor>) {
+        let (emisor, receptor) = mpsc::sync_channel::<MensajeServidor>(capacidad);
+        (ColaSalida::crear(Emisor::Mensajes(emisor), plazo_saturacion), receptor)
+    }
+
+    /// Crea una nueva cola vacía con el emisor recibido.
+    fn crear(emisor: Emisor, plazo_saturacion: Duration) -> ColaSalida {
+        ColaSalida {
+            emisor,
+            saturada_desde: Arc::new(Mutex::new(None)),
+            plazo_saturacion,
+            aviso: None,
+        }
+    }
+
+    /// Define el aviso por el que la cola envía el identificador recibido cada vez que se
+    /// le agrega un mensaje. Las copias de la cola creadas después lo conservan.
+    pub fn set_aviso(&mut self, id: IdCliente, aviso: mpsc::Sender<IdCliente>) {
+        self.aviso = Some((id, aviso));
+    }
+
+    /// Regresa el plazo que la cola puede permanecer llena.
+    pub fn get_plazo_saturacion(&self) -> Duration {
+        self.plazo_saturacion
+    }
+
+    /// Agrega un mensaje a la cola, terminado en un salto de línea.
+    /// Si la cola está llena, descarta el mensaje y regresa un error de tipo
+    /// [`WouldBlock`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.WouldBlock),
+    /// o de tipo [`TimedOut`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.TimedOut)
+    /// si ha estado llena por más del plazo de saturación. Si el hilo escritor terminó,
+    /// regresa un error de tipo
+    /// [`BrokenPipe`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.BrokenPipe).
+    pub fn encolar(&self, mensaje: &str) -> Result<(), Error> {
+        match self.emisor {
+            Emisor::Bytes(ref emisor) => self.resultado(emisor.try_send(util::enmarcar_mensaje(mensaje))),
+            Emisor::Mensajes(_) => Ok(()),
+        }
+    }
+
+    /// Agrega un mensaje del servidor a la cola, formateado en el formato recibido si quien
+    /// consume la cola recibe bytes. Los errores son los mismos que los de
+    /// [`encolar`](#method.encolar).
+    pub fn encolar_mensaje(&self, mensaje: &MensajeServidor, formato: Formato) -> Result<(), Error> {
+        match self.emisor {
+            Emisor::Bytes(_) => self.encolar(&formato.formatear_mensaje(mensaje)),
+            Emisor::Mensajes(ref emisor) => self.resultado(emisor.try_send(mensaje.clone())),
+        }
+    }
+
+    /// Actualiza el estado de saturación de la cola según el resultado de un envío.
+    fn resultado<T>(&self, envio: Result<(), mpsc::TrySendError<T>>) -> Result<(), Error> {
+        let mut saturada_desde = self.saturada_desde.lock().unwrap();
+        match envio {
+            Ok(_) => {
+                *saturada_desde = None;
+                if let Some((id, ref aviso)) = self.aviso {
+                    let _ = aviso.send(id);
+                }
+                Ok(())
+            },
+            Err(mpsc::TrySendError::Full(_)) => {
+                let desde = *saturada_desde.get_or_insert_with(Instant::now);
+                if desde.elapsed() >= self.plazo_saturacion {
+                    Err(Error::new(ErrorKind::TimedOut,
+                        "La cola de salida permaneció llena por más del plazo permitido"))
+                }
+                else {
+                    Err(Error::new(ErrorKind::WouldBlock,
+                        "La cola de salida está llena, se descartó el mensaje"))
+                }
+            },
+            Err(mpsc::TrySendError::Disconnected(_)) => {
+                Err(Error::new(ErrorKind::BrokenPipe, "La conexión de salida está cerrada"))
+            },
+        }
+    }
+}
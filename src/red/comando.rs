@@ -0,0 +1,418 @@
+use super::{eventoconexion::EventoConexion, estadocliente::EstadoCliente, formato::Formato,
+    respuesta::CodigoRespuesta, util::{self, citar, citar_final}};
+use std::str::FromStr;
+use std::{error, fmt};
+
+/// Longitud máxima (en caracteres) del nombre de un usuario.
+pub const LONGITUD_MAXIMA_NOMBRE: usize = 20;
+
+/// Carácter que precede al identificador de petición opcional de un comando.
+pub const PREFIJO_ID: char = '#';
+
+/// Identificador que un cliente puede adjuntar a un comando, y que el servidor repite en
+/// la respuesta correspondiente.
+pub type IdPeticion = u64;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "comando", rename_all = "UPPERCASE")]
+/// Comandos del protocolo de comunicación junto con sus argumentos ya validados.
+/// Cada comando corresponde a un [`EventoConexion`](../eventoconexion/enum.EventoConexion.html)
+/// y se obtiene al analizar una línea enviada por un cliente, en el
+/// [`Formato`](../formato/enum.Formato.html) de su conexión.
+pub enum Comando {
+    /// Acordar la versión del protocolo y las capacidades de la conexión.
+    Hello {
+        version: u32,
+        #[serde(default)]
+        capacidades: Vec<String>,
+    },
+    /// Cambiar el formato de la conexión.
+    Protocol { formato: Formato },
+    /// Darse a conocer al servidor con un nombre de usuario.
+    Identify { nombre: String },
+    /// Asignarse un estado.
+    Status { estado: EstadoCliente },
+    /// Ver usuarios identificados.
+    Users,
+    /// Enviar un mensaje privado a un usuario.
+    Message { destinatario: String, texto: String },
+    /// Enviar un mensaje público.
+    PublicMessage { texto: String },
+    /// Crear una sala.
+    CreateRoom { sala: String },
+    /// Invitar usuarios a una sala.
+    Invite { sala: String, usuarios: Vec<String> },
+    /// Unirse a una sala.
+    JoinRoom { sala: String },
+    /// Enviar un mensaje a una sala.
+    #[serde(rename = "ROOMESSAGE")]
+    RoomMessage { sala: String, texto: String },
+    /// Desconectarse.
+    Disconnect,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Argumentos de los comandos, usados para describir los errores de análisis.
+pub enum Argumento {
+    /// El nombre de un usuario.
+    Nombre,
+    /// El estado de un usuario.
+    Estado,
+    /// El destinatario de un mensaje privado.
+    Destinatario,
+    /// El contenido de un mensaje.
+    Mensaje,
+    /// El nombre de una sala.
+    Sala,
+    /// La lista de usuarios a invitar.
+    Usuarios,
+    /// El identificador de la petición.
+    Id,
+    /// El formato de la conexión.
+    Formato,
+    /// La versión del protocolo.
+    Version,
+    /// Las capacidades solicitadas.
+    Capacidades,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Errores al analizar una línea enviada por un cliente.
+pub enum ErrorComando {
+    /// La línea no comienza con un evento válido del protocolo.
+    EventoInvalido,
+    /// El comando requiere un argumento que no se especificó.
+    FaltaArgumento(Argumento),
+    /// Un argumento del comando tiene un valor inválido.
+    ArgumentoInvalido(Argumento),
+    /// Los campos de un comando en JSON no corresponden a los de su evento.
+    CamposInvalidos(String),
+    /// Unas comillas de la línea no se cerraron.
+    ComillasSinCerrar,
+}
+
+impl ErrorComando {
+
+    /// Regresa el código de respuesta con el que se reporta el error al cliente.
+    pub fn get_codigo(&self) -> CodigoRespuesta {
+        match *self {
+            ErrorComando::EventoInvalido => CodigoRespuesta::ERR_INVALID_COMMAND,
+            ErrorComando::FaltaArgumento(_) => CodigoRespuesta::ERR_MISSING_ARGUMENT,
+            ErrorComando::ArgumentoInvalido(_) | ErrorComando::CamposInvalidos(_) |
+            ErrorComando::ComillasSinCerrar => CodigoRespuesta::ERR_INVALID_ARGUMENT,
+        }
+    }
+}
+
+impl Comando {
+
+    /// Regresa el evento del protocolo al que corresponde el comando.
+    pub fn evento(&self) -> EventoConexion {
+        match *self {
+            Comando::Hello { .. } => EventoConexion::HELLO,
+            Comando::Protocol { .. } => EventoConexion::PROTOCOL,
+            Comando::Identify { .. } => EventoConexion::IDENTIFY,
+            Comando::Status { .. } => EventoConexion::STATUS,
+            Comando::Users => EventoConexion::USERS,
+            Comando::Message { .. } => EventoConexion::MESSAGE,
+            Comando::PublicMessage { .. } => EventoConexion::PUBLICMESSAGE,
+            Comando::CreateRoom { .. } => EventoConexion::CREATEROOM,
+            Comando::Invite { .. } => EventoConexion::INVITE,
+            Comando::JoinRoom { .. } => EventoConexion::JOINROOM,
+            Comando::RoomMessage { .. } => EventoConexion::ROOMESSAGE,
+            Comando::Disconnect => EventoConexion::DISCONNECT,
+        }
+    }
+
+    /// Verifica que los argumentos del comando sean válidos, sin importar el formato del
+    /// que se obtuvo. Los nombres de usuarios no pueden ser vacíos ni contener espacios, los
+    /// de las salas y los textos no pueden ser vacíos, y ninguno puede contener caracteres
+    /// prohibidos, como se define en
+    /// [`es_caracter_prohibido`](../util/fn.es_caracter_prohibido.html).
+    pub fn validar(&self) -> Result<(), ErrorComando> {
+        match *self {
+            Comando::Hello { version, ref capacidades } => {
+                if version < 1 {
+                    return Err(ErrorComando::ArgumentoInvalido(Argumento::Version));
+                }
+                for capacidad in capacidades.iter() {
+                    validar_palabra(capacidad, Argumento::Capacidades)?;
+                }
+                Ok(())
+            },
+            Comando::Identify { ref nombre } => {
+                let longitud = nombre.chars().count();
+                if !(1..=LONGITUD_MAXIMA_NOMBRE).contains(&longitud) || !es_palabra(nombre) {
+                    return Err(ErrorComando::ArgumentoInvalido(Argumento::Nombre));
+                }
+                Ok(())
+            },
+            Comando::Message { ref destinatario, ref texto } => {
+                validar_palabra(destinatario, Argumento::Destinatario)?;
+                validar_texto(texto)
+            },
+            Comando::PublicMessage { ref texto } => validar_texto(texto),
+            Comando::CreateRoom { ref sala } | Comando::JoinRoom { ref sala } => validar_sala(sala),
+            Comando::Invite { ref sala, ref usuarios } => {
+                validar_sala(sala)?;
+                if usuarios.is_empty() {
+                    return Err(ErrorComando::FaltaArgumento(Argumento::Usuarios));
+                }
+                for usuario in usuarios.iter() {
+                    validar_palabra(usuario, Argumento::Usuarios)?;
+                }
+                Ok(())
+            },
+            Comando::RoomMessage { ref sala, ref texto } => {
+                validar_sala(sala)?;
+                validar_texto(texto)
+            },
+            Comando::Protocol { .. } | Comando::Status { .. } | Comando::Users |
+            Comando::Disconnect => Ok(()),
+        }
+    }
+}
+
+/// Determina si una cadena es una sola palabra, sin espacios ni caracteres prohibidos.
+fn es_palabra(cadena: &str) -> bool {
+    !cadena.chars().any(|c| c.is_whitespace() || util::es_caracter_prohibido(c))
+}
+
+/// Verifica que un argumento sea una palabra no vacía.
+fn validar_palabra(cadena: &str, argumento: Argumento) -> Result<(), ErrorComando> {
+    if cadena.is_empty() {
+        Err(ErrorComando::FaltaArgumento(argumento))
+    }
+    else if !es_palabra(cadena) {
+        Err(ErrorComando::ArgumentoInvalido(argumento))
+    }
+    else {
+        Ok(())
+    }
+}
+
+/// Verifica que el nombre de una sala no sea vacío ni contenga caracteres prohibidos.
+fn validar_sala(sala: &str) -> Result<(), ErrorComando> {
+    if sala.is_empty() {
+        Err(ErrorComando::FaltaArgumento(Argumento::Sala))
+    }
+    else if sala.chars().any(util::es_caracter_prohibido) {
+        Err(ErrorComando::ArgumentoInvalido(Argumento::Sala))
+    }
+    else {
+        Ok(())
+    }
+}
+
+/// Verifica que el contenido de un mensaje no sea vacío ni contenga caracteres prohibidos,
+/// por lo que ocupa una sola línea.
+fn validar_texto(texto: &str) -> Result<(), ErrorComando> {
+    if texto.is_empty() {
+        Err(ErrorComando::FaltaArgumento(Argumento::Mensaje))
+    }
+    else if texto.chars().any(util::es_caracter_prohibido) {
+        Err(ErrorComando::ArgumentoInvalido(Argumento::Mensaje))
+    }
+    else {
+        Ok(())
+    }
+}
+
+/// Analiza una línea enviada por un cliente, que puede comenzar con un identificador de
+/// petición de la forma `#<id>` seguido del comando. Regresa el identificador, si existe,
+/// junto con el resultado de analizar el comando.
+pub fn analizar_peticion(linea: &str) -> (Option<IdPeticion>, Result<Comando, ErrorComando>) {
+    if !linea.starts_with(PREFIJO_ID) {
+        return (None, linea.parse::<Comando>());
+    }
+    let mut partes = linea[PREFIJO_ID.len_utf8()..].splitn(2, ' ');
+    match partes.next().unwrap_or("").parse::<IdPeticion>() {
+        Ok(id) => (Some(id), partes.next().unwrap_or("").parse::<Comando>()),
+        Err(_) => (None, Err(ErrorComando::ArgumentoInvalido(Argumento::Id))),
+    }
+}
+
+/// Regresa la línea del protocolo para un comando con un identificador de petición.
+pub fn formatear_peticion(id: IdPeticion, comando: &Comando) -> String {
+    format!("{}{} {}", PREFIJO_ID, id, comando)
+}
+
+/// Regresa el siguiente argumento, o un error si no existe o es vacío.
+fn siguiente_argumento(argumentos: &mut Vec<String>, argumento: Argumento)
+    -> Result<String, ErrorComando> {
+    if argumentos.is_empty() || argumentos[0].is_empty() {
+        return Err(ErrorComando::FaltaArgumento(argumento));
+    }
+    Ok(argumentos.remove(0))
+}
+
+/// Regresa el contenido de un mensaje a partir del resto de la línea que sigue a los
+/// argumentos del comando, tal cual, sin el `:` que lo precede si lo tiene.
+fn contenido_mensaje(resto: &str) -> Result<String, ErrorComando> {
+    let texto = resto.strip_prefix(util::PREFIJO_ARGUMENTO_FINAL).unwrap_or(resto);
+    if !texto.is_empty() {
+        Ok(texto.to_owned())
+    }
+    else {
+        Err(ErrorComando::FaltaArgumento(Argumento::Mensaje))
+    }
+}
+
+impl FromStr for Comando {
+    type Err = ErrorComando;
+
+    /// Analiza una línea del protocolo, formada por un evento seguido de sus argumentos,
+    /// separados como lo indica [`separar_argumentos`](../util/fn.separar_argumentos.html).
+    /// El contenido de los mensajes es el resto de la línea tal cual, sin interpretar
+    /// comillas ni escapes, y sin el `:` que lo precede si lo tiene.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut evento, resto) = util::separar_con_resto(s, 1)?;
+        let evento = evento.pop().ok_or(ErrorComando::EventoInvalido)?
+            .parse::<EventoConexion>().map_err(|_| ErrorComando::EventoInvalido)?;
+        let mut argumentos = match evento {
+            EventoConexion::MESSAGE | EventoConexion::PUBLICMESSAGE |
+            EventoConexion::ROOMESSAGE => Vec::new(),
+            _ => util::separar_argumentos(resto)?,
+        };
+        let comando = match evento {
+            EventoConexion::HELLO => {
+                let version = siguiente_argumento(&mut argumentos, Argumento::Version)?;
+                let version = version.parse::<u32>()
+                    .map_err(|_| ErrorComando::ArgumentoInvalido(Argumento::Version))?;
+                let capacidades: Vec<String> = argumentos.into_iter()
+                    .filter(|capacidad| !capacidad.is_empty()).collect();
+                Ok(Comando::Hello { version, capacidades })
+            },
+            EventoConexion::PROTOCOL => {
+                let formato = siguiente_argumento(&mut argumentos, Argumento::Formato)?;
+                let formato = formato.parse::<Formato>()
+                    .map_err(|_| ErrorComando::ArgumentoInvalido(Argumento::Formato))?;
+                Ok(Comando::Protocol { formato })
+            },
+            EventoConexion::IDENTIFY => {
+                if argumentos.is_empty() {
+                    return Err(ErrorComando::FaltaArgumento(Argumento::Nombre));
+                }
+                Ok(Comando::Identify { nombre: argumentos.remove(0) })
+            },
+            EventoConexion::STATUS => {
+                let estado = siguiente_argumento(&mut argumentos, Argumento::Estado)?;
+                let estado = estado.parse::<EstadoCliente>()
+                    .map_err(|_| ErrorComando::ArgumentoInvalido(Argumento::Estado))?;
+                Ok(Comando::Status { estado })
+            },
+            EventoConexion::USERS => Ok(Comando::Users),
+            EventoConexion::MESSAGE => {
+                let (mut argumentos, texto) = util::separar_con_resto(resto, 1)?;
+                let destinatario = siguiente_argumento(&mut argumentos, Argumento::Destinatario)?;
+                let texto = contenido_mensaje(texto)?;
+                Ok(Comando::Message { destinatario, texto })
+            },
+            EventoConexion::PUBLICMESSAGE => {
+                let texto = contenido_mensaje(resto)?;
+                Ok(Comando::PublicMessage { texto })
+            },
+            EventoConexion::CREATEROOM => {
+                let sala = siguiente_argumento(&mut argumentos, Argumento::Sala)?;
+                Ok(Comando::CreateRoom { sala })
+            },
+            EventoConexion::INVITE => {
+                let sala = siguiente_argumento(&mut argumentos, Argumento::Sala)?;
+                let usuarios: Vec<String> = argumentos.into_iter()
+                    .filter(|usuario| !usuario.is_empty()).collect();
+                if usuarios.is_empty() {
+                    return Err(ErrorComando::FaltaArgumento(Argumento::Usuarios));
+                }
+                Ok(Comando::Invite { sala, usuarios })
+            },
+            EventoConexion::JOINROOM => {
+                let sala = siguiente_argumento(&mut argumentos, Argumento::Sala)?;
+                Ok(Comando::JoinRoom { sala })
+            },
+            EventoConexion::ROOMESSAGE => {
+                let (mut argumentos, texto) = util::separar_con_resto(resto, 1)?;
+                let sala = siguiente_argumento(&mut argumentos, Argumento::Sala)?;
+                let texto = contenido_mensaje(texto)?;
+                Ok(Comando::RoomMessage { sala, texto })
+            },
+            EventoConexion::DISCONNECT => Ok(Comando::Disconnect),
+            EventoConexion::SHUTDOWN | EventoConexion::INVALID | EventoConexion::ERROR =>
+                Err(ErrorComando::EventoInvalido),
+        }?;
+        comando.validar()?;
+        Ok(comando)
+    }
+}
+
+/// Regresa una lista de argumentos citados, separados por un espacio.
+fn citar_todos(argumentos: &[String]) -> String {
+    argumentos.iter().map(|argumento| citar(argumento)).collect::<Vec<String>>().join(" ")
+}
+
+impl fmt::Display for Comando {
+    /// Escribe el comando como una línea del protocolo, sin el salto de línea final.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let evento = self.evento();
+        match *self {
+            Comando::Hello { version, ref capacidades } if !capacidades.is_empty() =>
+                write!(f, "{} {} {}", evento, version, citar_todos(capacidades)),
+            Comando::Hello { version, .. } => write!(f, "{} {}", evento, version),
+            Comando::Protocol { ref formato } => write!(f, "{} {}", evento, formato),
+            Comando::Identify { ref nombre } => write!(f, "{} {}", evento, citar(nombre)),
+            Comando::Status { ref estado } => write!(f, "{} {}", evento, estado),
+            Comando::Message { ref destinatario, ref texto } =>
+                write!(f, "{} {} {}", evento, citar(destinatario), citar_final(texto)),
+            Comando::PublicMessage { ref texto } => write!(f, "{} {}", evento, citar_final(texto)),
+            Comando::CreateRoom { ref sala } | Comando::JoinRoom { ref sala } =>
+                write!(f, "{} {}", evento, citar(sala)),
+            Comando::Invite { ref sala, ref usuarios } =>
+                write!(f, "{} {} {}", evento, citar(sala), citar_todos(usuarios)),
+            Comando::RoomMessage { ref sala, ref texto } =>
+                write!(f, "{} {} {}", evento, citar(sala), citar_final(texto)),
+            Comando::Users | Comando::Disconnect => write!(f, "{}", evento),
+        }
+    }
+}
+
+impl fmt::Display for ErrorComando {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorComando::EventoInvalido => write!(f, "Mensaje inválido"),
+            ErrorComando::FaltaArgumento(argumento) => match argumento {
+                Argumento::Nombre => write!(f, "No se especificó el nombre"),
+                Argumento::Estado => write!(f, "No se especificó el estado"),
+                Argumento::Destinatario => write!(f, "No se especificó el destinatario"),
+                Argumento::Mensaje => write!(f, "No se identificó el contenido del mensaje"),
+                Argumento::Sala => write!(f, "No se especificó el nombre de la sala"),
+                Argumento::Usuarios => write!(f, "No se especificaron los usuarios a invitar"),
+                Argumento::Id => write!(f, "No se especificó el identificador de la petición"),
+                Argumento::Formato => write!(f, "No se especificó el formato"),
+                Argumento::Version => write!(f, "No se especificó la versión del protocolo"),
+                Argumento::Capacidades => write!(f, "No se especificaron las capacidades"),
+            },
+            ErrorComando::ArgumentoInvalido(argumento) => match argumento {
+                Argumento::Nombre => write!(f,
+                    "El nombre debe tener una longitud entre 1 y {} caracteres, sin espacios \
+                    ni caracteres de control", LONGITUD_MAXIMA_NOMBRE),
+                Argumento::Estado => write!(f, "Proporciona un estado válido: ACTIVE, AWAY, BUSY"),
+                Argumento::Id => write!(f, "El identificador de la petición debe ser un número"),
+                Argumento::Formato => write!(f, "Proporciona un formato válido: TEXT, JSON"),
+                Argumento::Mensaje => write!(f, "El mensaje no puede contener caracteres de control"),
+                Argumento::Sala =>
+                    write!(f, "El nombre de la sala no puede contener caracteres de control"),
+                Argumento::Version => write!(f, "La versión del protocolo debe ser un número positivo"),
+                Argumento::Destinatario => write!(f,
+                    "El destinatario debe ser un nombre de usuario, sin espacios ni caracteres de control"),
+                Argumento::Usuarios => write!(f,
+                    "Los usuarios a invitar deben ser nombres sin espacios ni caracteres de control"),
+                Argumento::Capacidades => write!(f,
+                    "Las capacidades deben ser palabras sin espacios ni caracteres de control"),
+            },
+            ErrorComando::CamposInvalidos(ref detalle) =>
+                write!(f, "Los campos del comando son inválidos: {}", detalle),
+            ErrorComando::ComillasSinCerrar => write!(f, "Faltan comillas de cierre en el comando"),
+        }
+    }
+}
+
+impl error::Error for ErrorComando {}
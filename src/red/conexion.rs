@@ -0,0 +1,368 @@
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, SocketAddr, Shutdown, IpAddr, Ipv4Addr, Ipv6Addr};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::{mpsc, Arc, Mutex, Condvar};
+use std::time::{Duration, Instant};
+
+/// Número máximo de bytes en tránsito en cada sentido de una tubería en memoria, como el
+/// buffer de un socket. Al llenarse, las escrituras esperan a que el otro extremo lea.
+pub const CAPACIDAD_TUBERIA: usize = 64 * 1024;
+
+/// Flujo de bytes bidireccional con un cliente, sin importar el transporte. El servidor
+/// lee los mensajes del cliente de una copia de la conexión y los escribe en otra, por lo
+/// que las copias deben referirse al mismo flujo. Las copias de un
+/// [`Cliente`](../cliente/struct.Cliente.html) comparten su conexión entre hilos de
+/// ejecución, por lo que debe ser `Sync`.
+/// Está implementada para [`TcpStream`](https://doc.rust-lang.org/std/net/struct.TcpStream.html),
+/// para [`UnixStream`](https://doc.rust-lang.org/std/os/unix/net/struct.UnixStream.html) y
+/// para los extremos de una [`tuberia`](fn.tuberia.html) en memoria.
+pub trait Conexion: Read + Write + Send + Sync {
+
+    /// Regresa una copia de la conexión que se refiere al mismo flujo.
+    fn duplicar(&self) -> Result<Box<dyn Conexion>, Error>;
+
+    /// Cierra ambos sentidos de la conexión, en todas sus copias. Quien esté leyendo de la
+    /// conexión deja de esperar datos.
+    fn cerrar(&self) -> Result<(), Error>;
+
+    /// Regresa una descripción de la dirección del otro extremo de la conexión.
+    fn direccion_remota(&self) -> String;
+
+    /// Define el tiempo máximo que una escritura puede esperar a que el otro extremo
+    /// acepte datos antes de fallar, en todas las copias de la conexión. Sin plazo, la
+    /// escritura espera indefinidamente.
+    fn set_plazo_escritura(&self, plazo: Option<Duration>) -> Result<(), Error>;
+}
+
+/// Fuente de conexiones de clientes, como un puerto TCP o un socket de Unix. La espera de
+/// conexiones es bloqueante, por lo que el aceptador debe poder despertar a quien espera,
+/// desde otro hilo de ejecución.
+pub trait Aceptador: Send + Sync {
+
+    /// Espera a la siguiente conexión y la regresa.
+    fn aceptar(&self) -> Result<Box<dyn Conexion>, Error>;
+
+    /// Abre una conexión hacia el propio aceptador, para despertar a quien espera conexiones
+    /// en [`aceptar`](#tymethod.aceptar).
+    fn despertar(&self) -> Result<(), Error>;
+
+    /// Regresa una descripción de la dirección en la que acepta conexiones.
+    fn direccion_local(&self) -> String;
+}
+
+impl Conexion for TcpStream {
+    fn duplicar(&self) -> Result<Box<dyn Conexion>, Error> {
+        Ok(Box::new(self.try_clone()?))
+    }
+
+    fn cerrar(&self) -> Result<(), Error> {
+        self.shutdown(Shutdown::Both)
+    }
+
+    fn direccion_remota(&self) -> String {
+        match self.peer_addr() {
+            Ok(direccion) => direccion.to_string(),
+            Err(_) => String::from("tcp"),
+        }
+    }
+
+    fn set_plazo_escritura(&self, plazo: Option<Duration>) -> Result<(), Error> {
+        self.set_write_timeout(plazo)
+    }
+}
+
+impl Aceptador for TcpListener {
+    fn aceptar(&self) -> Result<Box<dyn Conexion>, Error> {
+        let (socket, _) = self.accept()?;
+        Ok(Box::new(socket))
+    }
+
+    fn despertar(&self) -> Result<(), Error> {
+        TcpStream::connect(direccion_despertador(self.local_addr()?))?;
+        Ok(())
+    }
+
+    fn direccion_local(&self) -> String {
+        match self.local_addr() {
+            Ok(direccion) => direccion.to_string(),
+            Err(_) => String::from("tcp"),
+        }
+    }
+}
+
+/// Regresa la dirección a la que debe conectarse quien despierta a un aceptador TCP. Si el
+/// aceptador está en la dirección no especificada, usa la dirección de bucle local.
+pub fn direccion_despertador(direccion_local: SocketAddr) -> SocketAddr {
+    let mut despertador = direccion_local;
+    if despertador.ip().is_unspecified() {
+        despertador.set_ip(match despertador.ip() {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        });
+    }
+    despertador
+}
+
+#[cfg(unix)]
+impl Conexion for UnixStream {
+    fn duplicar(&self) -> Result<Box<dyn Conexion>, Error> {
+        Ok(Box::new(self.try_clone()?))
+    }
+
+    fn cerrar(&self) -> Result<(), Error> {
+        self.shutdown(Shutdown::Both)
+    }
+
+    fn direccion_remota(&self) -> String {
+        match self.peer_addr().ok().as_ref().and_then(|direccion| direccion.as_pathname()) {
+            Some(ruta) => ruta.display().to_string(),
+            None => String::from("unix"),
+        }
+    }
+
+    fn set_plazo_escritura(&self, plazo: Option<Duration>) -> Result<(), Error> {
+        self.set_write_timeout(plazo)
+    }
+}
+
+#[cfg(unix)]
+impl Aceptador for UnixListener {
+    fn aceptar(&self) -> Result<Box<dyn Conexion>, Error> {
+        let (socket, _) = self.accept()?;
+        Ok(Box::new(socket))
+    }
+
+    fn despertar(&self) -> Result<(), Error> {
+        let direccion = self.local_addr()?;
+        match direccion.as_pathname() {
+            Some(ruta) => UnixStream::connect(ruta).map(|_| ()),
+            None => Err(Error::new(ErrorKind::AddrNotAvailable, "El socket no tiene ruta")),
+        }
+    }
+
+    fn direccion_local(&self) -> String {
+        match self.local_addr().ok().as_ref().and_then(|direccion| direccion.as_pathname()) {
+            Some(ruta) => ruta.display().to_string(),
+            None => String::from("unix"),
+        }
+    }
+}
+
+/// Bytes en tránsito en un sentido de una tubería en memoria, y si el sentido está cerrado.
+/// La condición avisa tanto de datos nuevos como de espacio liberado.
+struct Sentido {
+    datos: Mutex<(VecDeque<u8>, bool)>,
+    disponible: Condvar,
+}
+
+impl Sentido {
+
+    /// Crea un sentido vacío y abierto.
+    fn new() -> Arc<Sentido> {
+        Arc::new(Sentido {
+            datos: Mutex::new((VecDeque::new(), false)),
+            disponible: Condvar::new(),
+        })
+    }
+
+    /// Cierra el sentido, despertando a quien espera datos.
+    fn cerrar(&self) {
+        self.datos.lock().unwrap().1 = true;
+        self.disponible.notify_all();
+    }
+}
+
+/// Sentidos de un extremo de una tubería, compartidos por todas sus copias junto con su
+/// plazo de escritura. Cierra ambos sentidos cuando se elimina la última copia del extremo,
+/// como ocurre al cerrar la última copia de un socket.
+struct GuardaExtremo {
+    entrada: Arc<Sentido>,
+    salida: Arc<Sentido>,
+    plazo_escritura: Mutex<Option<Duration>>,
+}
+
+impl Drop for GuardaExtremo {
+    fn drop(&mut self) {
+        self.entrada.cerrar();
+        self.salida.cerrar();
+    }
+}
+
+/// Extremo de una tubería en memoria, creada con [`tuberia`](fn.tuberia.html). Lo que se
+/// escribe en un extremo se lee del otro. Las lecturas bloquean hasta que hay datos o la
+/// tubería se cierra, en cuyo caso regresan 0 bytes. Como en un socket, cada sentido admite
+/// a lo más [`CAPACIDAD_TUBERIA`](constant.CAPACIDAD_TUBERIA.html) bytes sin leer; al
+/// llenarse, las escrituras esperan a que el otro extremo lea, a lo más el plazo de
+/// escritura del extremo.
+pub struct ExtremoMemoria {
+    guarda: Arc<GuardaExtremo>,
+}
+
+/// Crea una tubería en memoria, regresando sus dos extremos conectados entre sí. Permite
+/// conectar clientes al servidor sin sockets, por ejemplo en pruebas.
+pub fn tuberia() -> (ExtremoMemoria, ExtremoMemoria) {
+    let ida = Sentido::new();
+    let vuelta = Sentido::new();
+    let extremo = |entrada: &Arc<Sentido>, salida: &Arc<Sentido>| ExtremoMemoria {
+        guarda: Arc::new(GuardaExtremo {
+            entrada: Arc::clone(entrada),
+            salida: Arc::clone(salida),
+            plazo_escritura: Mutex::new(None),
+        }),
+    };
+    (extremo(&vuelta, &ida), extremo(&ida, &vuelta))
+}
+
+impl Read for ExtremoMemoria {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let entrada = &self.guarda.entrada;
+        let mut datos = entrada.datos.lock().unwrap();
+        while datos.0.is_empty() && !datos.1 {
+            datos = entrada.disponible.wait(datos).unwrap();
+        }
+        let leidos = datos.0.len().min(buffer.len());
+        for (destino, byte) in buffer.iter_mut().zip(datos.0.drain(..leidos)) {
+            *destino = byte;
+        }
+        entrada.disponible.notify_all();
+        Ok(leidos)
+    }
+}
+
+impl Write for ExtremoMemoria {
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, Error> {
+        let salida = &self.guarda.salida;
+        let limite = self.guarda.plazo_escritura.lock().unwrap().map(|plazo| Instant::now() + plazo);
+        let mut datos = salida.datos.lock().unwrap();
+        loop {
+            if datos.1 {
+                return Err(Error::new(ErrorKind::BrokenPipe, "La tubería está cerrada"));
+            }
+            let libres = CAPACIDAD_TUBERIA - datos.0.len();
+            if libres > 0 || buffer.is_empty() {
+                let escritos = libres.min(buffer.len());
+                datos.0.extend(&buffer[..escritos]);
+                salida.disponible.notify_all();
+                return Ok(escritos);
+            }
+            datos = match limite {
+                None => salida.disponible.wait(datos).unwrap(),
+                Some(limite) => {
+                    let restante = limite.saturating_duration_since(Instant::now());
+                    if restante == Duration::from_secs(0) {
+                        return Err(Error::new(ErrorKind::TimedOut,
+                            "El otro extremo de la tubería no leyó durante el plazo de escritura"));
+                    }
+                    salida.disponible.wait_timeout(datos, restante).unwrap().0
+                },
+            };
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl Conexion for ExtremoMemoria {
+    fn duplicar(&self) -> Result<Box<dyn Conexion>, Error> {
+        Ok(Box::new(ExtremoMemoria {
+            guarda: Arc::clone(&self.guarda),
+        }))
+    }
+
+    fn cerrar(&self) -> Result<(), Error> {
+        self.guarda.entrada.cerrar();
+        self.guarda.salida.cerrar();
+        Ok(())
+    }
+
+    fn direccion_remota(&self) -> String {
+        String::from("memoria")
+    }
+
+    fn set_plazo_escritura(&self, plazo: Option<Duration>) -> Result<(), Error> {
+        *self.guarda.plazo_escritura.lock().unwrap() = plazo;
+        Ok(())
+    }
+}
+
+/// Aceptador de conexiones en memoria. Los clientes se conectan con un
+/// [`ConectorMemoria`](struct.ConectorMemoria.html), obteniendo un extremo de una tubería
+/// cuyo otro extremo acepta el servidor. Como un escucha TCP, el aceptador espera
+/// conexiones aunque no quede ningún conector, hasta que se le despierta.
+pub struct AceptadorMemoria {
+    receptor: Mutex<mpsc::Receiver<ExtremoMemoria>>,
+    emisor: Mutex<mpsc::Sender<ExtremoMemoria>>,
+}
+
+impl Default for AceptadorMemoria {
+    fn default() -> AceptadorMemoria {
+        AceptadorMemoria::new()
+    }
+}
+
+impl AceptadorMemoria {
+
+    /// Crea un aceptador en memoria sin conexiones pendientes.
+    pub fn new() -> AceptadorMemoria {
+        let (emisor, receptor) = mpsc::channel();
+        AceptadorMemoria {
+            receptor: Mutex::new(receptor),
+            emisor: Mutex::new(emisor),
+        }
+    }
+
+    /// Regresa un conector para abrir conexiones hacia este aceptador.
+    pub fn get_conector(&self) -> ConectorMemoria {
+        ConectorMemoria {
+            emisor: Mutex::new(self.emisor.lock().unwrap().clone()),
+        }
+    }
+}
+
+impl Aceptador for AceptadorMemoria {
+    fn aceptar(&self) -> Result<Box<dyn Conexion>, Error> {
+        // El aceptador conserva un emisor del canal, por lo que la espera sólo termina
+        // con una conexión.
+        let extremo = self.receptor.lock().unwrap().recv()
+            .expect("El canal de un aceptador en memoria no se cierra");
+        Ok(Box::new(extremo))
+    }
+
+    fn despertar(&self) -> Result<(), Error> {
+        self.get_conector().conectar().map(|_| ())
+    }
+
+    fn direccion_local(&self) -> String {
+        String::from("memoria")
+    }
+}
+
+/// Conector que abre conexiones hacia un [`AceptadorMemoria`](struct.AceptadorMemoria.html).
+pub struct ConectorMemoria {
+    emisor: Mutex<mpsc::Sender<ExtremoMemoria>>,
+}
+
+impl ConectorMemoria {
+
+    /// Abre una conexión hacia el aceptador, regresando el extremo del cliente. Regresa un
+    /// error si el aceptador ya no existe.
+    pub fn conectar(&self) -> Result<ExtremoMemoria, Error> {
+        let (cliente, servidor) = tuberia();
+        match self.emisor.lock().unwrap().send(servidor) {
+            Ok(_) => Ok(cliente),
+            Err(_) => Err(Error::new(ErrorKind::ConnectionRefused, "El aceptador ya no existe")),
+        }
+    }
+}
+
+impl Clone for ConectorMemoria {
+    fn clone(&self) -> Self {
+        ConectorMemoria {
+            emisor: Mutex::new(self.emisor.lock().unwrap().clone()),
+        }
+    }
+}
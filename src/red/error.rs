@@ -0,0 +1,101 @@
+use super::{comando::ErrorComando, respuesta::CodigoRespuesta};
+use std::io;
+use std::{error, fmt};
+
+#[derive(Debug)]
+/// Errores al realizar los comandos de los clientes. Los errores del protocolo y del chat,
+/// como una sala que no existe, se reportan al cliente con el código de respuesta que les
+/// corresponde y la conexión continúa; sólo los errores de
+/// [`Transporte`](#variant.Transporte) terminan la conexión.
+pub enum ChatError {
+    /// El cliente debe identificarse para realizar la acción indicada.
+    NoIdentificado(String),
+    /// El nombre solicitado ya pertenece a otro usuario.
+    NombreEnUso(String),
+    /// La conexión sólo puede configurarse antes de identificarse.
+    YaIdentificado,
+    /// No existe el usuario con el nombre indicado.
+    UsuarioInexistente(String),
+    /// No existe la sala con el nombre indicado.
+    SalaInexistente(String),
+    /// Ya existe una sala con el nombre indicado.
+    SalaExistente(String),
+    /// El cliente no es propietario de la sala indicada.
+    NoPropietario(String),
+    /// El cliente no está invitado a la sala indicada.
+    NoInvitado(String),
+    /// El cliente no es miembro de la sala indicada.
+    NoMiembro(String),
+    /// El comando no es válido: no corresponde a un evento del protocolo, le falta un
+    /// argumento o alguno tiene un valor inválido.
+    ArgumentoInvalido(ErrorComando),
+    /// La conexión con el cliente falló o terminó.
+    Transporte(io::Error),
+}
+
+impl ChatError {
+
+    /// Regresa el código de respuesta con el que se reporta el error al cliente.
+    pub fn get_codigo(&self) -> CodigoRespuesta {
+        match *self {
+            ChatError::NoIdentificado(_) => CodigoRespuesta::ERR_NOT_IDENTIFIED,
+            ChatError::NombreEnUso(_) => CodigoRespuesta::ERR_NAME_IN_USE,
+            ChatError::YaIdentificado => CodigoRespuesta::ERR_ALREADY_IDENTIFIED,
+            ChatError::UsuarioInexistente(_) => CodigoRespuesta::ERR_NO_SUCH_USER,
+            ChatError::SalaInexistente(_) => CodigoRespuesta::ERR_NO_SUCH_ROOM,
+            ChatError::SalaExistente(_) => CodigoRespuesta::ERR_ROOM_EXISTS,
+            ChatError::NoPropietario(_) => CodigoRespuesta::ERR_NOT_OWNER,
+            ChatError::NoInvitado(_) => CodigoRespuesta::ERR_NOT_INVITED,
+            ChatError::NoMiembro(_) => CodigoRespuesta::ERR_NOT_MEMBER,
+            ChatError::ArgumentoInvalido(ref error) => error.get_codigo(),
+            ChatError::Transporte(_) => CodigoRespuesta::ERR_INTERNAL,
+        }
+    }
+
+    /// Determina si el error es de transporte, en cuyo caso la conexión debe terminar.
+    pub fn es_transporte(&self) -> bool {
+        matches!(*self, ChatError::Transporte(_))
+    }
+}
+
+impl fmt::Display for ChatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ChatError::NoIdentificado(ref accion) => write!(f, "Debes identificarte para {}", accion),
+            ChatError::NombreEnUso(ref nombre) => write!(f, "Ya existe un usuario con el nombre {}", nombre),
+            ChatError::YaIdentificado =>
+                write!(f, "La conexión sólo puede configurarse antes de identificarse"),
+            ChatError::UsuarioInexistente(ref nombre) => write!(f, "No se encontró al usuario {}", nombre),
+            ChatError::SalaInexistente(ref sala) => write!(f, "La sala {} no existe", sala),
+            ChatError::SalaExistente(ref sala) => write!(f, "Ya existe una sala con el nombre {}", sala),
+            ChatError::NoPropietario(ref sala) =>
+                write!(f, "Debes ser propietario de la sala {} para invitar personas a unirse", sala),
+            ChatError::NoInvitado(ref sala) => write!(f, "No estás invitado para unirte a la sala {}", sala),
+            ChatError::NoMiembro(ref sala) => write!(f, "No eres miembro de la sala {}", sala),
+            ChatError::ArgumentoInvalido(ref error) => write!(f, "{}", error),
+            ChatError::Transporte(ref error) => write!(f, "Error en la conexión: {}", error),
+        }
+    }
+}
+
+impl error::Error for ChatError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ChatError::ArgumentoInvalido(ref error) => Some(error),
+            ChatError::Transporte(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ErrorComando> for ChatError {
+    fn from(error: ErrorComando) -> ChatError {
+        ChatError::ArgumentoInvalido(error)
+    }
+}
+
+impl From<io::Error> for ChatError {
+    fn from(error: io::Error) -> ChatError {
+        ChatError::Transporte(error)
+    }
+}
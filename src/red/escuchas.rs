@@ -0,0 +1,80 @@
+use super::eventoservidor::{EventoServidor, TipoEvento};
+use std::sync::{mpsc, Arc, Mutex};
+
+/// Emisor de eventos de un escucha, junto con los tipos de eventos que desea recibir.
+/// Si no especifica tipos, recibe todos los eventos.
+struct Suscripcion {
+    emisor: mpsc::Sender<EventoServidor>,
+    tipos: Option<Vec<TipoEvento>>,
+}
+
+/// Lista de escuchas "suscritos" a los eventos de un servidor. Las copias de la lista se
+/// refieren a los mismos escuchas, por lo que los hilos que atienden a los clientes pueden
+/// anunciar eventos sin acceso al servidor. Los escuchas que se eliminan dejan de recibir
+/// eventos y se retiran de la lista en el siguiente anuncio.
+#[derive(Clone)]
+pub struct Escuchas {
+    suscripciones: Arc<Mutex<Vec<Suscripcion>>>,
+}
+
+impl Default for Escuchas {
+    fn default() -> Escuchas {
+        Escuchas::new()
+    }
+}
+
+impl Escuchas {
+
+    /// Crea una lista de escuchas vacía.
+    pub fn new() -> Escuchas {
+        Escuchas {
+            suscripciones: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Agrega un escucha que recibe todos los eventos, regresándolo.
+    pub fn suscribir(&self) -> mpsc::Receiver<EventoServidor> {
+        self.agregar(None)
+    }
+
+    /// Agrega un escucha que solo recibe los eventos de los tipos recibidos, regresándolo.
+    pub fn suscribir_a(&self, tipos: &[TipoEvento]) -> mpsc::Receiver<EventoServidor> {
+        self.agregar(Some(tipos.to_vec()))
+    }
+
+    /// Agrega un escucha con los tipos de eventos que desea recibir.
+    fn agregar(&self, tipos: Option<Vec<TipoEvento>>) -> mpsc::Receiver<EventoServidor> {
+        let (emisor, receptor) = mpsc::channel::<EventoServidor>();
+        self.suscripciones.lock().unwrap().push(Suscripcion {
+            emisor,
+            tipos,
+        });
+        receptor
+    }
+
+    /// Envía un evento a los escuchas que desean recibir su tipo.
+    pub fn anunciar(&self, evento: &EventoServidor) {
+        let tipo = evento.get_tipo();
+        self.suscripciones.lock().unwrap().retain(|suscripcion| {
+            match suscripcion.tipos {
+                Some(ref tipos) if !tipos.contains(&tipo) => true,
+                _ => suscripcion.emisor.send(evento.clone()).is_ok(),
+            }
+        });
+    }
+
+    /// Elimina a todos los escuchas.
+    pub fn eliminar(&self) {
+        self.suscripciones.lock().unwrap().clear();
+    }
+
+    /// Regresa el número de escuchas.
+    pub fn len(&self) -> usize {
+        self.suscripciones.lock().unwrap().len()
+    }
+
+    /// Determina si no hay escuchas.
+    pub fn is_empty(&self) -> bool {
+        self.suscripciones.lock().unwrap().is_empty()
+    }
+}
@@ -1,7 +1,7 @@
 use std::str::FromStr;
 use std::fmt;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 /// Estados de los clientes. Los estados definen en qué situación se encuentra
 /// el cliente mientras participa en el chat.
 pub enum EstadoCliente {
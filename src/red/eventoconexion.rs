@@ -6,6 +6,10 @@ use std::fmt;
 /// los clientes buscan realizar dentro del chat, enviando dichos eventos como cadenas,
 /// donde el servidor las interpreta y reacciona a ellas.
 pub enum EventoConexion {
+    /// Acordar la versión del protocolo y sus capacidades.
+    HELLO,
+    /// Cambiar el [`Formato`](../formato/enum.Formato.html) de la conexión.
+    PROTOCOL,
     /// Darse a conocer al servidor con un nombre de usuario.
     IDENTIFY,
     /// Asignarse un estado dentro de los disponibles
@@ -27,6 +31,8 @@ pub enum EventoConexion {
     ROOMESSAGE,
     /// Desconectarse.
     DISCONNECT,
+    /// Aviso del servidor de que se está deteniendo.
+    SHUTDOWN,
     /// Si el evento es inválido.
     INVALID,
     /// Si hubo un error al procesar el evento.
@@ -38,6 +44,8 @@ impl FromStr for EventoConexion {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
+            "HELLO" => Ok(EventoConexion::HELLO),
+            "PROTOCOL" => Ok(EventoConexion::PROTOCOL),
             "IDENTIFY" => Ok(EventoConexion::IDENTIFY),
             "STATUS" => Ok(EventoConexion::STATUS),
             "USERS" => Ok(EventoConexion::USERS),
@@ -48,6 +56,7 @@ impl FromStr for EventoConexion {
             "JOINROOM" => Ok(EventoConexion::JOINROOM),
             "ROOMESSAGE" => Ok(EventoConexion::ROOMESSAGE),
             "DISCONNECT" => Ok(EventoConexion::DISCONNECT),
+            "SHUTDOWN" => Ok(EventoConexion::SHUTDOWN),
             "INVALID" => Ok(EventoConexion::INVALID),
             "ERROR" => Ok(EventoConexion::ERROR),
             _ => Err(()),
@@ -1,9 +1,12 @@
+use super::{cliente::IdCliente, estadocliente::EstadoCliente};
 use std::str::FromStr;
+use std::fmt;
 
 #[derive(Clone, Debug, PartialEq)]
 /// Eventos del servidor. Los eventos permiten saber que ha ocurrido durante la ejecución
 /// del servidor. Generalmente dichos eventos son enviados a los escuchas que se han
-/// "suscrito" al servidor, enviando la representación en cadena de cada evento.
+/// "suscrito" al servidor, los cuales pueden recibir solo los eventos de ciertos
+/// [`TipoEvento`](enum.TipoEvento.html).
 pub enum EventoServidor {
     /// Si el servidor empieza a aceptar conexiones.
     ServidorArriba,
@@ -14,18 +17,136 @@ pub enum EventoServidor {
     ServidorAbajo,
     /// Si el evento es inválido.
     EventoInvalido,
+    /// Si un cliente se identifica o cambia su nombre.
+    ClienteIdentificado {
+        id: IdCliente,
+        nombre: String,
+    },
+    /// Si un cliente se desconecta, con su nombre si llegó a identificarse.
+    ClienteDesconectado {
+        id: IdCliente,
+        nombre: Option<String>,
+    },
+    /// Si un cliente identificado cambia su estado.
+    EstadoCambiado {
+        nombre: String,
+        estado: EstadoCliente,
+    },
+    /// Si un cliente crea una sala. Crear una sala no requiere identificarse, por lo que
+    /// el propietario se indica con su identificador.
+    SalaCreada {
+        sala: String,
+        propietario: IdCliente,
+    },
+    /// Si el propietario de una sala invita a un usuario a unirse a ella.
+    InvitacionEnviada {
+        sala: String,
+        anfitrion: String,
+        invitado: String,
+    },
+    /// Si un usuario invitado se une a una sala.
+    MiembroUnido {
+        sala: String,
+        usuario: String,
+    },
+    /// Si un usuario envía un mensaje público.
+    MensajePublico {
+        remitente: String,
+        texto: String,
+    },
+    /// Si un usuario envía un mensaje privado a otro.
+    MensajePrivado {
+        remitente: String,
+        destinatario: String,
+        texto: String,
+    },
+    /// Si un usuario envía un mensaje a una sala.
+    MensajeSala {
+        sala: String,
+        remitente: String,
+        texto: String,
+    },
 }
 
-impl FromStr for EventoServidor {
+impl EventoServidor {
+
+    /// Regresa el tipo del evento.
+    pub fn get_tipo(&self) -> TipoEvento {
+        match *self {
+            EventoServidor::ServidorArriba => TipoEvento::ServidorArriba,
+            EventoServidor::NuevoCliente => TipoEvento::NuevoCliente,
+            EventoServidor::ServidorAbajo => TipoEvento::ServidorAbajo,
+            EventoServidor::EventoInvalido => TipoEvento::EventoInvalido,
+            EventoServidor::ClienteIdentificado { .. } => TipoEvento::ClienteIdentificado,
+            EventoServidor::ClienteDesconectado { .. } => TipoEvento::ClienteDesconectado,
+            EventoServidor::EstadoCambiado { .. } => TipoEvento::EstadoCambiado,
+            EventoServidor::SalaCreada { .. } => TipoEvento::SalaCreada,
+            EventoServidor::InvitacionEnviada { .. } => TipoEvento::InvitacionEnviada,
+            EventoServidor::MiembroUnido { .. } => TipoEvento::MiembroUnido,
+            EventoServidor::MensajePublico { .. } => TipoEvento::MensajePublico,
+            EventoServidor::MensajePrivado { .. } => TipoEvento::MensajePrivado,
+            EventoServidor::MensajeSala { .. } => TipoEvento::MensajeSala,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Tipos de los eventos del servidor, sin su contenido. Permiten a los escuchas elegir
+/// qué eventos recibir.
+pub enum TipoEvento {
+    /// El servidor empieza a aceptar conexiones.
+    ServidorArriba,
+    /// Se acepta una nueva conexión.
+    NuevoCliente,
+    /// El servidor deja de aceptar conexiones.
+    ServidorAbajo,
+    /// Un evento inválido.
+    EventoInvalido,
+    /// Un cliente se identifica o cambia su nombre.
+    ClienteIdentificado,
+    /// Un cliente se desconecta.
+    ClienteDesconectado,
+    /// Un cliente identificado cambia su estado.
+    EstadoCambiado,
+    /// Un cliente crea una sala.
+    SalaCreada,
+    /// El propietario de una sala invita a un usuario.
+    InvitacionEnviada,
+    /// Un usuario invitado se une a una sala.
+    MiembroUnido,
+    /// Un usuario envía un mensaje público.
+    MensajePublico,
+    /// Un usuario envía un mensaje privado.
+    MensajePrivado,
+    /// Un usuario envía un mensaje a una sala.
+    MensajeSala,
+}
+
+impl FromStr for TipoEvento {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "ServidorArriba" => Ok(EventoServidor::ServidorArriba),
-            "NuevoCliente" => Ok(EventoServidor::NuevoCliente),
-            "ServidorAbajo" => Ok(EventoServidor::ServidorAbajo),
-            "EventoInvalido" => Ok(EventoServidor::EventoInvalido),
+            "ServidorArriba" => Ok(TipoEvento::ServidorArriba),
+            "NuevoCliente" => Ok(TipoEvento::NuevoCliente),
+            "ServidorAbajo" => Ok(TipoEvento::ServidorAbajo),
+            "EventoInvalido" => Ok(TipoEvento::EventoInvalido),
+            "ClienteIdentificado" => Ok(TipoEvento::ClienteIdentificado),
+            "ClienteDesconectado" => Ok(TipoEvento::ClienteDesconectado),
+            "EstadoCambiado" => Ok(TipoEvento::EstadoCambiado),
+            "SalaCreada" => Ok(TipoEvento::SalaCreada),
+            "InvitacionEnviada" => Ok(TipoEvento::InvitacionEnviada),
+            "MiembroUnido" => Ok(TipoEvento::MiembroUnido),
+            "MensajePublico" => Ok(TipoEvento::MensajePublico),
+            "MensajePrivado" => Ok(TipoEvento::MensajePrivado),
+            "MensajeSala" => Ok(TipoEvento::MensajeSala),
             _ => Err(()),
         }
     }
 }
+
+impl fmt::Display for TipoEvento {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
@@ -0,0 +1,117 @@
+use super::{cliente::Cliente, comando::Comando, mensajeservidor::MensajeServidor};
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+
+/// Decisión de un [`Filtro`](trait.Filtro.html) sobre un comando o un mensaje.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decision<T> {
+    /// Dejar pasar el comando o mensaje sin cambios.
+    Permitir,
+    /// Reemplazar el comando o mensaje por otro, que reciben los filtros siguientes.
+    Reescribir(T),
+    /// Descartar el comando o mensaje, por la razón indicada.
+    Rechazar(String),
+}
+
+/// Lógica que se conecta al servidor para interceptar los comandos de los clientes y los
+/// mensajes que el servidor les envía, sin modificar al servidor. Cada método puede permitir,
+/// reescribir o rechazar lo que recibe; por omisión ambos lo permiten, de modo que un filtro
+/// solo implementa los que necesita. Usos comunes son censurar palabras, auditar comandos o
+/// eliminar enlaces.
+/// Los filtros se ejecutan desde los hilos que atienden a los clientes, por lo que deben
+/// poder compartirse entre hilos, y no deben bloquear.
+pub trait Filtro: Send + Sync {
+
+    /// Revisa un comando ya interpretado antes de realizarlo. El cliente es el remitente,
+    /// tal como está en el registro del servidor. Si se rechaza, el comando no se realiza y
+    /// el cliente recibe una respuesta
+    /// [`ERR_REJECTED`](../respuesta/enum.CodigoRespuesta.html#variant.ERR_REJECTED) con
+    /// la razón.
+    fn filtrar_comando(&self, _cliente: &Cliente, _comando: &Comando) -> Decision<Comando> {
+        Decision::Permitir
+    }
+
+    /// Revisa un mensaje antes de entregarlo a su destinatario, ya sea una notificación o
+    /// una respuesta. Si se rechaza, el mensaje se descarta sin avisar al destinatario;
+    /// rechazar una respuesta deja al comando sin contestar.
+    fn filtrar_mensaje(&self, _destinatario: &Cliente, _mensaje: &MensajeServidor)
+        -> Decision<MensajeServidor> {
+        Decision::Permitir
+    }
+}
+
+/// Lista de filtros de un servidor, que se aplican en el orden en que se agregaron. Las
+/// copias de la lista comparten los mismos filtros.
+#[derive(Clone)]
+pub struct Filtros {
+    filtros: Arc<Vec<Box<dyn Filtro>>>,
+}
+
+impl Default for Filtros {
+    fn default() -> Filtros {
+        Filtros::new()
+    }
+}
+
+impl Filtros {
+
+    /// Crea una lista de filtros vacía.
+    pub fn new() -> Filtros {
+        Filtros {
+            filtros: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Agrega un filtro al final de la lista. Los filtros sólo pueden agregarse mientras
+    /// la lista no tenga copias, es decir, antes de iniciar el servidor; si las tiene,
+    /// regresa un error de tipo
+    /// [`InvalidInput`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput).
+    pub fn agregar<F: Filtro + 'static>(&mut self, filtro: F) -> Result<(), Error> {
+        match Arc::get_mut(&mut self.filtros) {
+            Some(filtros) => {
+                filtros.push(Box::new(filtro));
+                Ok(())
+            },
+            None => Err(Error::new(ErrorKind::InvalidInput,
+                "Los filtros sólo pueden agregarse mientras la lista no tenga copias")),
+        }
+    }
+
+    /// Aplica los filtros a un comando. Cada filtro recibe el comando como lo dejó el
+    /// anterior. Regresa el comando resultante, o la razón del primer filtro que lo rechazó.
+    pub fn filtrar_comando(&self, cliente: &Cliente, mut comando: Comando) -> Result<Comando, String> {
+        for filtro in self.filtros.iter() {
+            match filtro.filtrar_comando(cliente, &comando) {
+                Decision::Permitir => {},
+                Decision::Reescribir(nuevo) => comando = nuevo,
+                Decision::Rechazar(razon) => return Err(razon),
+            }
+        }
+        Ok(comando)
+    }
+
+    /// Aplica los filtros a un mensaje para un destinatario. Cada filtro recibe el mensaje
+    /// como lo dejó el anterior. Regresa el mensaje resultante, o la razón del primer filtro
+    /// que lo rechazó.
+    pub fn filtrar_mensaje(&self, destinatario: &Cliente, mut mensaje: MensajeServidor)
+        -> Result<MensajeServidor, String> {
+        for filtro in self.filtros.iter() {
+            match filtro.filtrar_mensaje(destinatario, &mensaje) {
+                Decision::Permitir => {},
+                Decision::Reescribir(nuevo) => mensaje = nuevo,
+                Decision::Rechazar(razon) => return Err(razon),
+            }
+        }
+        Ok(mensaje)
+    }
+
+    /// Regresa el número de filtros.
+    pub fn len(&self) -> usize {
+        self.filtros.len()
+    }
+
+    /// Determina si la lista no tiene filtros.
+    pub fn is_empty(&self) -> bool {
+        self.filtros.is_empty()
+    }
+}
@@ -0,0 +1,125 @@
+use super::{comando::{self, Argumento, Comando, ErrorComando, IdPeticion},
+    eventoconexion::EventoConexion, mensajeservidor::MensajeServidor};
+use serde_json::{self, Value};
+use std::str::FromStr;
+use std::fmt;
+
+/// Nombre del campo que contiene el identificador de petición en los objetos JSON.
+pub const CAMPO_ID: &str = "id";
+
+/// Nombre del campo que contiene el evento del comando en los objetos JSON.
+pub const CAMPO_COMANDO: &str = "comando";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+/// Formatos en los que se pueden representar los comandos y mensajes del protocolo.
+/// Cada conexión comienza en formato de texto y puede cambiar a JSON con el comando
+/// `PROTOCOL JSON` antes de identificarse. El servidor conserva un solo modelo de los
+/// comandos y mensajes, y los representa en el formato de cada conexión.
+pub enum Formato {
+    /// Una línea con el evento seguido de sus argumentos separados por espacios.
+    TEXT,
+    /// Un objeto JSON por línea, con los argumentos como campos.
+    JSON,
+}
+
+impl Formato {
+
+    /// Analiza una línea enviada por un cliente en este formato. Regresa el identificador
+    /// de petición, si existe, junto con el resultado de analizar el comando.
+    ///
+    /// En JSON, el comando es un objeto con el evento en el campo `comando`, sus argumentos
+    /// como campos y opcionalmente un identificador en el campo `id`, por ejemplo
+    /// `{"id":1,"comando":"MESSAGE","destinatario":"cliente","texto":"hola"}`.
+    pub fn analizar_peticion(&self, linea: &str) -> (Option<IdPeticion>, Result<Comando, ErrorComando>) {
+        match *self {
+            Formato::TEXT => comando::analizar_peticion(linea),
+            Formato::JSON => analizar_peticion_json(linea),
+        }
+    }
+
+    /// Regresa la línea para un comando en este formato, con un identificador de petición
+    /// opcional.
+    pub fn formatear_peticion(&self, id: Option<IdPeticion>, comando: &Comando) -> String {
+        match *self {
+            Formato::TEXT => match id {
+                Some(id) => comando::formatear_peticion(id, comando),
+                None => comando.to_string(),
+            },
+            Formato::JSON => {
+                let mut objeto = serde_json::to_value(comando).expect("Error al serializar el comando");
+                if let (Some(id), Some(campos)) = (id, objeto.as_object_mut()) {
+                    campos.insert(CAMPO_ID.to_owned(), Value::from(id));
+                }
+                objeto.to_string()
+            },
+        }
+    }
+
+    /// Regresa la línea para un mensaje del servidor en este formato.
+    ///
+    /// En JSON, las respuestas son objetos con `"tipo":"REPLY"`, su código, texto e
+    /// identificador, y las notificaciones son objetos con `"tipo":"EVENT"`, el evento
+    /// en el campo `evento` y sus campos.
+    pub fn formatear_mensaje(&self, mensaje: &MensajeServidor) -> String {
+        match *self {
+            Formato::TEXT => mensaje.to_string(),
+            Formato::JSON => serde_json::to_string(mensaje).expect("Error al serializar el mensaje"),
+        }
+    }
+
+    /// Analiza una línea enviada por el servidor en este formato. Como
+    /// [`MensajeServidor::from_str`](../mensajeservidor/enum.MensajeServidor.html), no
+    /// distingue la causa por la que la línea no es un mensaje válido.
+    #[allow(clippy::result_unit_err)]
+    pub fn analizar_mensaje(&self, linea: &str) -> Result<MensajeServidor, ()> {
+        match *self {
+            Formato::TEXT => linea.parse::<MensajeServidor>(),
+            Formato::JSON => serde_json::from_str::<MensajeServidor>(linea).map_err(|_| ()),
+        }
+    }
+}
+
+/// Analiza un comando representado como un objeto JSON.
+fn analizar_peticion_json(linea: &str) -> (Option<IdPeticion>, Result<Comando, ErrorComando>) {
+    let objeto = match serde_json::from_str::<Value>(linea) {
+        Ok(Value::Object(objeto)) => objeto,
+        _ => return (None, Err(ErrorComando::EventoInvalido)),
+    };
+    let id = match objeto.get(CAMPO_ID) {
+        None | Some(&Value::Null) => None,
+        Some(id) => match id.as_u64() {
+            Some(id) => Some(id),
+            None => return (None, Err(ErrorComando::ArgumentoInvalido(Argumento::Id))),
+        },
+    };
+    let evento = objeto.get(CAMPO_COMANDO).and_then(|evento| evento.as_str())
+        .and_then(|evento| evento.parse::<EventoConexion>().ok());
+    match evento {
+        None | Some(EventoConexion::SHUTDOWN) | Some(EventoConexion::INVALID) |
+        Some(EventoConexion::ERROR) =>
+            return (id, Err(ErrorComando::EventoInvalido)),
+        Some(_) => {},
+    }
+    let comando = serde_json::from_value::<Comando>(Value::Object(objeto))
+        .map_err(|error| ErrorComando::CamposInvalidos(error.to_string()))
+        .and_then(|comando| comando.validar().map(|_| comando));
+    (id, comando)
+}
+
+impl FromStr for Formato {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "TEXT" => Ok(Formato::TEXT),
+            "JSON" => Ok(Formato::JSON),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Formato {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
@@ -0,0 +1,163 @@
+use super::util::SALTO_DE_LINEA;
+use std::io::{Read, Error, ErrorKind};
+use std::{error, fmt};
+
+/// Tamaño máximo por omisión (en bytes) de un mensaje, sin contar el salto de línea.
+pub const TAMANO_MAXIMO_POR_OMISION: usize = 4096;
+
+/// Tamaño del bloque leído del flujo en cada llamada a `read`.
+const TAMANO_BLOQUE: usize = 512;
+
+/// Carácter de retorno de carro "\r", que se descarta si precede al salto de línea.
+const RETORNO_DE_CARRO: u8 = 13;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Motivos por los que el lector rechaza un mensaje. Se transportan dentro de los errores
+/// de tipo [`InvalidData`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData)
+/// que regresa el lector.
+pub enum ErrorMensaje {
+    /// El mensaje excede el tamaño máximo (en bytes) indicado.
+    TamanoExcedido(usize),
+    /// El mensaje no es UTF-8 válido.
+    CodificacionInvalida,
+}
+
+impl ErrorMensaje {
+
+    /// Regresa el motivo del rechazo que transporta un error de E/S, si lo hay.
+    pub fn de_error(error: &Error) -> Option<ErrorMensaje> {
+        error.get_ref().and_then(|interno| interno.downcast_ref::<ErrorMensaje>()).cloned()
+    }
+
+    /// Regresa un error de E/S que transporta el motivo del rechazo.
+    pub fn como_error(self) -> Error {
+        Error::new(ErrorKind::InvalidData, self)
+    }
+}
+
+impl fmt::Display for ErrorMensaje {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorMensaje::TamanoExcedido(tamano_maximo) =>
+                write!(f, "El mensaje excede el tamaño máximo de {} bytes", tamano_maximo),
+            ErrorMensaje::CodificacionInvalida => write!(f, "El mensaje no es UTF-8 válido"),
+        }
+    }
+}
+
+impl error::Error for ErrorMensaje {}
+
+/// Lector de mensajes delimitados por saltos de línea sobre un flujo de bytes.
+/// El lector conserva los datos parciales entre lecturas, por lo que un mensaje que llega
+/// en varios segmentos se reensambla, y varios mensajes que llegan en un mismo segmento
+/// se entregan uno por uno.
+pub struct LectorMensajes<R: Read> {
+    flujo: R,
+    pendiente: Vec<u8>,
+    tamano_maximo: usize,
+    descartando: bool,
+}
+
+impl<R: Read> LectorMensajes<R> {
+
+    /// Crea un nuevo lector sobre el flujo recibido, con el tamaño máximo por omisión.
+    pub fn new(flujo: R) -> LectorMensajes<R> {
+        LectorMensajes::con_tamano_maximo(flujo, TAMANO_MAXIMO_POR_OMISION)
+    }
+
+    /// Crea un nuevo lector sobre el flujo recibido, que rechaza los mensajes con más de
+    /// `tamano_maximo` bytes.
+    pub fn con_tamano_maximo(flujo: R, tamano_maximo: usize) -> LectorMensajes<R> {
+        LectorMensajes {
+            flujo,
+            pendiente: Vec::new(),
+            tamano_maximo,
+            descartando: false,
+        }
+    }
+
+    /// Regresa el tamaño máximo (en bytes) de los mensajes aceptados.
+    pub fn get_tamano_maximo(&self) -> usize {
+        self.tamano_maximo
+    }
+
+    /// Define el tamaño máximo (en bytes) de los mensajes aceptados.
+    pub fn set_tamano_maximo(&mut self, tamano_maximo: usize) {
+        self.tamano_maximo = tamano_maximo;
+    }
+
+    /// Regresa una referencia al flujo de bytes subyacente.
+    pub fn get_ref(&self) -> &R {
+        &self.flujo
+    }
+
+    /// Regresa el siguiente mensaje completo del flujo, sin el salto de línea final.
+    /// Las líneas vacías se ignoran.
+    /// Si el mensaje excede el tamaño máximo, regresa un error de tipo
+    /// [`InvalidData`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData)
+    /// y descarta el resto del mensaje, de modo que la siguiente llamada continúa con el
+    /// mensaje posterior. Lo mismo ocurre si el mensaje no es UTF-8 válido. El motivo se
+    /// obtiene con [`ErrorMensaje::de_error`](enum.ErrorMensaje.html#method.de_error).
+    /// Si el flujo termina, regresa un error de tipo
+    /// [`ConnectionAborted`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.ConnectionAborted).
+    /// Si la lectura falla, regresa el error del flujo, como uno de tipo
+    /// [`TimedOut`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.TimedOut)
+    /// si se agotó su plazo de lectura, o de tipo
+    /// [`WouldBlock`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.WouldBlock)
+    /// si el flujo no es bloqueante y no hay datos disponibles, y conserva los datos
+    /// parciales para la siguiente llamada.
+    pub fn siguiente_mensaje(&mut self) -> Result<String, Error> {
+        loop {
+            if let Some(posicion) = self.pendiente.iter().position(|&b| b == SALTO_DE_LINEA) {
+                let mut linea: Vec<u8> = self.pendiente.drain(..posicion + 1).collect();
+                linea.pop();
+                if self.descartando {
+                    self.descartando = false;
+                    continue;
+                }
+                if linea.last() == Some(&RETORNO_DE_CARRO) {
+                    linea.pop();
+                }
+                if linea.is_empty() {
+                    continue;
+                }
+                if linea.len() > self.tamano_maximo {
+                    return Err(self.error_tamano());
+                }
+                return String::from_utf8(linea)
+                    .map_err(|_| ErrorMensaje::CodificacionInvalida.como_error());
+            }
+
+            // Un retorno de carro final puede ser parte del fin de línea, por lo que no
+            // cuenta contra el tamaño máximo hasta saber si le sigue el salto de línea.
+            let mut tamano_pendiente = self.pendiente.len();
+            if self.pendiente.last() == Some(&RETORNO_DE_CARRO) {
+                tamano_pendiente -= 1;
+            }
+            if tamano_pendiente > self.tamano_maximo {
+                self.pendiente.clear();
+                if !self.descartando {
+                    self.descartando = true;
+                    return Err(self.error_tamano());
+                }
+            }
+
+            let mut bloque = [0; TAMANO_BLOQUE];
+            match self.flujo.read(&mut bloque) {
+                Ok(0) => {
+                    return Err(Error::new(ErrorKind::ConnectionAborted,
+                        "El cliente terminó la conexión"));
+                },
+                Ok(leidos) => {
+                    self.pendiente.extend_from_slice(&bloque[..leidos]);
+                },
+                Err(ref error) if error.kind() == ErrorKind::Interrupted => {},
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn error_tamano(&self) -> Error {
+        ErrorMensaje::TamanoExcedido(self.tamano_maximo).como_error()
+    }
+}
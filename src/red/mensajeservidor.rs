@@ -0,0 +1,92 @@
+use super::{respuesta::Respuesta, notificacion::Notificacion};
+use std::str::FromStr;
+use std::fmt;
+
+/// Prefijo de las líneas que contienen una respuesta a un comando.
+pub const PREFIJO_RESPUESTA: &str = "REPLY";
+
+/// Prefijo de las líneas que contienen una notificación.
+pub const PREFIJO_NOTIFICACION: &str = "EVENT";
+
+/// Marcador del identificador de petición en las respuestas a comandos que no lo incluyeron.
+pub const SIN_ID: &str = "*";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "tipo")]
+/// Mensajes que el servidor envía a sus clientes. Cada línea enviada por el servidor es
+/// una respuesta a un comando del propio cliente, con la forma `REPLY <id> <código> <texto>`,
+/// o una notificación no solicitada, con la forma `EVENT <evento> <campos>`.
+/// El identificador de las respuestas es el que el cliente adjuntó a su comando,
+/// o `*` si no adjuntó ninguno.
+pub enum MensajeServidor {
+    /// La respuesta a un comando del cliente.
+    #[serde(rename = "REPLY")]
+    Respuesta(Respuesta),
+    /// Una notificación provocada por otro cliente.
+    #[serde(rename = "EVENT")]
+    Notificacion(Notificacion),
+}
+
+impl MensajeServidor {
+
+    /// Regresa el mensaje como texto legible, sin prefijo: el texto de las respuestas, o su
+    /// código si no tienen texto, precedido de `#<id>` si tienen identificador, o la
+    /// [`descripcion`](../notificacion/enum.Notificacion.html#method.descripcion) de las
+    /// notificaciones. Así lo reciben los clientes de texto que no acordaron la capacidad
+    /// `CODES`; como los lectores de mensajes omiten las líneas vacías, el texto nunca
+    /// es vacío.
+    pub fn texto_plano(&self) -> String {
+        match *self {
+            MensajeServidor::Respuesta(ref respuesta) => {
+                let texto = match respuesta.get_texto() {
+                    "" => respuesta.get_codigo().to_string(),
+                    texto => texto.to_owned(),
+                };
+                match respuesta.get_id() {
+                    Some(id) => format!("#{} {}", id, texto),
+                    None => texto,
+                }
+            },
+            MensajeServidor::Notificacion(ref notificacion) => notificacion.descripcion(),
+        }
+    }
+}
+
+impl FromStr for MensajeServidor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut partes = s.splitn(2, ' ');
+        let prefijo = partes.next().unwrap_or("");
+        let resto = partes.next().unwrap_or("");
+        match prefijo {
+            PREFIJO_RESPUESTA => {
+                let mut partes = resto.splitn(2, ' ');
+                let id = match partes.next().unwrap_or("") {
+                    SIN_ID => None,
+                    id => Some(id.parse::<u64>().map_err(|_| ())?),
+                };
+                let mut respuesta = partes.next().unwrap_or("").parse::<Respuesta>()?;
+                respuesta.set_id(id);
+                Ok(MensajeServidor::Respuesta(respuesta))
+            },
+            PREFIJO_NOTIFICACION => {
+                Ok(MensajeServidor::Notificacion(resto.parse::<Notificacion>()?))
+            },
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for MensajeServidor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MensajeServidor::Respuesta(ref respuesta) => match respuesta.get_id() {
+                Some(id) => write!(f, "{} {} {}", PREFIJO_RESPUESTA, id, respuesta),
+                None => write!(f, "{} {} {}", PREFIJO_RESPUESTA, SIN_ID, respuesta),
+            },
+            MensajeServidor::Notificacion(ref notificacion) =>
+                write!(f, "{} {}", PREFIJO_NOTIFICACION, notificacion),
+        }
+    }
+}
@@ -1,11 +1,44 @@
+/// Contiene las capacidades opcionales del protocolo y su negociación.
+pub mod capacidad;
+/// Contiene los bots que participan en el chat desde el servidor como clientes virtuales.
+pub mod bot;
 /// Contiene la representación abstracta de los clientes en el servidor.
 pub mod cliente;
+/// Contiene las conexiones con los clientes y los aceptadores de conexiones, sin importar
+/// el transporte.
+pub mod conexion;
+/// Contiene la cola acotada de mensajes salientes de cada conexión.
+pub mod colasalida;
+/// Contiene los comandos del protocolo con sus argumentos y su analizador.
+pub mod comando;
+/// Contiene el error de los comandos del chat y su código de respuesta.
+pub mod error;
+/// Contiene la lista de escuchas suscritos a los eventos del servidor.
+pub mod escuchas;
 /// Contiene una enumeración de los posibles estados de los clientes.
 pub mod estadocliente;
 /// Contiene una enumeración de los eventos del protocolo de comunicación.
 pub mod eventoconexion;
 /// Contiene una enumeración de los eventos de los eventos del servidor.
 pub mod eventoservidor;
+/// Contiene los filtros que interceptan los comandos y mensajes del servidor.
+pub mod filtro;
+/// Contiene los formatos en los que se representan los mensajes del protocolo.
+pub mod formato;
+/// Contiene el lector de mensajes delimitados por saltos de línea.
+pub mod lector;
+/// Contiene los mensajes que el servidor envía a sus clientes.
+pub mod mensajeservidor;
+/// Contiene los núcleos con los que el servidor atiende las conexiones.
+pub mod nucleo;
+/// Contiene las notificaciones que el servidor envía sin ser solicitadas.
+pub mod notificacion;
+/// Contiene el núcleo del servidor basado en un reactor de eventos.
+pub mod reactor;
+/// Contiene los registros indexados de clientes y salas del servidor.
+pub mod registro;
+/// Contiene las respuestas del servidor y sus códigos.
+pub mod respuesta;
 /// Contiene la representación abstracta de las salas de chat en el servidor.
 pub mod sala;
 /// Contiene la estructura del servidor TCP.
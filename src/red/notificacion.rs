@@ -0,0 +1,119 @@
+use super::{eventoconexion::EventoConexion, util::{self, citar, citar_final}};
+use std::str::FromStr;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "evento", rename_all = "UPPERCASE")]
+/// Notificaciones que el servidor envía a un cliente sin que éste las haya solicitado,
+/// como consecuencia de los comandos de otros clientes. Cada notificación se identifica
+/// con el [`EventoConexion`](../eventoconexion/enum.EventoConexion.html) que la provocó.
+pub enum Notificacion {
+    /// Un mensaje privado de otro usuario.
+    Message { remitente: String, texto: String },
+    /// Un mensaje público de un usuario.
+    PublicMessage { remitente: String, texto: String },
+    /// Una invitación para unirse a una sala.
+    Invite { sala: String, anfitrion: String },
+    /// Un usuario se unió a una sala de la que se es miembro.
+    JoinRoom { sala: String, usuario: String },
+    /// Un mensaje enviado a una sala de la que se es miembro.
+    #[serde(rename = "ROOMESSAGE")]
+    RoomMessage { sala: String, remitente: String, texto: String },
+    /// El servidor se está deteniendo y pide a los clientes desconectarse.
+    Shutdown { texto: String },
+}
+
+impl Notificacion {
+
+    /// Regresa el evento del protocolo que provocó la notificación.
+    pub fn evento(&self) -> EventoConexion {
+        match *self {
+            Notificacion::Message { .. } => EventoConexion::MESSAGE,
+            Notificacion::PublicMessage { .. } => EventoConexion::PUBLICMESSAGE,
+            Notificacion::Invite { .. } => EventoConexion::INVITE,
+            Notificacion::JoinRoom { .. } => EventoConexion::JOINROOM,
+            Notificacion::RoomMessage { .. } => EventoConexion::ROOMESSAGE,
+            Notificacion::Shutdown { .. } => EventoConexion::SHUTDOWN,
+        }
+    }
+
+    /// Regresa una descripción legible de la notificación, para mostrarla a los usuarios.
+    pub fn descripcion(&self) -> String {
+        match *self {
+            Notificacion::Message { ref remitente, ref texto } =>
+                format!("{}: {}", remitente, texto),
+            Notificacion::PublicMessage { ref remitente, ref texto } =>
+                format!("Público-{}: {}", remitente, texto),
+            Notificacion::Invite { ref sala, ref anfitrion } =>
+                format!("Invitación de unirse a la sala {} por {}", sala, anfitrion),
+            Notificacion::JoinRoom { ref sala, ref usuario } =>
+                format!("{} se unió a la sala {}", usuario, sala),
+            Notificacion::RoomMessage { ref sala, ref remitente, ref texto } =>
+                format!("{}-{}: {}", sala, remitente, texto),
+            Notificacion::Shutdown { ref texto } => format!("Servidor: {}", texto),
+        }
+    }
+}
+
+impl FromStr for Notificacion {
+    type Err = ();
+
+    /// Analiza una notificación formada por el evento seguido de sus campos, separados como
+    /// lo indica [`separar_argumentos`](../util/fn.separar_argumentos.html), donde el texto
+    /// de los mensajes son los campos restantes unidos por un espacio.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut campos = util::separar_argumentos(s).map_err(|_| ())?;
+        if campos.len() < 2 {
+            return Err(());
+        }
+        let evento = campos.remove(0).parse::<EventoConexion>()?;
+        let primero = campos.remove(0);
+        match evento {
+            EventoConexion::MESSAGE if !campos.is_empty() => Ok(Notificacion::Message {
+                remitente: primero,
+                texto: campos.join(" "),
+            }),
+            EventoConexion::PUBLICMESSAGE if !campos.is_empty() => Ok(Notificacion::PublicMessage {
+                remitente: primero,
+                texto: campos.join(" "),
+            }),
+            EventoConexion::INVITE if campos.len() == 1 => Ok(Notificacion::Invite {
+                sala: primero,
+                anfitrion: campos.remove(0),
+            }),
+            EventoConexion::JOINROOM if campos.len() == 1 => Ok(Notificacion::JoinRoom {
+                sala: primero,
+                usuario: campos.remove(0),
+            }),
+            EventoConexion::ROOMESSAGE if campos.len() > 1 => Ok(Notificacion::RoomMessage {
+                sala: primero,
+                remitente: campos.remove(0),
+                texto: campos.join(" "),
+            }),
+            EventoConexion::SHUTDOWN => {
+                campos.insert(0, primero);
+                Ok(Notificacion::Shutdown { texto: campos.join(" ") })
+            },
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Notificacion {
+    /// Escribe la notificación con sus campos citados, y el texto de los mensajes al final.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let evento = self.evento();
+        match *self {
+            Notificacion::Message { ref remitente, ref texto } |
+            Notificacion::PublicMessage { ref remitente, ref texto } =>
+                write!(f, "{} {} {}", evento, citar(remitente), citar_final(texto)),
+            Notificacion::Invite { ref sala, anfitrion: ref usuario } |
+            Notificacion::JoinRoom { ref sala, ref usuario } =>
+                write!(f, "{} {} {}", evento, citar(sala), citar(usuario)),
+            Notificacion::RoomMessage { ref sala, ref remitente, ref texto } =>
+                write!(f, "{} {} {} {}", evento, citar(sala), citar(remitente), citar_final(texto)),
+            Notificacion::Shutdown { ref texto } =>
+                write!(f, "{} {}", evento, citar_final(texto)),
+        }
+    }
+}
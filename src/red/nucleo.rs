@@ -0,0 +1,37 @@
+use std::str::FromStr;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Núcleos con los que el servidor puede atender las conexiones. Ambos núcleos implementan
+/// el mismo protocolo y procesan los comandos de la misma forma.
+pub enum Nucleo {
+    /// Un hilo de ejecución que lee cada conexión y otro que vacía su cola de salida, con
+    /// los registros de clientes y salas compartidos entre hilos. Cada cliente ocupa dos
+    /// hilos del sistema operativo, por lo que el número de conexiones que puede atender
+    /// está limitado por el de hilos del proceso.
+    Hilos,
+    /// Un solo hilo de ejecución que atiende todas las conexiones con un reactor de eventos,
+    /// y que es el único dueño de los registros de clientes y salas.
+    Reactor,
+}
+
+impl FromStr for Nucleo {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hilos" => Ok(Nucleo::Hilos),
+            "reactor" => Ok(Nucleo::Reactor),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Nucleo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Nucleo::Hilos => write!(f, "hilos"),
+            Nucleo::Reactor => write!(f, "reactor"),
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use super::{cliente::{Cliente, IdCliente}, colasalida::ColaSalida, escuchas::Escuchas,
+    eventoservidor::EventoServidor, filtro::Filtros, bot::Bots,
+    lector::LectorMensajes, mensajeservidor::MensajeServidor, notificacion::Notificacion,
+    registro::{RegistroClientes, RegistroSalas},
+    servidor::{Parada, Servidor, ServidorHandle, TEXTO_DRENADO}};
+use mio::{Events, Poll, PollOpt, Ready, Token};
+use mio::net::{TcpListener, TcpStream};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::io::{Error, ErrorKind, Write};
+use std::{cmp, net};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Token con el que se registra el escucha de conexiones en el reactor.
+const ESCUCHA: Token = Token(0);
+
+/// Número de eventos que el reactor atiende en cada espera.
+const CAPACIDAD_EVENTOS: usize = 1024;
+
+/// Número máximo de mensajes de una conexión que el reactor procesa por cada evento o vuelta,
+/// para que un cliente que envía ráfagas no acapare al hilo del reactor.
+const MENSAJES_POR_LOTE: usize = 64;
+
+/// Número de bytes pendientes de escribir en una conexión a partir del cual el reactor deja
+/// de extraer mensajes de la cola de salida del cliente, de modo que la cola se llena si el
+/// cliente no lee.
+const TAMANO_MAXIMO_SALIDA: usize = 64 * 1024;
+
+/// Tiempo máximo que el reactor espera eventos mientras se drena, para revisar el plazo y
+/// si se solicitó detenerlo de inmediato.
+const ESPERA_DRENADO: u64 = 100;
+
+/// Tiempo (en milisegundos) que el reactor espera para volver a aceptar conexiones después de
+/// un error que no es propio de una conexión, como exceder el límite de descriptores abiertos.
+const ESPERA_ACEPTAR: u64 = 50;
+
+/// Conexión atendida por el reactor: la copia del cliente con la que se procesan sus
+/// comandos, el lector de sus mensajes, el receptor de su cola de salida, los bytes que
+/// aún no se escriben en el socket, el instante desde el cual el socket no los acepta y si
+/// la conexión está en la lista de conexiones con mensajes por leer.
+/// Mientras el socket no acepta los bytes pendientes, la conexión está registrada en el
+/// reactor para escritura además de lectura.
+struct Conexion {
+    cliente: Cliente,
+    lector: LectorMensajes<TcpStream>,
+    receptor: mpsc::Receiver<Vec<u8>>,
+    salida: Vec<u8>,
+    bloqueada_desde: Option<Instant>,
+    por_leer: bool,
+}
+
+impl Conexion {
+
+    /// Escribe en el socket los mensajes de la cola de salida hasta que se vacía o el socket
+    /// deja de aceptar datos. Regresa un error si la conexión falló.
+    fn escribir(&mut self) -> Result<(), Error> {
+        loop {
+            while self.salida.len() < TAMANO_MAXIMO_SALIDA {
+                match self.receptor.try_recv() {
+                    Ok(mensaje) => self.salida.extend_from_slice(&mensaje[..]),
+                    Err(_) => break,
+                }
+            }
+            if self.salida.is_empty() {
+                self.bloqueada_desde = None;
+                return Ok(());
+            }
+            let mut flujo = self.lector.get_ref();
+            match flujo.write(&self.salida[..]) {
+                Ok(0) => return Err(Error::new(ErrorKind::WriteZero, "La conexión no acepta datos")),
+                Ok(escritos) => {
+                    self.salida.drain(..escritos);
+                    self.bloqueada_desde = None;
+                },
+                Err(ref error) if error.kind() == ErrorKind::WouldBlock => {
+                    self.bloqueada_desde.get_or_insert_with(Instant::now);
+                    return Ok(());
+                },
+                Err(ref error) if error.kind() == ErrorKind::Interrupted => {},
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+/// Núcleo del servidor que atiende todas las conexiones desde un solo hilo de ejecución,
+/// con un reactor de eventos de [`mio`](https://docs.rs/mio/0.6). Las conexiones no
+/// bloquean al hilo: el reactor lee de ellas cuando tienen datos y escribe cuando aceptan
+/// datos. Cada cola de salida avisa al reactor cuando recibe un mensaje, de modo que sólo
+/// se escribe en las conexiones con mensajes nuevos o que vuelven a aceptar datos, y los
+/// plazos de las conexiones que no aceptan datos se guardan ordenados, por lo que el costo
+/// de cada evento no depende del número de conexiones. De cada conexión se procesan a lo
+/// más [`MENSAJES_POR_LOTE`](constant.MENSAJES_POR_LOTE.html) mensajes seguidos; si le
+/// quedan datos, pasa a una lista que se atiende en la siguiente vuelta, después de
+/// escribir los mensajes pendientes de todas las conexiones.
+/// Los registros de clientes y salas pertenecen al reactor, por lo que no se
+/// comparten entre hilos ni requieren candados; los comandos se procesan con
+/// [`Servidor::procesar_mensaje`](../servidor/struct.Servidor.html#method.procesar_mensaje),
+/// al igual que en el núcleo de hilos.
+pub struct Reactor {
+    poll: Poll,
+    escucha: Option<TcpListener>,
+    reintento_aceptar: Option<Instant>,
+    conexiones: HashMap<IdCliente, Conexion>,
+    por_leer: VecDeque<IdCliente>,
+    limites_escritura: BTreeSet<(Instant, IdCliente)>,
+    aviso: mpsc::Sender<IdCliente>,
+    avisos: mpsc::Receiver<IdCliente>,
+    clientes: RegistroClientes,
+    salas: RegistroSalas,
+    escuchas: Escuchas,
+    filtros: Filtros,
+    bots: Bots,
+    siguiente_id: IdCliente,
+    tamano_maximo_mensaje: usize,
+    capacidad_cola: usize,
+    plazo_saturacion: Duration,
+}
+
+impl Reactor {
+
+    /// Crea un reactor que acepta conexiones del escucha recibido, anuncia los eventos del
+    /// chat a los escuchas del servidor, aplica sus filtros y conecta a sus bots, con el
+    /// tamaño máximo de los mensajes, la capacidad y el plazo de saturación de las colas de
+    /// salida del servidor.
+    pub fn new(escucha: net::TcpListener, escuchas: Escuchas, filtros: Filtros, bots: Bots,
+        tamano_maximo_mensaje: usize, capacidad_cola: usize, plazo_saturacion: Duration)
+        -> Result<Reactor, Error> {
+        let poll = Poll::new()?;
+        let escucha = TcpListener::from_std(escucha)?;
+        poll.register(&escucha, ESCUCHA, Ready::readable(), PollOpt::edge())?;
+        let mut clientes = RegistroClientes::new();
+        let mut siguiente_id = 0;
+        bots.conectar(&mut clientes, &mut siguiente_id, capacidad_cola, plazo_saturacion);
+        let (aviso, avisos) = mpsc::channel();
+        Ok(Reactor {
+            poll,
+            escucha: Some(escucha),
+            reintento_aceptar: None,
+            conexiones: HashMap::new(),
+            por_leer: VecDeque::new(),
+            limites_escritura: BTreeSet::new(),
+            aviso,
+            avisos,
+            clientes,
+            salas: RegistroSalas::new(),
+            escuchas,
+            filtros,
+            bots,
+            siguiente_id,
+            tamano_maximo_mensaje,
+            capacidad_cola,
+            plazo_saturacion,
+        })
+    }
+
+    /// Atiende conexiones hasta que se solicita detener al servidor mediante su controlador,
+    /// anunciando a los escuchas del servidor cada nuevo cliente. Si se solicita drenarlo,
+    /// deja de aceptar conexiones, avisa a los clientes y sigue atendiéndolos hasta que se
+    /// desconecten o se cumpla el plazo. Al terminar cierra las conexiones restantes.
+    pub fn ejecutar(mut self, servidor: &mut Servidor, control: &ServidorHandle) {
+        let mut eventos = Events::with_capacity(CAPACIDAD_EVENTOS);
+        let mut limite_drenado: Option<Instant> = None;
+        loop {
+            let mut espera = limite_drenado.map(|_| Duration::from_millis(ESPERA_DRENADO));
+            if let Some(reintento) = self.reintento_aceptar {
+                espera = acotar_espera(espera, reintento);
+            }
+            if let Some(&(limite, _)) = self.limites_escritura.iter().next() {
+                espera = acotar_espera(espera, limite);
+            }
+            if !self.por_leer.is_empty() {
+                espera = Some(Duration::from_millis(0));
+            }
+            if let Err(error) = self.poll.poll(&mut eventos, espera) {
+                if error.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                error!(target: "Servidor", "Error en el reactor: {}", error);
+                break;
+            }
+            self.leer_pendientes();
+            for evento in eventos.iter() {
+                match evento.token() {
+                    ESCUCHA => self.aceptar(servidor, control),
+                    Token(token) => {
+                        let id = (token - 1) as IdCliente;
+                        if evento.readiness().is_readable() {
+                            self.leer(id);
+                        }
+                        if evento.readiness().is_writable() {
+                            self.escribir(id);
+                        }
+                    },
+                }
+            }
+            if self.reintento_aceptar.is_some_and(|reintento| Instant::now() >= reintento) {
+                self.aceptar(servidor, control);
+            }
+            let terminar = match control.get_parada() {
+                None => false,
+                Some(Parada::Inmediata) => true,
+                Some(Parada::Drenado(plazo)) => {
+                    let limite = match limite_drenado {
+                        Some(limite) => limite,
+                        None => {
+                            info!(target: "Servidor", "Drenando servidor");
+                            self.escucha = None;
+                            self.avisar_clientes(&Notificacion::Shutdown {
+                                texto: TEXTO_DRENADO.to_owned(),
+                            });
+                            let limite = Instant::now() + plazo;
+                            limite_drenado = Some(limite);
+                            limite
+                        },
+                    };
+                    self.conexiones.is_empty() || Instant::now() >= limite
+                },
+            };
+            self.escribir_pendientes();
+            if terminar {
+                break;
+            }
+        }
+        info!(target: "Servidor", "Desconectando servidor");
+        let ids: Vec<IdCliente> = self.conexiones.keys().cloned().collect();
+        for id in ids {
+            self.desconectar(id);
+        }
+        info!(target: "Servidor", "Clientes eliminados");
+    }
+
+    /// Acepta todas las conexiones pendientes. Si ya se solicitó detener al servidor, las
+    /// conexiones se descartan, incluida la que abre el controlador para despertarlo.
+    /// Los errores de una sola conexión, como que el cliente la abortara, no interrumpen la
+    /// aceptación. Con cualquier otro error, como exceder el límite de descriptores abiertos,
+    /// las conexiones pendientes se aceptan después de
+    /// [`ESPERA_ACEPTAR`](constant.ESPERA_ACEPTAR.html) milisegundos, pues el escucha sólo
+    /// avisa al reactor de las conexiones nuevas.
+    fn aceptar(&mut self, servidor: &mut Servidor, control: &ServidorHandle) {
+        self.reintento_aceptar = None;
+        loop {
+            let resultado = match self.escucha {
+                Some(ref escucha) => escucha.accept_std(),
+                None => return,
+            };
+            let (socket, direccion) = match resultado {
+                Ok(conexion) => conexion,
+                Err(ref error) if error.kind() == ErrorKind::WouldBlock => return,
+                Err(ref error) if es_error_de_conexion(error) => {
+                    debug!(target: "Servidor", "Se descartó una conexión: {}", error);
+                    continue;
+                },
+                Err(error) => {
+                    warn!(target: "Servidor", "Error al aceptar una conexión: {}", error);
+                    self.reintento_aceptar = Some(Instant::now() + Duration::from_millis(ESPERA_ACEPTAR));
+                    return;
+                },
+            };
+            if control.get_parada().is_some() {
+                continue;
+            }
+            match self.registrar(socket, direccion) {
+                Ok(_) => servidor.anunciar_escuchas(EventoServidor::NuevoCliente),
+                Err(error) => warn!(target: "Servidor",
+                    "No se pudo registrar al cliente {}: {}", direccion, error),
+            }
+        }
+    }
+
+    /// Crea un nuevo cliente con un identificador único para el socket aceptado, lo guarda
+    /// en el registro de clientes y registra su conexión en el reactor.
+    fn registrar(&mut self, socket: net::TcpStream, direccion: net::SocketAddr) -> Result<(), Error> {
+        let id = self.siguiente_id;
+        let flujo = TcpStream::from_stream(socket.try_clone()?)?;
+        self.poll.register(&flujo, Token(id as usize + 1), Ready::readable(), PollOpt::edge())?;
+        self.siguiente_id += 1;
+        let (mut cola, receptor) = ColaSalida::con_receptor(self.capacidad_cola, self.plazo_saturacion);
+        cola.set_aviso(id, self.aviso.clone());
+        let cliente = Cliente::con_cola_salida(id, None, Box::new(socket), cola);
+        self.clientes.agregar(cliente.clone());
+        self.conexiones.insert(id, Conexion {
+            cliente,
+            lector: LectorMensajes::con_tamano_maximo(flujo, self.tamano_maximo_mensaje),
+            receptor,
+            salida: Vec::new(),
+            bloqueada_desde: None,
+            por_leer: false,
+        });
+        info!(target: "Servidor", "Nuevo cliente {}: {}", id, direccion);
+        Ok(())
+    }
+
+    /// Procesa los mensajes completos disponibles en la conexión de un cliente, a lo más
+    /// [`MENSAJES_POR_LOTE`](constant.MENSAJES_POR_LOTE.html). Si quedan datos por leer, la
+    /// conexión pasa a la lista de conexiones por leer, pues el reactor sólo avisa de los
+    /// datos nuevos. Si la conexión falló o el cliente especificó su desconexión, lo
+    /// desconecta.
+    fn leer(&mut self, id: IdCliente) {
+        for _ in 0..MENSAJES_POR_LOTE {
+            let resultado = {
+                let conexion = match self.conexiones.get_mut(&id) {
+                    Some(conexion) => conexion,
+                    None => return,
+                };
+                let mensaje = conexion.lector.siguiente_mensaje();
+                if let Err(ref error) = mensaje {
+                    if error.kind() == ErrorKind::WouldBlock {
+                        return;
+                    }
+                }
+                let resultado = Servidor::procesar_mensaje(&mut conexion.cliente, &mut self.clientes,
+                    &mut self.salas, &self.escuchas, &self.filtros, mensaje);
+                self.bots.atender(&mut self.clientes, &mut self.salas, &self.escuchas, &self.filtros);
+                resultado
+            };
+            if resultado.is_err() {
+                warn!(target: "Servidor", "Se perdió la conexión con el cliente {}", id);
+                self.desconectar(id);
+                return;
+            }
+        }
+        if let Some(conexion) = self.conexiones.get_mut(&id) {
+            if !conexion.por_leer {
+                conexion.por_leer = true;
+                self.por_leer.push_back(id);
+            }
+        }
+    }
+
+    /// Procesa otro lote de mensajes de cada conexión que quedó con datos por leer en la
+    /// vuelta anterior. Las que vuelven a agotar su lote se atienden en la siguiente vuelta.
+    fn leer_pendientes(&mut self) {
+        let pendientes: Vec<IdCliente> = self.por_leer.drain(..).collect();
+        for id in pendientes {
+            match self.conexiones.get_mut(&id) {
+                Some(conexion) => conexion.por_leer = false,
+                None => continue,
+            }
+            self.leer(id);
+        }
+    }
+
+    /// Escribe los mensajes pendientes de las conexiones cuyas colas de salida recibieron
+    /// mensajes, y desconecta a los clientes cuya conexión no ha aceptado datos durante el
+    /// plazo de saturación.
+    fn escribir_pendientes(&mut self) {
+        let avisadas: HashSet<IdCliente> = self.avisos.try_iter().collect();
+        for id in avisadas {
+            self.escribir(id);
+        }
+        let ahora = Instant::now();
+        let vencidas: Vec<IdCliente> = self.limites_escritura.iter()
+            .take_while(|&&(limite, _)| limite <= ahora)
+            .map(|&(_, id)| id)
+            .collect();
+        for id in vencidas {
+            warn!(target: "Servidor", "El cliente {} no aceptó datos durante el plazo", id);
+            self.desconectar(id);
+        }
+    }
+
+    /// Escribe los mensajes pendientes de la conexión de un cliente. Si el socket deja de
+    /// aceptar datos, la conexión se registra para escritura y su plazo se agrega a los
+    /// límites de escritura; al escribirlos todos deja de registrarse para escritura.
+    /// Si la conexión falló, desconecta al cliente.
+    fn escribir(&mut self, id: IdCliente) {
+        let plazo = self.plazo_saturacion;
+        let resultado = {
+            let conexion = match self.conexiones.get_mut(&id) {
+                Some(conexion) => conexion,
+                None => return,
+            };
+            let antes = conexion.bloqueada_desde;
+            let resultado = conexion.escribir();
+            let despues = conexion.bloqueada_desde;
+            if antes != despues {
+                if let Some(desde) = antes {
+                    self.limites_escritura.remove(&(desde + plazo, id));
+                }
+                if let Some(desde) = despues {
+                    self.limites_escritura.insert((desde + plazo, id));
+                }
+            }
+            if resultado.is_ok() && antes.is_some() != despues.is_some() {
+                let interes = if despues.is_some() {
+                    Ready::readable() | Ready::writable()
+                } else {
+                    Ready::readable()
+                };
+                self.poll.reregister(conexion.lector.get_ref(), Token(id as usize + 1), interes,
+                    PollOpt::edge())
+            } else {
+                resultado
+            }
+        };
+        if let Err(error) = resultado {
+            warn!(target: "Servidor", "Se perdió la conexión con el cliente {}: {}", id, error);
+            self.desconectar(id);
+        }
+    }
+
+    /// Envía una notificación a todos los clientes conectados.
+    fn avisar_clientes(&mut self, notificacion: &Notificacion) {
+        let mensaje = MensajeServidor::Notificacion(notificacion.clone());
+        for cliente in self.clientes.iter_mut() {
+            let _ = Servidor::entregar(cliente, &mensaje, &self.filtros);
+        }
+    }
+
+    /// Elimina la conexión de un cliente del reactor y al cliente de los registros,
+    /// cerrando su socket.
+    fn desconectar(&mut self, id: IdCliente) {
+        if let Some(conexion) = self.conexiones.remove(&id) {
+            if let Some(desde) = conexion.bloqueada_desde {
+                self.limites_escritura.remove(&(desde + self.plazo_saturacion, id));
+            }
+            if let Err(error) = self.poll.deregister(conexion.lector.get_ref()) {
+                debug!(target: "Servidor", "No se pudo retirar al cliente {} del reactor: {}", id, error);
+            }
+            Servidor::desconectar_cliente(&conexion.cliente, &mut self.clientes, &mut self.salas,
+                &self.escuchas);
+        }
+    }
+}
+
+/// Regresa la espera recibida, acortada para que termine a más tardar en el límite.
+fn acotar_espera(espera: Option<Duration>, limite: Instant) -> Option<Duration> {
+    let restante = limite.saturating_duration_since(Instant::now());
+    Some(espera.map_or(restante, |espera| cmp::min(espera, restante)))
+}
+
+/// Determina si un error al aceptar corresponde sólo a la conexión que se aceptaba, por lo que
+/// pueden aceptarse las siguientes de inmediato.
+fn es_error_de_conexion(error: &Error) -> bool {
+    matches!(error.kind(), ErrorKind::ConnectionAborted | ErrorKind::ConnectionReset | ErrorKind::Interrupted)
+}
@@ -0,0 +1,199 @@
+use super::{cliente::{Cliente, IdCliente}, sala::Sala};
+use std::collections::HashMap;
+use std::collections::hash_map::{Values, ValuesMut};
+
+/// Registro de los clientes conectados al servidor, indexado por identificador y por nombre.
+/// Las operaciones que verifican y modifican el registro se realizan en una sola llamada,
+/// por lo que son atómicas mientras el registro esté protegido por un mismo candado.
+pub struct RegistroClientes {
+    clientes: HashMap<IdCliente, Cliente>,
+    nombres: HashMap<String, IdCliente>,
+}
+
+impl Default for RegistroClientes {
+    fn default() -> RegistroClientes {
+        RegistroClientes::new()
+    }
+}
+
+impl RegistroClientes {
+
+    /// Crea un registro vacío.
+    pub fn new() -> RegistroClientes {
+        RegistroClientes {
+            clientes: HashMap::new(),
+            nombres: HashMap::new(),
+        }
+    }
+
+    /// Agrega un cliente al registro, reemplazando al que tuviera el mismo identificador.
+    pub fn agregar(&mut self, cliente: Cliente) {
+        self.eliminar(cliente.get_id());
+        if let Some(nombre) = cliente.get_nombre().clone() {
+            self.nombres.insert(nombre, cliente.get_id());
+        }
+        self.clientes.insert(cliente.get_id(), cliente);
+    }
+
+    /// Elimina del registro al cliente con el identificador, regresándolo si existía.
+    pub fn eliminar(&mut self, id: IdCliente) -> Option<Cliente> {
+        let cliente = self.clientes.remove(&id)?;
+        if let Some(nombre) = cliente.get_nombre() {
+            self.nombres.remove(nombre);
+        }
+        Some(cliente)
+    }
+
+    /// Regresa el cliente con el identificador.
+    pub fn obtener(&self, id: IdCliente) -> Option<&Cliente> {
+        self.clientes.get(&id)
+    }
+
+    /// Regresa el cliente con el identificador, para modificarlo. El nombre de los clientes
+    /// debe modificarse con [`asignar_nombre`](#method.asignar_nombre) para mantener el índice.
+    pub fn obtener_mut(&mut self, id: IdCliente) -> Option<&mut Cliente> {
+        self.clientes.get_mut(&id)
+    }
+
+    /// Regresa el identificador del cliente con el nombre.
+    pub fn id_de(&self, nombre: &str) -> Option<IdCliente> {
+        self.nombres.get(nombre).cloned()
+    }
+
+    /// Regresa el cliente con el nombre, para modificarlo.
+    pub fn buscar_por_nombre_mut(&mut self, nombre: &str) -> Option<&mut Cliente> {
+        let id = self.id_de(nombre)?;
+        self.clientes.get_mut(&id)
+    }
+
+    /// Regresa el nombre del cliente con el identificador, si existe y ya se identificó.
+    pub fn nombre_de(&self, id: IdCliente) -> Option<String> {
+        self.clientes.get(&id).and_then(|cliente| cliente.get_nombre().clone())
+    }
+
+    /// Asigna un nombre al cliente con el identificador, liberando su nombre anterior.
+    /// La verificación de que el nombre esté libre y la asignación ocurren en la misma
+    /// operación. Regresa `false` si el nombre ya pertenece a otro cliente o si el cliente
+    /// no existe.
+    pub fn asignar_nombre(&mut self, id: IdCliente, nombre: &str) -> bool {
+        if let Some(&propietario) = self.nombres.get(nombre) {
+            return propietario == id;
+        }
+        let cliente = match self.clientes.get_mut(&id) {
+            Some(cliente) => cliente,
+            None => return false,
+        };
+        if let Some(anterior) = cliente.get_nombre().clone() {
+            self.nombres.remove(&anterior);
+        }
+        cliente.set_nombre(nombre);
+        self.nombres.insert(nombre.to_owned(), id);
+        true
+    }
+
+    /// Regresa los nombres de los clientes identificados, en el orden en que se conectaron.
+    pub fn nombres(&self) -> Vec<String> {
+        let mut identificados: Vec<(&IdCliente, &String)> = self.nombres.iter()
+            .map(|(nombre, id)| (id, nombre)).collect();
+        identificados.sort();
+        identificados.into_iter().map(|(_, nombre)| nombre.to_owned()).collect()
+    }
+
+    /// Regresa un iterador sobre los clientes del registro.
+    pub fn iter<'a>(&'a self) -> Values<'a, IdCliente, Cliente> {
+        self.clientes.values()
+    }
+
+    /// Regresa un iterador sobre los clientes del registro, para modificarlos.
+    pub fn iter_mut<'a>(&'a mut self) -> ValuesMut<'a, IdCliente, Cliente> {
+        self.clientes.values_mut()
+    }
+
+    /// Regresa el número de clientes en el registro.
+    pub fn len(&self) -> usize {
+        self.clientes.len()
+    }
+
+    /// Determina si el registro está vacío.
+    pub fn is_empty(&self) -> bool {
+        self.clientes.is_empty()
+    }
+}
+
+/// Registro de las salas del servidor, indexado por nombre.
+pub struct RegistroSalas {
+    salas: HashMap<String, Sala>,
+}
+
+impl Default for RegistroSalas {
+    fn default() -> RegistroSalas {
+        RegistroSalas::new()
+    }
+}
+
+impl RegistroSalas {
+
+    /// Crea un registro vacío.
+    pub fn new() -> RegistroSalas {
+        RegistroSalas {
+            salas: HashMap::new(),
+        }
+    }
+
+    /// Agrega una sala al registro si su nombre no está en uso. La verificación y la
+    /// creación ocurren en la misma operación. Regresa `false` si ya existía una sala con
+    /// el mismo nombre.
+    pub fn crear(&mut self, sala: Sala) -> bool {
+        if self.salas.contains_key(sala.get_nombre()) {
+            return false;
+        }
+        self.salas.insert(sala.get_nombre().to_owned(), sala);
+        true
+    }
+
+    /// Regresa la sala con el nombre.
+    pub fn obtener(&self, nombre: &str) -> Option<&Sala> {
+        self.salas.get(nombre)
+    }
+
+    /// Regresa la sala con el nombre, para modificarla.
+    pub fn obtener_mut(&mut self, nombre: &str) -> Option<&mut Sala> {
+        self.salas.get_mut(nombre)
+    }
+
+    /// Elimina al cliente con el identificador de las listas de invitados y miembros de
+    /// todas las salas. En las salas de las que era propietario, la propiedad pasa al
+    /// miembro más antiguo, el de menor identificador; las que se quedan sin miembros se
+    /// eliminan.
+    pub fn eliminar_cliente(&mut self, id: IdCliente) {
+        self.salas.retain(|_, sala| {
+            sala.elimina_invitado(id);
+            sala.elimina_miembro(id);
+            if !sala.es_propietario(id) {
+                return true;
+            }
+            match sala.get_miembros().iter().min().cloned() {
+                Some(sucesor) => {
+                    sala.set_propietario(sucesor);
+                    true
+                },
+                None => false,
+            }
+        });
+    }
+
+    /// Regresa un iterador sobre las salas del registro.
+    pub fn iter<'a>(&'a self) -> Values<'a, String, Sala> {
+        self.salas.values()
+    }
+
+    /// Regresa el número de salas en el registro.
+    pub fn len(&self) -> usize {
+        self.salas.len()
+    }
+
+    /// Determina si el registro está vacío.
+    pub fn is_empty(&self) -> bool {
+        self.salas.is_empty()
+    }
+}
@@ -0,0 +1,173 @@
+use super::{comando::{ErrorComando, IdPeticion}, error::ChatError};
+use std::str::FromStr;
+use std::{fmt, error};
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Códigos de las respuestas del servidor. Cada comando enviado por un cliente recibe
+/// exactamente una respuesta, que inicia con el código simbólico seguido de un texto legible.
+/// Los códigos son estables, por lo que los clientes pueden basarse en ellos en lugar
+/// de en el texto. Sólo se escriben en la conexión si el cliente acordó la capacidad
+/// [`CODES`](../capacidad/enum.Capacidad.html#variant.CODES) o usa el formato JSON.
+pub enum CodigoRespuesta {
+    /// El comando se realizó correctamente.
+    OK,
+    /// El mensaje no corresponde a ningún comando del protocolo.
+    ERR_INVALID_COMMAND,
+    /// Falta un argumento obligatorio del comando.
+    ERR_MISSING_ARGUMENT,
+    /// Un argumento del comando tiene un valor inválido.
+    ERR_INVALID_ARGUMENT,
+    /// El mensaje excede el tamaño máximo aceptado por el servidor.
+    ERR_MESSAGE_TOO_LONG,
+    /// El mensaje no es UTF-8 válido.
+    ERR_INVALID_ENCODING,
+    /// El cliente debe identificarse antes de realizar el comando.
+    ERR_NOT_IDENTIFIED,
+    /// Ya existe un usuario con el nombre solicitado.
+    ERR_NAME_IN_USE,
+    /// El comando sólo puede realizarse antes de identificarse.
+    ERR_ALREADY_IDENTIFIED,
+    /// No existe el usuario indicado.
+    ERR_NO_SUCH_USER,
+    /// No existe la sala indicada.
+    ERR_NO_SUCH_ROOM,
+    /// Ya existe una sala con el nombre solicitado.
+    ERR_ROOM_EXISTS,
+    /// El cliente no es propietario de la sala.
+    ERR_NOT_OWNER,
+    /// El cliente no está invitado a la sala.
+    ERR_NOT_INVITED,
+    /// El cliente no es miembro de la sala.
+    ERR_NOT_MEMBER,
+    /// Un filtro del servidor rechazó el comando.
+    ERR_REJECTED,
+    /// Ocurrió un error interno al procesar el comando.
+    ERR_INTERNAL,
+}
+
+impl CodigoRespuesta {
+
+    /// Determina si el código indica que el comando se realizó correctamente.
+    pub fn es_exito(&self) -> bool {
+        *self == CodigoRespuesta::OK
+    }
+}
+
+impl FromStr for CodigoRespuesta {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "OK" => Ok(CodigoRespuesta::OK),
+            "ERR_INVALID_COMMAND" => Ok(CodigoRespuesta::ERR_INVALID_COMMAND),
+            "ERR_MISSING_ARGUMENT" => Ok(CodigoRespuesta::ERR_MISSING_ARGUMENT),
+            "ERR_INVALID_ARGUMENT" => Ok(CodigoRespuesta::ERR_INVALID_ARGUMENT),
+            "ERR_MESSAGE_TOO_LONG" => Ok(CodigoRespuesta::ERR_MESSAGE_TOO_LONG),
+            "ERR_INVALID_ENCODING" => Ok(CodigoRespuesta::ERR_INVALID_ENCODING),
+            "ERR_NOT_IDENTIFIED" => Ok(CodigoRespuesta::ERR_NOT_IDENTIFIED),
+            "ERR_NAME_IN_USE" => Ok(CodigoRespuesta::ERR_NAME_IN_USE),
+            "ERR_ALREADY_IDENTIFIED" => Ok(CodigoRespuesta::ERR_ALREADY_IDENTIFIED),
+            "ERR_NO_SUCH_USER" => Ok(CodigoRespuesta::ERR_NO_SUCH_USER),
+            "ERR_NO_SUCH_ROOM" => Ok(CodigoRespuesta::ERR_NO_SUCH_ROOM),
+            "ERR_ROOM_EXISTS" => Ok(CodigoRespuesta::ERR_ROOM_EXISTS),
+            "ERR_NOT_OWNER" => Ok(CodigoRespuesta::ERR_NOT_OWNER),
+            "ERR_NOT_INVITED" => Ok(CodigoRespuesta::ERR_NOT_INVITED),
+            "ERR_NOT_MEMBER" => Ok(CodigoRespuesta::ERR_NOT_MEMBER),
+            "ERR_REJECTED" => Ok(CodigoRespuesta::ERR_REJECTED),
+            "ERR_INTERNAL" => Ok(CodigoRespuesta::ERR_INTERNAL),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for CodigoRespuesta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// Respuesta del servidor a un comando, formada por un código, un texto legible y el
+/// identificador de petición que el cliente adjuntó al comando, si lo hizo.
+/// Se representa como el código simbólico seguido del texto.
+pub struct Respuesta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<IdPeticion>,
+    codigo: CodigoRespuesta,
+    texto: String,
+}
+
+impl Respuesta {
+
+    /// Crea una nueva respuesta con un código y un texto.
+    pub fn new(codigo: CodigoRespuesta, texto: &str) -> Respuesta {
+        Respuesta {
+            id: None,
+            codigo,
+            texto: texto.to_owned(),
+        }
+    }
+
+    /// Crea una respuesta exitosa con un texto.
+    pub fn ok(texto: &str) -> Respuesta {
+        Respuesta::new(CodigoRespuesta::OK, texto)
+    }
+
+    /// Regresa el identificador de la petición a la que corresponde la respuesta.
+    pub fn get_id(&self) -> Option<IdPeticion> {
+        self.id
+    }
+
+    /// Define el identificador de la petición a la que corresponde la respuesta.
+    pub fn set_id(&mut self, id: Option<IdPeticion>) {
+        self.id = id;
+    }
+
+    /// Regresa el código de la respuesta.
+    pub fn get_codigo(&self) -> CodigoRespuesta {
+        self.codigo
+    }
+
+    /// Regresa el texto legible de la respuesta.
+    pub fn get_texto(&self) -> &str {
+        &self.texto[..]
+    }
+}
+
+impl<'a> From<&'a ChatError> for Respuesta {
+    fn from(error: &'a ChatError) -> Respuesta {
+        Respuesta::new(error.get_codigo(), &error.to_string())
+    }
+}
+
+impl<'a> From<&'a ErrorComando> for Respuesta {
+    fn from(error: &'a ErrorComando) -> Respuesta {
+        Respuesta::new(error.get_codigo(), &error.to_string())
+    }
+}
+
+impl FromStr for Respuesta {
+    type Err = ();
+
+    /// Analiza una línea de respuesta del servidor.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut partes = s.splitn(2, ' ');
+        let codigo = partes.next().unwrap_or("").parse::<CodigoRespuesta>()?;
+        let texto = partes.next().unwrap_or("");
+        Ok(Respuesta::new(codigo, texto))
+    }
+}
+
+impl fmt::Display for Respuesta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.texto.is_empty() {
+            write!(f, "{} {}", self.codigo, self.texto)
+        }
+        else {
+            write!(f, "{}", self.codigo)
+        }
+    }
+}
+
+impl error::Error for Respuesta {}
@@ -1,26 +1,29 @@
-use std::net::{TcpStream, SocketAddr};
-use std::collections::HashMap;
+use super::cliente::IdCliente;
+use std::collections::HashSet;
 
 /// Estructura de las salas de chat en el servidor.
-/// Las salas tienen un nombre asociado único, la dirección IP del cliente propietario
-/// (quien creó la sala), un diccionario de invitados y un diccionario de miembros.
+/// Las salas tienen un nombre asociado único, el identificador del cliente propietario
+/// (quien creó la sala, o el miembro que la heredó al desconectarse éste), un conjunto de invitados y un conjunto de miembros, ambos con los
+/// identificadores de los clientes. La sala no conoce las conexiones de sus miembros: los
+/// mensajes se les entregan a través del registro de clientes del servidor, por lo que un
+/// miembro que no está en el registro simplemente no los recibe.
 /// Solo los clientes invitados por el propietario pueden unirse como miembros a la sala.
 pub struct Sala {
     nombre: String,
-    direccion_propietario: SocketAddr,
-    invitados: HashMap<SocketAddr, TcpStream>,
-    miembros: HashMap<SocketAddr, TcpStream>,
+    propietario: IdCliente,
+    invitados: HashSet<IdCliente>,
+    miembros: HashSet<IdCliente>,
 }
 
 impl Sala {
 
-    /// Crea una nueva instancia de una sala, con un nombre y la dirección IP del propietario.
-    pub fn new(nombre: &str, direccion_propietario: SocketAddr) -> Sala {
+    /// Crea una nueva instancia de una sala, con un nombre y el identificador del propietario.
+    pub fn new(nombre: &str, propietario: IdCliente) -> Sala {
         Sala {
             nombre: nombre.to_owned(),
-            direccion_propietario: direccion_propietario,
-            invitados: HashMap::new(),
-            miembros: HashMap::new(),
+            propietario,
+            invitados: HashSet::new(),
+            miembros: HashSet::new(),
         }
     }
 
@@ -34,77 +37,60 @@ impl Sala {
         self.nombre = nombre.to_owned()
     }
 
-    /// Regresa la dirección IP del propietario.
-    pub fn get_direccion_propietario(&self) -> SocketAddr {
-        self.direccion_propietario
+    /// Regresa el identificador del propietario.
+    pub fn get_propietario(&self) -> IdCliente {
+        self.propietario
     }
 
-    /// Define la dirección IP del propietario.
-    pub fn set_direccion_propietario(&mut self, direccion_propietario: SocketAddr) {
-        self.direccion_propietario = direccion_propietario;
+    /// Define el identificador del propietario.
+    pub fn set_propietario(&mut self, propietario: IdCliente) {
+        self.propietario = propietario;
     }
 
-    /// Regresa el diccionario de invitados a la sala.
-    pub fn get_invitados(&mut self) -> &mut HashMap<SocketAddr, TcpStream> {
-        &mut self.invitados
+    /// Regresa el conjunto de invitados a la sala.
+    pub fn get_invitados(&self) -> &HashSet<IdCliente> {
+        &self.invitados
     }
 
-    /// Regresa el diccionario de miembros de la sala.
-    pub fn get_miembros(&mut self) -> &mut HashMap<SocketAddr, TcpStream> {
-        &mut self.miembros
+    /// Regresa el conjunto de miembros de la sala.
+    pub fn get_miembros(&self) -> &HashSet<IdCliente> {
+        &self.miembros
     }
 
-    /// Determina si el cliente al que pertenece la dirección IP es el propietario de la sala.
-    pub fn es_propietario(&self, direccion: SocketAddr) -> bool {
-        self.direccion_propietario.eq(&direccion)
+    /// Determina si el cliente con el identificador es el propietario de la sala.
+    pub fn es_propietario(&self, id: IdCliente) -> bool {
+        self.propietario == id
     }
 
-    /// Determina si el cliente al que pertenece la dirección IP está invitado a la sala.
-    pub fn cliente_es_invitado(&self, direccion: SocketAddr) -> bool {
-        match self.get_socket_invitado(direccion) {
-            Some(_) => true,
-            None => false,
-        }
-    }
-
-    /// Añade al cliente al que pertenecen el socket y la dirección IP a la lista de invitados.
-    pub fn invitar_miembro(&mut self, direccion: SocketAddr, socket: &TcpStream) {
-        let socket_clon = socket.try_clone().expect("Error al clonar socket");
-        self.invitados.entry(direccion).or_insert(socket_clon);
+    /// Determina si el cliente con el identificador está invitado a la sala.
+    pub fn cliente_es_invitado(&self, id: IdCliente) -> bool {
+        self.invitados.contains(&id)
     }
 
-    /// Regresa el socket de comunicación del cliente invitado al que pertenece la dirección IP.
-    pub fn get_socket_invitado(&self, direccion: SocketAddr) -> Option<&TcpStream> {
-        self.invitados.get(&direccion)
+    /// Añade al cliente con el identificador a la lista de invitados.
+    pub fn invitar_miembro(&mut self, id: IdCliente) {
+        self.invitados.insert(id);
     }
 
-    /// Elimina de la lista de invitados al cliente al que pertenece la dirección IP.
-    pub fn elimina_invitado(&mut self, direccion: SocketAddr) {
-        self.invitados.remove(&direccion);
-    }
-
-    /// Determina si el cliente al que pertenece la dirección IP es miembro de la sala.
-    pub fn cliente_es_miembro(&self, direccion: SocketAddr) -> bool {
-        match self.get_socket_miembro(direccion) {
-            Some(_) => true,
-            None => false,
-        }
+    /// Elimina de la lista de invitados al cliente con el identificador.
+    pub fn elimina_invitado(&mut self, id: IdCliente) {
+        self.invitados.remove(&id);
     }
 
-    /// Añade al cliente al que pertenecen el socket y la dirección IP a la lista de miembros.
-    pub fn agregar_miembro(&mut self, direccion: SocketAddr, socket: &TcpStream) {
-        self.elimina_invitado(direccion);
-        let socket_clon = socket.try_clone().expect("Error al clonar socket");
-        self.miembros.entry(direccion).or_insert(socket_clon);
+    /// Determina si el cliente con el identificador es miembro de la sala.
+    pub fn cliente_es_miembro(&self, id: IdCliente) -> bool {
+        self.miembros.contains(&id)
     }
 
-    /// Regresa el socket de comunicación del cliente miembro al que pertenece la dirección IP.
-    pub fn get_socket_miembro(&self, direccion: SocketAddr) -> Option<&TcpStream> {
-        self.miembros.get(&direccion)
+    /// Añade al cliente con el identificador a la lista de miembros, retirándolo de la
+    /// lista de invitados.
+    pub fn agregar_miembro(&mut self, id: IdCliente) {
+        self.elimina_invitado(id);
+        self.miembros.insert(id);
     }
 
-    /// Elimina de la lista de miembros al cliente al que pertenece la dirección IP.
-    pub fn elimina_miembro(&mut self, direccion: SocketAddr) {
-        self.miembros.remove(&direccion);
+    /// Elimina de la lista de miembros al cliente con el identificador.
+    pub fn elimina_miembro(&mut self, id: IdCliente) {
+        self.miembros.remove(&id);
     }
 }
@@ -1,28 +1,184 @@
-use super::{cliente::Cliente, eventoservidor::EventoServidor, eventoconexion::EventoConexion,
-    sala::Sala, util, estadocliente::EstadoCliente};
+use super::{cliente::{Cliente, IdCliente}, eventoservidor::{EventoServidor, TipoEvento},
+    comando::{Comando, ErrorComando, IdPeticion}, sala::Sala, estadocliente::EstadoCliente,
+    lector::{self, LectorMensajes, ErrorMensaje}, respuesta::{Respuesta, CodigoRespuesta},
+    mensajeservidor::MensajeServidor, notificacion::Notificacion, formato::Formato,
+    capacidad::{Capacidad, VERSION_PROTOCOLO}, registro::{RegistroClientes, RegistroSalas},
+    colasalida, escuchas::Escuchas, filtro::{Filtro, Filtros}, nucleo::Nucleo, reactor::Reactor,
+    bot::{Bot, Bots}, conexion::{self, Aceptador, Conexion}, error::ChatError};
 
 use std::net::{TcpStream, TcpListener, SocketAddr};
-use std::sync::{mpsc, Arc, Mutex};
-use std::{thread, time};
-use std::io::{Error, ErrorKind};
+use std::sync::{mpsc, Arc, Weak, Mutex, Condvar};
+use std::{cmp, thread, time};
+use std::io::{Error, ErrorKind, Read};
 
-type MutexCliente = Arc<Mutex<Vec<Cliente>>>;
-type MutexSala = Arc<Mutex<Vec<Sala>>>;
-type CanalServidor = mpsc::Sender<EventoServidor>;
+type MutexCliente = Arc<Mutex<RegistroClientes>>;
+type MutexSala = Arc<Mutex<RegistroSalas>>;
+type HilosActivos = Arc<(Mutex<usize>, Condvar)>;
+
+/// Texto de la notificación que reciben los clientes cuando el servidor se drena.
+pub const TEXTO_DRENADO: &str = "El servidor se está deteniendo";
+
+/// Forma en la que se solicitó detener al servidor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Parada {
+    /// Cerrar de inmediato las conexiones de todos los clientes.
+    Inmediata,
+    /// Avisar a los clientes y esperar a que se desconecten, a lo más el plazo indicado.
+    Drenado(time::Duration),
+}
+
+/// Forma en la que el controlador despierta al hilo que espera conexiones.
+#[derive(Clone)]
+enum Despertador {
+    /// Conectándose a una dirección TCP.
+    Direccion(SocketAddr),
+    /// Mediante el aceptador del servidor, mientras el servidor lo conserve.
+    Aceptador(Weak<dyn Aceptador>),
+}
+
+/// Controlador de un servidor en ejecución, que se obtiene al iniciarlo con
+/// [`iniciar`](struct.Servidor.html#method.iniciar). Todas las copias del controlador se
+/// refieren al mismo servidor, por lo que pueden repartirse entre hilos para detenerlo
+/// desde cualquiera de ellos. Como el servidor espera conexiones de forma bloqueante, el
+/// controlador lo despierta abriendo una conexión hacia él.
+#[derive(Clone)]
+pub struct ServidorHandle {
+    parada: Arc<Mutex<Option<Parada>>>,
+    terminado: Arc<(Mutex<bool>, Condvar)>,
+    direccion_local: Option<SocketAddr>,
+    despertador: Despertador,
+}
+
+impl ServidorHandle {
+
+    /// Crea el controlador de un servidor que aún no se detiene, que escucha conexiones
+    /// en la dirección recibida, si es TCP, y al que se despierta con el despertador recibido.
+    fn new(direccion_local: Option<SocketAddr>, despertador: Despertador) -> ServidorHandle {
+        ServidorHandle {
+            parada: Arc::new(Mutex::new(None)),
+            terminado: Arc::new((Mutex::new(false), Condvar::new())),
+            direccion_local,
+            despertador,
+        }
+    }
+
+    /// Solicita detener al servidor: deja de aceptar conexiones y cierra las de todos los
+    /// clientes, aún si el servidor se está drenando. No espera a que el servidor termine,
+    /// para ello se usa [`esperar`](#method.esperar).
+    pub fn detener(&self) {
+        *self.parada.lock().unwrap() = Some(Parada::Inmediata);
+        self.despertar();
+    }
+
+    /// Solicita drenar al servidor: deja de aceptar conexiones, envía la notificación
+    /// [`Shutdown`](../notificacion/enum.Notificacion.html#variant.Shutdown) a los clientes
+    /// conectados y espera a que terminen los hilos que los atienden. Las conexiones que
+    /// sigan abiertas al terminar el plazo se cierran. No tiene efecto si ya se solicitó
+    /// detener al servidor.
+    pub fn drenar(&self, plazo: time::Duration) {
+        {
+            let mut parada = self.parada.lock().unwrap();
+            if parada.is_some() {
+                return;
+            }
+            *parada = Some(Parada::Drenado(plazo));
+        }
+        self.despertar();
+    }
+
+    /// Bloquea el hilo actual hasta que el servidor termine, lo cual ocurre después de que
+    /// anuncia [`ServidorAbajo`](../eventoservidor/enum.EventoServidor.html#variant.ServidorAbajo)
+    /// a sus escuchas.
+    pub fn esperar(&self) {
+        let (ref terminado, ref condicion) = *self.terminado;
+        let mut terminado = terminado.lock().unwrap();
+        while !*terminado {
+            terminado = condicion.wait(terminado).unwrap();
+        }
+    }
+
+    /// Determina si el servidor ya terminó.
+    pub fn esta_terminado(&self) -> bool {
+        *self.terminado.0.lock().unwrap()
+    }
+
+    /// Regresa la dirección TCP en la que el servidor escucha conexiones. Si el servidor se
+    /// creó con el puerto 0, contiene el puerto que le asignó el sistema operativo. Si el
+    /// servidor acepta conexiones de un [`Aceptador`](../conexion/trait.Aceptador.html)
+    /// propio no tiene dirección TCP y regresa `None`; su dirección se obtiene del aceptador.
+    pub fn get_direccion_local(&self) -> Option<SocketAddr> {
+        self.direccion_local
+    }
+
+    /// Regresa la forma en la que se solicitó detener al servidor, si ya se solicitó.
+    pub fn get_parada(&self) -> Option<Parada> {
+        *self.parada.lock().unwrap()
+    }
+
+    /// Despierta al hilo que acepta conexiones para que atienda la solicitud de detenerse,
+    /// abriendo una conexión que el servidor descarta. Si el servidor ya no acepta
+    /// conexiones no tiene efecto.
+    fn despertar(&self) {
+        let resultado = match self.despertador {
+            Despertador::Direccion(direccion) => TcpStream::connect(direccion).map(|_| ()),
+            Despertador::Aceptador(ref aceptador) => match aceptador.upgrade() {
+                Some(aceptador) => aceptador.despertar(),
+                None => return,
+            },
+        };
+        if let Err(error) = resultado {
+            debug!(target: "Servidor", "No se pudo despertar al servidor: {}", error);
+        }
+    }
+
+    /// Marca al servidor como terminado, despertando a los hilos que lo esperan.
+    fn terminar(&self) {
+        let (ref terminado, ref condicion) = *self.terminado;
+        *terminado.lock().unwrap() = true;
+        condicion.notify_all();
+    }
+}
+
+/// Guarda que descuenta un hilo de los hilos activos del servidor al eliminarse, aún si
+/// el hilo termina por un pánico.
+struct GuardaHilo(HilosActivos);
+
+impl Drop for GuardaHilo {
+    fn drop(&mut self) {
+        let (ref activos, ref condicion) = *self.0;
+        *activos.lock().unwrap() -= 1;
+        condicion.notify_all();
+    }
+}
 
 /// Representación abstracta del servidor.
 /// Los servidores tienen una dirección IP asociada, un contador de referencias atómico
 /// [`std::sync::Arc`](https://doc.rust-lang.org/std/sync/struct.Arc.html)
 /// que contiene un primitiva de exclusión mutua
-/// [`std::sync::Mutex`](https://doc.rust-lang.org/std/sync/struct.Mutex.html) de un vector de clientes, y a su vez
-/// uno de salas, así como un vector de escuchas y un boolean que indica si el servidor se
-/// encuentra aceptando conexiones.
+/// [`std::sync::Mutex`](https://doc.rust-lang.org/std/sync/struct.Mutex.html) de un
+/// [`RegistroClientes`](../registro/struct.RegistroClientes.html), y a su vez uno de un
+/// [`RegistroSalas`](../registro/struct.RegistroSalas.html), así como sus
+/// [`Escuchas`](../escuchas/struct.Escuchas.html), [`Filtros`](../filtro/struct.Filtros.html) y
+/// [`Bots`](../bot/struct.Bots.html), el
+/// [`Nucleo`](../nucleo/enum.Nucleo.html) con el que atiende las conexiones, el
+/// número de hilos que atienden conexiones, el tamaño máximo de los mensajes que acepta, la capacidad
+/// y el plazo de saturación de las colas de salida de sus clientes, y el identificador que
+/// asignará a la siguiente conexión. En lugar de escuchar en una dirección IP, el servidor
+/// puede aceptar conexiones de cualquier [`Aceptador`](../conexion/trait.Aceptador.html).
 pub struct Servidor {
     direccion: String,
+    aceptador: Option<Box<dyn Aceptador>>,
     clientes: MutexCliente,
-    escuchas: Vec<CanalServidor>,
-    aceptando_conexiones: bool,
-    salas: MutexSala
+    escuchas: Escuchas,
+    filtros: Filtros,
+    bots: Bots,
+    nucleo: Nucleo,
+    hilos_activos: HilosActivos,
+    salas: MutexSala,
+    tamano_maximo_mensaje: usize,
+    capacidad_cola: usize,
+    plazo_saturacion: time::Duration,
+    siguiente_id: IdCliente,
 }
 
 impl Servidor {
@@ -30,113 +186,374 @@ impl Servidor {
     /// Crea una nueva instancia de un servidor, recibiendo un puerto y creando
     /// una dirección IP donde escuchar conexiones.
     pub fn new(puerto: &str) -> Servidor {
-        let direccion = format!("0.0.0.0:{}", puerto);
+        Servidor::crear(format!("0.0.0.0:{}", puerto))
+    }
+
+    /// Crea una nueva instancia de un servidor que escucha conexiones en la dirección
+    /// recibida, ya sea IPv4 o IPv6. Con el puerto 0 el sistema operativo asigna un puerto
+    /// libre, el cual se obtiene del controlador con
+    /// [`get_direccion_local`](struct.ServidorHandle.html#method.get_direccion_local).
+    pub fn con_direccion(direccion: SocketAddr) -> Servidor {
+        Servidor::crear(direccion.to_string())
+    }
+
+    /// Crea una nueva instancia de un servidor que acepta conexiones del aceptador
+    /// recibido, como un socket de Unix o un
+    /// [`AceptadorMemoria`](../conexion/struct.AceptadorMemoria.html). Sólo puede iniciarse
+    /// con el núcleo [`Hilos`](../nucleo/enum.Nucleo.html#variant.Hilos): el núcleo
+    /// [`Reactor`](../nucleo/enum.Nucleo.html#variant.Reactor) se registra con el escucha TCP
+    /// en mio, por lo que sólo acepta conexiones TCP.
+    pub fn con_aceptador<A: Aceptador + 'static>(aceptador: A) -> Servidor {
+        let mut servidor = Servidor::crear(aceptador.direccion_local());
+        servidor.aceptador = Some(Box::new(aceptador));
+        servidor
+    }
+
+    /// Crea una nueva instancia de un servidor que escucha en la dirección recibida.
+    fn crear(direccion: String) -> Servidor {
         Servidor {
-            direccion: direccion,
-            clientes: Arc::new(Mutex::new(Vec::new())),
-            escuchas: Vec::new(),
-            aceptando_conexiones: false,
-            salas: Arc::new(Mutex::new(Vec::new())),
-        }
-    }
-
-    /// Intenta enlazarse con la dirección IP creada, para posteriormente comenzar a escuchar
-    /// peticiones en dicha dirección. Al recibir una petición nueva, crea un nuevo cliente
-    /// y lanza un hilo de ejecución que se encargue de escuchar a dicho cliente. El método se
-    /// realiza de forma repetida hasta que se modifique el valor de la variable
-    /// "aceptando_conexiones".
-    /// El servidor toma "pausas" de 500 milisegundos para evitar consumir recursos de manera
-    /// excesiva.
-    pub fn comenzar(&mut self) {
-        let escucha_tcp = match TcpListener::bind(&self.direccion) {
-            Ok(escucha) => escucha,
+            direccion,
+            aceptador: None,
+            clientes: Arc::new(Mutex::new(RegistroClientes::new())),
+            escuchas: Escuchas::new(),
+            filtros: Filtros::new(),
+            bots: Bots::new(),
+            nucleo: Nucleo::Hilos,
+            hilos_activos: Arc::new((Mutex::new(0), Condvar::new())),
+            salas: Arc::new(Mutex::new(RegistroSalas::new())),
+            tamano_maximo_mensaje: lector::TAMANO_MAXIMO_POR_OMISION,
+            capacidad_cola: colasalida::CAPACIDAD_POR_OMISION,
+            plazo_saturacion: time::Duration::from_millis(colasalida::PLAZO_SATURACION_POR_OMISION),
+            siguiente_id: 0,
+        }
+    }
+
+    /// Regresa la dirección con la que el servidor se enlaza al iniciar, o la del aceptador
+    /// con el que se creó.
+    pub fn get_direccion(&self) -> &str {
+        &self.direccion
+    }
+
+    /// Agrega un filtro que intercepta los comandos de los clientes y los mensajes que el
+    /// servidor les envía. Los filtros se aplican en el orden en que se agregan.
+    /// Regresa un error si existe una copia de los filtros obtenida con
+    /// [`get_filtros`](#method.get_filtros), pues la comparten.
+    pub fn agregar_filtro<F: Filtro + 'static>(&mut self, filtro: F) -> Result<(), Error> {
+        self.filtros.agregar(filtro)
+    }
+
+    /// Agrega un bot que participa en el chat como un cliente virtual desde que el servidor
+    /// inicia.
+    pub fn agregar_bot<B: Bot + 'static>(&mut self, bot: B) {
+        self.bots.agregar(bot);
+    }
+
+    /// Regresa los bots del servidor.
+    pub fn get_bots(&self) -> &Bots {
+        &self.bots
+    }
+
+    /// Regresa los filtros del servidor.
+    pub fn get_filtros(&self) -> &Filtros {
+        &self.filtros
+    }
+
+    /// Regresa el núcleo con el que el servidor atiende las conexiones.
+    pub fn get_nucleo(&self) -> Nucleo {
+        self.nucleo
+    }
+
+    /// Define el núcleo con el que el servidor atiende las conexiones. Solo tiene efecto
+    /// antes de iniciar el servidor.
+    pub fn set_nucleo(&mut self, nucleo: Nucleo) {
+        self.nucleo = nucleo;
+    }
+
+    /// Regresa el tamaño máximo (en bytes) de los mensajes que acepta el servidor.
+    pub fn get_tamano_maximo_mensaje(&self) -> usize {
+        self.tamano_maximo_mensaje
+    }
+
+    /// Define el tamaño máximo (en bytes) de los mensajes que acepta el servidor.
+    /// Solo afecta a las conexiones aceptadas posteriormente.
+    pub fn set_tamano_maximo_mensaje(&mut self, tamano_maximo: usize) {
+        self.tamano_maximo_mensaje = tamano_maximo;
+    }
+
+    /// Regresa el número máximo de mensajes pendientes en la cola de salida de cada cliente.
+    pub fn get_capacidad_cola(&self) -> usize {
+        self.capacidad_cola
+    }
+
+    /// Define el número máximo de mensajes pendientes en la cola de salida de cada cliente.
+    /// Solo afecta a las conexiones aceptadas posteriormente.
+    pub fn set_capacidad_cola(&mut self, capacidad: usize) {
+        self.capacidad_cola = capacidad;
+    }
+
+    /// Regresa el tiempo que la cola de salida de un cliente puede permanecer llena, o su
+    /// conexión sin aceptar datos, antes de que el servidor lo desconecte.
+    pub fn get_plazo_saturacion(&self) -> time::Duration {
+        self.plazo_saturacion
+    }
+
+    /// Define el tiempo que la cola de salida de un cliente puede permanecer llena, o su
+    /// conexión sin aceptar datos, antes de que el servidor lo desconecte. Solo afecta a las
+    /// conexiones aceptadas posteriormente.
+    pub fn set_plazo_saturacion(&mut self, plazo: time::Duration) {
+        self.plazo_saturacion = plazo;
+    }
+
+    /// Inicia el servidor con [`iniciar`](#method.iniciar) y bloquea el hilo actual hasta
+    /// que el servidor se detenga.
+    pub fn comenzar(self) {
+        match self.iniciar() {
+            Ok(control) => control.esperar(),
             Err(error) => {
                 error!("Ocurrió un problema al iniciar el servidor: {}", error);
                 panic!("{:?}", error);
             },
-        };
+        }
+    }
 
+    /// Intenta enlazarse con la dirección IP creada y lanza un hilo de ejecución que escucha
+    /// peticiones en dicha dirección. Al recibir una petición nueva, crea un nuevo cliente y
+    /// anuncia [`NuevoCliente`](../eventoservidor/enum.EventoServidor.html#variant.NuevoCliente)
+    /// a los escuchas. Con el núcleo [`Hilos`](../nucleo/enum.Nucleo.html#variant.Hilos)
+    /// lanza un hilo de ejecución que se encargue de escuchar a cada cliente, y con el
+    /// núcleo [`Reactor`](../nucleo/enum.Nucleo.html#variant.Reactor) un solo hilo atiende
+    /// a todos los clientes. El servidor acepta conexiones hasta que se detiene mediante el
+    /// [`ServidorHandle`](struct.ServidorHandle.html) que regresa el método.
+    /// Regresa un error si no es posible enlazarse con la dirección, o si el servidor se creó
+    /// con un aceptador propio y usa el núcleo reactor.
+    pub fn iniciar(mut self) -> Result<ServidorHandle, Error> {
+        if let Some(aceptador) = self.aceptador.take() {
+            if self.nucleo == Nucleo::Reactor {
+                return Err(Error::new(ErrorKind::InvalidInput,
+                    "El núcleo reactor sólo acepta conexiones TCP"));
+            }
+            return self.iniciar_hilos(Arc::from(aceptador), None);
+        }
+        let escucha_tcp = TcpListener::bind(&self.direccion)?;
+        let direccion_local = escucha_tcp.local_addr()?;
+        if self.nucleo == Nucleo::Hilos {
+            return self.iniciar_hilos(Arc::new(escucha_tcp), Some(direccion_local));
+        }
+        let control = ServidorHandle::new(Some(direccion_local),
+            Despertador::Direccion(conexion::direccion_despertador(direccion_local)));
+        let control_hilo = control.clone();
+        let reactor = Reactor::new(escucha_tcp, self.escuchas.clone(), self.filtros.clone(),
+            self.bots.clone(),
+            self.tamano_maximo_mensaje, self.capacidad_cola, self.plazo_saturacion)?;
+        self.anunciar_escuchas(EventoServidor::ServidorArriba);
+        info!(target: "Servidor", "Aceptando conexiones con reactor en: {}", direccion_local);
+        thread::Builder::new().name("servidor".into()).spawn(move || {
+            reactor.ejecutar(&mut self, &control_hilo);
+            self.eliminar_escuchas();
+            info!(target: "Servidor", "Servidor desconectado");
+            control_hilo.terminar();
+        })?;
+        Ok(control)
+    }
+
+    /// Inicia el servidor con el núcleo [`Hilos`](../nucleo/enum.Nucleo.html#variant.Hilos),
+    /// lanzando el hilo que acepta conexiones del aceptador recibido.
+    fn iniciar_hilos(mut self, aceptador: Arc<dyn Aceptador>, direccion_local: Option<SocketAddr>)
+        -> Result<ServidorHandle, Error> {
+        let control = ServidorHandle::new(direccion_local,
+            Despertador::Aceptador(Arc::downgrade(&aceptador)));
+        let control_hilo = control.clone();
+        self.bots.conectar(&mut self.clientes.lock().unwrap(), &mut self.siguiente_id,
+            self.capacidad_cola, self.plazo_saturacion);
         self.anunciar_escuchas(EventoServidor::ServidorArriba);
-        self.aceptando_conexiones = true;
-        escucha_tcp.set_nonblocking(true).expect("Error al inicializar el non-blocking");
-        info!(target: "Servidor", "Aceptando conexiones en: {}", &self.direccion);
-        while self.aceptando_conexiones {
-            if let Ok((socket, direccion)) = escucha_tcp.accept() {
-                let mut cliente = self.aceptar_cliente(socket, direccion);
-                self.maneja_conexion(cliente);
+        info!(target: "Servidor", "Aceptando conexiones en: {}", aceptador.direccion_local());
+        thread::Builder::new().name("servidor".into()).spawn(move || {
+            let parada = self.aceptar_conexiones(aceptador, &control_hilo);
+            self.finalizar(parada, &control_hilo);
+        })?;
+        Ok(control)
+    }
+
+    /// Acepta conexiones en cuanto llegan hasta que se solicita detener al servidor,
+    /// regresando la forma en que se solicitó. La espera de conexiones es bloqueante; al
+    /// solicitar que se detenga, el controlador abre una conexión para despertar al servidor,
+    /// la cual se descarta. Si ocurre un error al aceptar, como agotar los descriptores de
+    /// archivo, el servidor toma una pausa breve antes de reintentar. El aceptador se
+    /// elimina al regresar, por lo que el servidor deja de recibir conexiones.
+    fn aceptar_conexiones(&mut self, aceptador: Arc<dyn Aceptador>, control: &ServidorHandle)
+        -> Parada {
+        loop {
+            let conexion = aceptador.aceptar();
+            if let Some(parada) = control.get_parada() {
+                return parada;
             }
+            match conexion {
+                Ok(conexion) => match self.aceptar_cliente(conexion) {
+                    Ok(cliente) => {
+                        self.anunciar_escuchas(EventoServidor::NuevoCliente);
+                        self.maneja_conexion(cliente);
+                    },
+                    Err(error) => {
+                        warn!(target: "Servidor", "No se pudo registrar la conexión: {}", error);
+                    },
+                },
+                Err(error) => {
+                    warn!(target: "Servidor", "Error al aceptar una conexión: {}", error);
+                    thread::sleep(time::Duration::from_millis(50));
+                },
+            }
+        }
+    }
+
+    /// Termina la ejecución del servidor una vez que dejó de aceptar conexiones. Si se está
+    /// drenando, avisa a los clientes y espera a que se desconecten hasta que se cumpla el
+    /// plazo o se solicite detenerlo de inmediato. Después cierra las conexiones restantes,
+    /// espera a que terminen los hilos que las atienden y anuncia a los escuchas que el
+    /// servidor terminó.
+    fn finalizar(&mut self, parada: Parada, control: &ServidorHandle) {
+        if let Parada::Drenado(plazo) = parada {
+            info!(target: "Servidor", "Drenando servidor");
+            self.avisar_clientes(&Notificacion::Shutdown { texto: TEXTO_DRENADO.to_owned() });
+            let limite = time::Instant::now() + plazo;
+            while !self.esperar_hilos(Some(time::Duration::from_millis(100))) {
+                if time::Instant::now() >= limite || control.get_parada() == Some(Parada::Inmediata) {
+                    break;
+                }
+            }
+        }
+        info!(target: "Servidor", "Desconectando servidor");
+        self.eliminar_clientes();
+        self.esperar_hilos(None);
+        info!(target: "Servidor", "Clientes eliminados");
+        self.eliminar_escuchas();
+        info!(target: "Servidor", "Servidor desconectado");
+        control.terminar();
+    }
 
-            thread::sleep(time::Duration::from_millis(500));
+    /// Espera a que terminen todos los hilos que atienden conexiones, a lo más el plazo
+    /// recibido. Regresa si los hilos terminaron.
+    fn esperar_hilos(&self, plazo: Option<time::Duration>) -> bool {
+        let (ref activos, ref condicion) = *self.hilos_activos;
+        let mut activos = activos.lock().unwrap();
+        let limite = plazo.map(|plazo| time::Instant::now() + plazo);
+        while *activos > 0 {
+            activos = match limite {
+                Some(limite) => {
+                    let ahora = time::Instant::now();
+                    if ahora >= limite {
+                        return false;
+                    }
+                    condicion.wait_timeout(activos, limite - ahora).unwrap().0
+                },
+                None => condicion.wait(activos).unwrap(),
+            };
         }
+        true
     }
 
-    /// Crea un nuevo cliente y lo guarda dentro del vector de clientes, regresando una copia
-    pub fn aceptar_cliente(&mut self, socket: TcpStream, direccion: SocketAddr) -> Cliente {
-        let cliente = Cliente::new(None, socket, direccion);
+    /// Envía una notificación a todos los clientes conectados.
+    fn avisar_clientes(&mut self, notificacion: &Notificacion) {
+        let mensaje = MensajeServidor::Notificacion(notificacion.clone());
         let mut clientes = self.clientes.lock().unwrap();
-        clientes.push(cliente.clone());
-        info!(target: "Servidor", "Nuevo cliente: {}", direccion);
-        cliente
+        for cliente in clientes.iter_mut() {
+            let _ = Servidor::entregar(cliente, &mensaje, &self.filtros);
+        }
+    }
+
+    /// Crea un nuevo cliente con un identificador único y lo guarda dentro del registro de
+    /// clientes, regresando una copia. Regresa un error si no se pudo duplicar la conexión
+    /// para la cola de salida del cliente, en cuyo caso la conexión se descarta.
+    pub fn aceptar_cliente(&mut self, conexion: Box<dyn Conexion>) -> Result<Cliente, Error> {
+        let id = self.siguiente_id;
+        self.siguiente_id += 1;
+        let cliente = Cliente::con_cola(id, None, conexion, self.capacidad_cola,
+            self.plazo_saturacion)?;
+        let mut clientes = self.clientes.lock().unwrap();
+        clientes.agregar(cliente.clone());
+        info!(target: "Servidor", "Nuevo cliente {}: {}", id, cliente.get_direccion());
+        Ok(cliente)
     }
 
     /// Lanza un hilo de ejecución encargado de escuchar al cliente recibido y reaccionar
     /// dependiendo de los eventos que el cliente especifique. En caso de un error o que el
     /// mismo cliente interrumpa la conexión, el servidor lo desconecta y lo elimina
     /// de la lista de clientes.
-    pub fn maneja_conexion(&mut self, cliente: Cliente) {
+    pub fn maneja_conexion(&mut self, mut cliente: Cliente) {
         let clientes = Arc::clone(&self.clientes);
         let salas = Arc::clone(&self.salas);
-        thread::spawn(move || loop {
-                match Servidor::reaccionar(cliente.clone(), &clientes, &salas) {
+        let escuchas = self.escuchas.clone();
+        let filtros = self.filtros.clone();
+        let bots = self.bots.clone();
+        let tamano_maximo = self.tamano_maximo_mensaje;
+        *self.hilos_activos.0.lock().unwrap() += 1;
+        let guarda = GuardaHilo(Arc::clone(&self.hilos_activos));
+        thread::spawn(move || {
+            let _guarda = guarda;
+            let conexion = match cliente.get_conexion().map(Conexion::duplicar) {
+                Some(Ok(conexion)) => conexion,
+                _ => {
+                    warn!(target: "Servidor", "No se pudo leer del cliente {}",
+                            cliente.get_direccion());
+                    Servidor::desconectar_cliente(&cliente, &mut clientes.lock().unwrap(),
+                        &mut salas.lock().unwrap(), &escuchas);
+                    return;
+                },
+            };
+            let mut lector = LectorMensajes::con_tamano_maximo(conexion, tamano_maximo);
+            loop {
+                match Servidor::reaccionar(&mut cliente, &mut lector, &clientes, &salas, &escuchas,
+                    &filtros, &bots) {
                     Ok(_) => {
 
                     },
                     Err(_) => {
                         warn!(target: "Servidor", "Se perdió la conexión con el cliente {}",
                                 cliente.get_direccion());
-                        Servidor::desconectar_cliente(&cliente, &clientes, &salas);
+                        Servidor::desconectar_cliente(&cliente, &mut clientes.lock().unwrap(),
+                            &mut salas.lock().unwrap(), &escuchas);
                         break;
                     }
                 }
             }
-        );
-    }
-
-    /// Detiene la ejecución del servidor, eliminando de la memoria a los clientes y los escuchas.
-    pub fn detener(&mut self) {
-        info!(target: "Servidor", "Desconectando servidor");
-        self.eliminar_clientes();
-        info!(target: "Servidor", "Clientes eliminados");
-        self.eliminar_escuchas();
-        info!(target: "Servidor", "Escuchas eliminados");
-        self.aceptando_conexiones = false;
-        info!(target: "Servidor", "Servidor desconectado");
+        });
     }
 
     /// Crea una nueva tupla escucha-emisor, guardando el emisor en la lista de escuchas y
-    /// regresando a su correspondiente escucha.
+    /// regresando a su correspondiente escucha, el cual recibe todos los eventos.
     pub fn nuevo_escucha(&mut self) -> mpsc::Receiver<EventoServidor> {
         info!(target: "Servidor", "Creando nuevo escucha");
-        let (tx, rx) = mpsc::channel::<EventoServidor>();
-        self.escuchas.push(tx);
-        rx
+        self.escuchas.suscribir()
+    }
+
+    /// Crea un nuevo escucha que solo recibe los eventos de los tipos recibidos.
+    pub fn nuevo_escucha_de(&mut self, tipos: &[TipoEvento]) -> mpsc::Receiver<EventoServidor> {
+        info!(target: "Servidor", "Creando nuevo escucha de los eventos {:?}", tipos);
+        self.escuchas.suscribir_a(tipos)
+    }
+
+    /// Regresa la lista de escuchas del servidor.
+    pub fn get_escuchas(&self) -> &Escuchas {
+        &self.escuchas
     }
 
     /// Anuncia a los escuchas existentens sobre un evento ocurrido en el servidor.
     pub fn anunciar_escuchas(&mut self, evento: EventoServidor) {
-        info!(target: "Servidor", "Anunciando escuchas del evento: {:?}", evento);
-        for escucha in &self.escuchas {
-            &escucha.send(evento.clone());
-        }
+        Servidor::anunciar(&self.escuchas, evento);
+    }
+
+    /// Anuncia un evento a los escuchas recibidos. Los hilos que atienden a los clientes
+    /// anuncian los eventos del chat con este método.
+    pub fn anunciar(escuchas: &Escuchas, evento: EventoServidor) {
+        debug!(target: "Servidor", "Anunciando escuchas del evento: {:?}", evento);
+        escuchas.anunciar(&evento);
     }
 
-    /// Elimina de memoria a los escuchas creados.
+    /// Elimina de memoria a los escuchas creados, después de anunciarles que el servidor
+    /// está abajo.
     pub fn eliminar_escuchas(&mut self) {
         info!(target: "Servidor", "Eliminando escuchas");
         self.anunciar_escuchas(EventoServidor::ServidorAbajo);
-        for escucha in self.escuchas.iter() {
-            drop(escucha);
-        }
+        self.escuchas.eliminar();
     }
 
 
@@ -148,490 +565,533 @@ impl Servidor {
         for cliente in clientes.iter_mut() {
             info!(target: "Servidor", "Eliminando al cliente {}", cliente.get_direccion());
             cliente.detener();
-            drop(cliente);
             info!(target: "Servidor", "Cliente eliminado");
         }
     }
 
-    /// Regresa un nombre único con longitud entre 1 - 20 caracteres dentro de un vector de argumentos.
-    /// Si el nombre no cumple con alguna condición, regresa un error.
-    pub fn obtener_nombre(mut argumentos: Vec<String>, mutex_clientes: &MutexCliente) -> Result<String, Error> {
-        if argumentos.len() != 0 {
-            let nombre = argumentos.remove(0);
-            if nombre.len() < 1 || nombre.len() > 20 {
-                Err(Error::new(ErrorKind::ConnectionRefused,
-                    "El nombre debe tener una longitud entre 1 y 20 caracteres"))
-            }
-            else if Servidor::es_nombre_unico(&nombre, mutex_clientes) {
-                Ok(nombre)
-            }
-            else {
-                Err(Error::new(ErrorKind::ConnectionRefused, "Ya existe un usuario con ese nombre"))
-            }
+    /// Define el nuevo nombre único de un cliente. La verificación de que el nombre esté
+    /// libre y la asignación ocurren bajo el mismo candado, por lo que dos clientes no
+    /// pueden obtener el mismo nombre aunque lo soliciten al mismo tiempo.
+    /// Si el cliente ya tiene ese nombre, responde con éxito sin anunciar nada.
+    /// Regresa un error si ya existe un usuario con ese nombre.
+    pub fn cambiar_nombre_usuario(cliente: &Cliente, clientes: &mut RegistroClientes,
+        escuchas: &Escuchas, nombre: String) -> Result<String, ChatError> {
+        let confirmacion = format!("Nombre cambiado a: {}", nombre);
+        let nombre_actual = clientes.obtener(cliente.get_id())
+            .and_then(|cliente| cliente.get_nombre().as_ref());
+        if nombre_actual == Some(&nombre) {
+            return Ok(confirmacion);
         }
-        else {
-            Err(Error::new(ErrorKind::ConnectionRefused, "No se especificó el nombre"))
+        if !clientes.asignar_nombre(cliente.get_id(), &nombre) {
+            return Err(ChatError::NombreEnUso(nombre));
         }
+        info!(target: "Servidor",
+            "El cliente con dirección {} se identificó como {}", cliente.get_direccion(), nombre);
+        Servidor::anunciar(escuchas, EventoServidor::ClienteIdentificado {
+            id: cliente.get_id(),
+            nombre,
+        });
+        Ok(confirmacion)
     }
 
-    /// Regresa un estado válido dentro de un vector de argumentos.
-    /// Si el estado no cumple con alguna condición, regresa un error.
-    pub fn obtener_estado(mut argumentos: Vec<String>) -> Result<EstadoCliente, Error> {
-        if argumentos.len() != 0 {
-            let estado = argumentos.remove(0);
-            match estado.parse::<EstadoCliente>() {
-                Ok(estado) => {
-                    Ok(estado)
-                },
-                Err(_) => {
-                    Err(Error::new(ErrorKind::ConnectionRefused,
-                        "Proporciona un estado válido: ACTIVE, AWAY, BUSY"))
-                }
-            }
-        }
-        else {
-            Err(Error::new(ErrorKind::ConnectionRefused, "No se especificó el estado"))
+    /// Define el nuevo estado de un cliente.
+    pub fn cambiar_estado_usuario(cliente: &Cliente, clientes: &mut RegistroClientes,
+        escuchas: &Escuchas, estado: EstadoCliente) -> Result<String, ChatError> {
+        let nombre_cliente = Servidor::nombre_identificado(clientes, cliente,
+            "actualizar tu estado")?;
+        if let Some(cliente) = clientes.obtener_mut(cliente.get_id()) {
+            cliente.set_estado(estado.clone());
         }
+        info!(target: "Servidor",
+            "{} actualizó su estado a {}", nombre_cliente, estado);
+        let confirmacion = format!("Estado cambiado a: {}", estado);
+        Servidor::anunciar(escuchas, EventoServidor::EstadoCambiado {
+            nombre: nombre_cliente,
+            estado,
+        });
+        Ok(confirmacion)
     }
 
-    /// Determina si un nombre es único entre todos los clientes ya identificados.
-    pub fn es_nombre_unico(nombre: &str, mutex_clientes: &MutexCliente) -> bool {
-        let mut clientes = mutex_clientes.lock().unwrap();
-        for cliente in clientes.iter_mut() {
-            if let Some(nombre_cliente) = cliente.get_nombre() {
-                if nombre_cliente.eq(nombre) {
-                    return false;
-                }
-            }
+    /// Regresa un vector de nombres de los clientes identificados en el servidor.
+    pub fn obtener_usuarios(clientes: &RegistroClientes) -> Vec<String> {
+        clientes.nombres()
+    }
+
+    /// Envía un mensaje privado a un cliente en específico.
+    /// Regresa un error si el remitente no está identificado ó no se encuentra al destinatario.
+    pub fn envia_mensaje_privado(cliente: &Cliente, clientes: &mut RegistroClientes,
+        escuchas: &Escuchas, filtros: &Filtros, destinatario: &str, texto: &str)
+        -> Result<String, ChatError> {
+        let remitente = Servidor::nombre_identificado(clientes, cliente,
+            "enviar un mensaje")?;
+        let notificacion = Notificacion::Message {
+            remitente: remitente.clone(),
+            texto: texto.to_owned(),
+        };
+        match clientes.buscar_por_nombre_mut(destinatario) {
+            Some(destinatario) => Servidor::entregar_desde(cliente, destinatario,
+                &MensajeServidor::Notificacion(notificacion), filtros)?,
+            None => return Err(ChatError::UsuarioInexistente(destinatario.to_owned())),
         }
-        true
+        let confirmacion = format!("{}: {}", remitente, texto);
+        Servidor::anunciar(escuchas, EventoServidor::MensajePrivado {
+            remitente,
+            destinatario: destinatario.to_owned(),
+            texto: texto.to_owned(),
+        });
+        Ok(confirmacion)
     }
 
-    /// Define el nuevo nombre único de un cliente.
-    pub fn cambiar_nombre_usuario(cliente: &Cliente, mutex_clientes: &MutexCliente, argumentos: Vec<String>)
-        -> Result<String, Error> {
-        let nombre = Servidor::obtener_nombre(argumentos, mutex_clientes)?;
-        let mut clientes = mutex_clientes.lock().unwrap();
+    /// Envía un mensaje público a todos los clientes en el servidor. Un cliente al que no se
+    /// le pueda entregar el mensaje no impide que lo reciban los demás.
+    /// Regresa un error si el remitente no está identificado, o uno de transporte si falló
+    /// la conexión del propio remitente.
+    pub fn envia_mensaje_publico(cliente: &Cliente, clientes: &mut RegistroClientes,
+        escuchas: &Escuchas, filtros: &Filtros, texto: &str) -> Result<String, ChatError> {
+        let remitente = Servidor::nombre_identificado(clientes, cliente,
+            "enviar un mensaje")?;
+        let mensaje = MensajeServidor::Notificacion(Notificacion::PublicMessage {
+            remitente: remitente.clone(),
+            texto: texto.to_owned(),
+        });
+        let mut resultado = Ok(());
         for cliente_iter in clientes.iter_mut() {
-            if cliente.eq(cliente_iter) {
-                cliente_iter.set_nombre(&nombre);
-                break;
+            if let Err(error) = Servidor::entregar_desde(cliente, cliente_iter, &mensaje, filtros) {
+                resultado = Err(error);
             }
         }
-        info!(target: "Servidor",
-            "El cliente con dirección {} se identificó como {}", cliente.get_direccion(), nombre);
-        let confirmacion = format!("Nombre cambiado a: {}", nombre);
-        Ok(confirmacion)
+        Servidor::anunciar(escuchas, EventoServidor::MensajePublico {
+            remitente,
+            texto: texto.to_owned(),
+        });
+        resultado.map(|_| String::from("Mensaje público enviado"))
     }
 
-    /// Define el nuevo estado de un cliente.
-    pub fn cambiar_estado_usuario(cliente: &Cliente, mutex_clientes: &MutexCliente, argumentos: Vec<String>)
-        -> Result<String, Error> {
-        if let Some(nombre_cliente) = Servidor::obtener_nombre_cliente(&cliente, &mutex_clientes) {
-            let estado = Servidor::obtener_estado(argumentos)?;
-            let mut clientes = mutex_clientes.lock().unwrap();
-            for cliente_iter in clientes.iter_mut() {
-                if cliente.eq(cliente_iter) {
-                    cliente_iter.set_estado(estado.clone());
-                    break;
-                }
-            }
-            info!(target: "Servidor",
-                "{} actualizó su estado a {}", nombre_cliente, estado);
-            let confirmacion = format!("Estado cambiado a: {}", estado);
-            Ok(confirmacion)
+    /// Crea una nueva sala, cuyo propietario es el creador de la misma. La verificación de
+    /// que el nombre esté libre y la creación ocurren bajo el mismo candado.
+    /// Regresa un error si la sala ya existe.
+    pub fn crear_sala(cliente: &Cliente, clientes: &mut RegistroClientes, salas: &mut RegistroSalas,
+        escuchas: &Escuchas, nombre_nueva_sala: &str) -> Result<String, ChatError> {
+        let mut nueva_sala = Sala::new(nombre_nueva_sala, cliente.get_id());
+        nueva_sala.agregar_miembro(cliente.get_id());
+        if !salas.crear(nueva_sala) {
+            return Err(ChatError::SalaExistente(nombre_nueva_sala.to_owned()));
         }
-        else {
-            Err(Error::new(ErrorKind::ConnectionRefused,
-                format!("Debes identificarte para actualizar tu estado")))
+        if let Some(nombre) = clientes.nombre_de(cliente.get_id()) {
+            info!(target: "Servidor", "{} creó la sala {}", nombre, nombre_nueva_sala);
         }
+        Servidor::anunciar(escuchas, EventoServidor::SalaCreada {
+            sala: nombre_nueva_sala.to_owned(),
+            propietario: cliente.get_id(),
+        });
+        let confirmacion = format!("Creación de la sala {} exitosa", nombre_nueva_sala);
+        Ok(confirmacion)
     }
 
-    /// Regresa un vector de nombres de los clientes identificados en el servidor.
-    pub fn obtener_usuarios(mutex_clientes: &MutexCliente) -> Vec<String> {
-        let clientes = mutex_clientes.lock().unwrap();
-        let mut lista_clientes = Vec::new();
-        for cliente in clientes.iter() {
-            if let Some(nombre) = cliente.get_nombre() {
-                lista_clientes.push(nombre.to_owned());
+    /// Dado un vector de nombres de usuarios y una sala, envía una invitación de unirse a la
+    /// sala a cada cliente.
+    /// Regresa un error si el remitente no se ha identificado, la sala no existe o si no
+    /// se es propietario de la misma.
+    pub fn enviar_invitacion(cliente: &Cliente, clientes: &mut RegistroClientes, salas: &mut RegistroSalas,
+        escuchas: &Escuchas, filtros: &Filtros, nombre_sala: &str, usuarios: &[String])
+        -> Result<String, ChatError> {
+        let nombre_anfitrion = Servidor::nombre_identificado(clientes, cliente,
+            "invitar usuarios")?;
+        let sala = Servidor::buscar_sala(salas, nombre_sala)?;
+        if !sala.es_propietario(cliente.get_id()) {
+            return Err(ChatError::NoPropietario(nombre_sala.to_owned()));
+        }
+        let invitacion = MensajeServidor::Notificacion(Notificacion::Invite {
+            sala: nombre_sala.to_owned(),
+            anfitrion: nombre_anfitrion.clone(),
+        });
+        let mut resultado = Ok(());
+        for usuario in usuarios.iter() {
+            if let Some(invitado) = clientes.buscar_por_nombre_mut(usuario) {
+                sala.invitar_miembro(invitado.get_id());
+                if let Err(error) = Servidor::entregar_desde(cliente, invitado, &invitacion, filtros) {
+                    resultado = Err(error);
+                }
+                Servidor::anunciar(escuchas, EventoServidor::InvitacionEnviada {
+                    sala: nombre_sala.to_owned(),
+                    anfitrion: nombre_anfitrion.clone(),
+                    invitado: usuario.to_owned(),
+                });
             }
         }
-        lista_clientes
+        resultado.map(|_| format!("Invitaciones de la sala {} enviadas", nombre_sala))
     }
 
-    /// Envía un mensaje privado a un cliente en específico.
-    pub fn envia_mensaje_privado(cliente: &Cliente, mutex_clientes: &MutexCliente, mut argumentos: Vec<String>)
-        -> Result<String, Error> {
-        if let Some(remitente) = Servidor::obtener_nombre_cliente(&cliente, &mutex_clientes) {
-            let mut destinatario = Servidor::obtener_destinatario(mutex_clientes, &mut argumentos)?;
-            let mut mensaje = argumentos.join(" ");
-            if mensaje.len() > 0 {
-                let remitente = format!("{}: ", remitente);
-                mensaje = remitente + &mensaje;
-                let confirmacion = mensaje.clone();
-                destinatario.enviar_mensaje(&mensaje)?;
-                Ok(confirmacion)
-            }
-            else {
-                Err(Error::new(ErrorKind::ConnectionRefused,
-                    format!("No se identificó el contenido del mensaje")))
-            }
-        }
-        else {
-            Err(Error::new(ErrorKind::ConnectionRefused,
-                format!("Debes identificarte para enviar un mensaje")))
+    /// Permite que un cliente se una a una sala, notificando sobre su llegada al resto de los
+    /// miembros de la habitación.
+    /// Regresa un error si la sala no existe o no se tiene una invitación.
+    pub fn unirse_a_sala(cliente: &Cliente, clientes: &mut RegistroClientes, salas: &mut RegistroSalas,
+        escuchas: &Escuchas, filtros: &Filtros, nombre_sala: &str) -> Result<String, ChatError> {
+        let nombre_cliente = Servidor::nombre_identificado(clientes, cliente,
+            "unirte a una sala")?;
+        let sala = Servidor::buscar_sala(salas, nombre_sala)?;
+        if !sala.cliente_es_invitado(cliente.get_id()) {
+            return Err(ChatError::NoInvitado(nombre_sala.to_owned()));
         }
+        sala.agregar_miembro(cliente.get_id());
+        info!(target: "Servidor", "{} se unió a la sala {}", nombre_cliente, nombre_sala);
+        let mensaje = MensajeServidor::Notificacion(Notificacion::JoinRoom {
+            sala: nombre_sala.to_owned(),
+            usuario: nombre_cliente.clone(),
+        });
+        let resultado = Servidor::notificar_miembros(cliente, clientes, sala, &mensaje, filtros);
+        Servidor::anunciar(escuchas, EventoServidor::MiembroUnido {
+            sala: nombre_sala.to_owned(),
+            usuario: nombre_cliente,
+        });
+        resultado.map(|_| format!("Te uniste a la sala {}", nombre_sala))
     }
 
-    /// Envía un mensaje público a todos los clientes en el servidor.
-    /// Regresa un error si el remitente no está identificado ó no se especifica un mensaje.
-    pub fn envia_mensaje_publico(cliente: &Cliente, mutex_clientes: &MutexCliente, argumentos: Vec<String>)
-        -> Result<String, Error> {
-        if let Some(remitente) = Servidor::obtener_nombre_cliente(&cliente, &mutex_clientes) {
-            let mut mensaje = argumentos.join(" ");
-            if mensaje.len() > 0 {
-                let remitente = format!("Público-{}: ", remitente);
-                mensaje = remitente + &mensaje;
-                let mut clientes = mutex_clientes.lock().unwrap();
-                for cliente_iter in clientes.iter_mut() {
-                    cliente_iter.enviar_mensaje(&mensaje.clone())?;
-                }
-                Ok(String::new())
-            }
-            else {
-                Err(Error::new(ErrorKind::ConnectionRefused,
-                    format!("No se identificó el contenido del mensaje")))
-            }
-        }
-        else {
-            Err(Error::new(ErrorKind::ConnectionRefused,
-                format!("Debes identificarte para enviar un mensaje")))
+    /// Envía un mensaje a todos los miembros de una sala en específico.
+    /// Regresa un error si la sala no existe o no se es miembro de la sala.
+    pub fn envia_mensaje_sala(cliente: &Cliente, clientes: &mut RegistroClientes, salas: &mut RegistroSalas,
+        escuchas: &Escuchas, filtros: &Filtros, nombre_sala: &str, texto: &str)
+        -> Result<String, ChatError> {
+        let remitente = Servidor::nombre_identificado(clientes, cliente,
+            "enviar mensajes a la sala")?;
+        let sala = Servidor::buscar_sala(salas, nombre_sala)?;
+        if !sala.cliente_es_miembro(cliente.get_id()) {
+            return Err(ChatError::NoMiembro(nombre_sala.to_owned()));
         }
+        let mensaje = MensajeServidor::Notificacion(Notificacion::RoomMessage {
+            sala: nombre_sala.to_owned(),
+            remitente: remitente.clone(),
+            texto: texto.to_owned(),
+        });
+        let resultado = Servidor::notificar_miembros(cliente, clientes, sala, &mensaje, filtros);
+        Servidor::anunciar(escuchas, EventoServidor::MensajeSala {
+            sala: nombre_sala.to_owned(),
+            remitente,
+            texto: texto.to_owned(),
+        });
+        resultado.map(|_| format!("Mensaje enviado a la sala {}", nombre_sala))
     }
 
-    /// Regresa el cliente al cual se busca enviar un mensaje. Regresa un error si no se encuentra.
-    pub fn obtener_destinatario(mutex_clientes: &MutexCliente, argumentos: &mut Vec<String>)
-        -> Result<Cliente, Error> {
-        if argumentos.len() != 0 {
-            let nombre_a_buscar = argumentos.remove(0);
-            let clientes = mutex_clientes.lock().unwrap();
-            for cliente in clientes.iter() {
-                if let Some(nombre) = cliente.get_nombre() {
-                    if nombre.eq(&nombre_a_buscar) {
-                        return Ok(cliente.clone())
-                    }
+    /// Regresa el nombre de un cliente, o un error con la acción recibida si el cliente
+    /// aún no se identifica.
+    fn nombre_identificado(clientes: &RegistroClientes, cliente: &Cliente, accion: &str)
+        -> Result<String, ChatError> {
+        clientes.nombre_de(cliente.get_id()).ok_or_else(|| ChatError::NoIdentificado(accion.to_owned()))
+    }
+
+    /// Regresa la sala con el nombre, o un error si no existe.
+    fn buscar_sala<'a>(salas: &'a mut RegistroSalas, nombre_sala: &str)
+        -> Result<&'a mut Sala, ChatError> {
+        salas.obtener_mut(nombre_sala).ok_or_else(|| ChatError::SalaInexistente(nombre_sala.to_owned()))
+    }
+
+    /// Envía una notificación de un remitente a los miembros de una sala a través del
+    /// registro de clientes, cada uno en el formato de su conexión. Los miembros que no están
+    /// en el registro no la reciben.
+    /// Regresa un error de transporte si falló la conexión del propio remitente.
+    fn notificar_miembros(remitente: &Cliente, clientes: &mut RegistroClientes, sala: &Sala,
+        mensaje: &MensajeServidor, filtros: &Filtros) -> Result<(), ChatError> {
+        let mut resultado = Ok(());
+        for id in sala.get_miembros() {
+            if let Some(miembro) = clientes.obtener_mut(*id) {
+                if let Err(error) = Servidor::entregar_desde(remitente, miembro, mensaje, filtros) {
+                    resultado = Err(error);
                 }
             }
-            Err(Error::new(ErrorKind::ConnectionRefused,
-                format!("No se encontró al usuario {}", nombre_a_buscar)))
-        }
-        else {
-            Err(Error::new(ErrorKind::ConnectionRefused, "No se especificó el destinatario"))
         }
+        resultado
     }
 
-    /// Crea una nueva sala, cuyo propietario es el creador de la misma.
-    /// Regresa un error si la sala ya existe.
-    pub fn crear_sala(cliente: &Cliente, mutex_clientes: &MutexCliente, mutex_salas: &MutexSala,
-        mut argumentos: Vec<String>) -> Result<String, Error> {
-        if argumentos.len() != 0 {
-            let nombre_nueva_sala = argumentos.remove(0);
-            if Servidor::sala_es_unica(&nombre_nueva_sala, mutex_salas) {
-                let mut salas = mutex_salas.lock().unwrap();
-                let mut nueva_sala = Sala::new(&nombre_nueva_sala, cliente.get_direccion());
-                nueva_sala.agregar_miembro(cliente.get_direccion(), cliente.get_socket());
-                salas.push(nueva_sala);
-                if let Some(nombre) = Servidor::obtener_nombre_cliente(&cliente, &mutex_clientes) {
-                    info!(target: "Servidor", "{} creó la sala {}", nombre, nombre_nueva_sala);
+    /// Agrega una notificación a la cola de salida de un cliente, después de aplicarle los
+    /// filtros recibidos; si un filtro la rechaza, se descarta. En formato de texto, los
+    /// clientes que no acordaron [`CODES`](../capacidad/enum.Capacidad.html#variant.CODES)
+    /// la reciben como texto plano, como `bob: hola`. Si no es posible, el error
+    /// solo afecta a ese cliente: el mensaje se descarta, y si su cola excedió el plazo de
+    /// saturación o su conexión falló, su socket se cierra para que el hilo que lo atiende
+    /// lo desconecte.
+    /// Regresa un error de transporte si la conexión del cliente falló; que su cola esté
+    /// llena no es un error.
+    pub fn entregar(destinatario: &mut Cliente, mensaje: &MensajeServidor, filtros: &Filtros)
+        -> Result<(), ChatError> {
+        let mensaje = match filtros.filtrar_mensaje(destinatario, mensaje.clone()) {
+            Ok(mensaje) => mensaje,
+            Err(razon) => {
+                info!(target: "Servidor", "Se descartó un mensaje para el cliente {}: {}",
+                    destinatario.get_id(), razon);
+                return Ok(());
+            },
+        };
+        match destinatario.enviar(&mensaje) {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                warn!(target: "Servidor", "No se pudo entregar un mensaje al cliente {}: {}",
+                    destinatario.get_id(), error);
+                if error.kind() == ErrorKind::WouldBlock {
+                    Ok(())
+                } else {
+                    Err(ChatError::Transporte(error))
                 }
-                let confirmacion = format!("Creación de la sala {} exitosa", nombre_nueva_sala);
-                return Ok(confirmacion);
-            }
-            else {
-                Err(Error::new(ErrorKind::ConnectionRefused, "Ya existe una sala con ese nombre"))
-            }
-        }
-        else {
-            Err(Error::new(ErrorKind::ConnectionRefused, "No se especificó el nombre de la sala"))
+            },
         }
     }
 
-    /// Determina si el nombre de una nueva sala ya ha sido utilizado.
-    pub fn sala_es_unica(nombre: &str, mutex_salas: &MutexSala) -> bool {
-        let mut salas = mutex_salas.lock().unwrap();
-        for sala in salas.iter_mut() {
-            if sala.get_nombre().eq(nombre) {
-                return false;
-            }
+    /// Entrega un mensaje provocado por el comando de un remitente con
+    /// [`entregar`](#method.entregar). Los errores al entregarlo a otro cliente sólo le
+    /// afectan a él, por lo que sólo se regresa el error de transporte si el destinatario
+    /// es el propio remitente.
+    fn entregar_desde(remitente: &Cliente, destinatario: &mut Cliente, mensaje: &MensajeServidor,
+        filtros: &Filtros) -> Result<(), ChatError> {
+        let resultado = Servidor::entregar(destinatario, mensaje, filtros);
+        if destinatario.get_id() == remitente.get_id() {
+            resultado
+        } else {
+            Ok(())
         }
-        true
     }
 
-    /// Dado un vector de clientes y una sala, envía una invitación de unirse a la sala a cada cliente.
-    /// Regresa un error si el remitente no se ha identificado, la sala no existe o si no
-    /// se es propietario de la misma.
-    pub fn enviar_invitacion(cliente: &Cliente, mutex_clientes: &MutexCliente, mutex_salas: &MutexSala,
-        mut argumentos: Vec<String>) -> Result<String, Error> {
-        if argumentos.len() == 0 {
-            return Err(Error::new(ErrorKind::ConnectionRefused,
-                "No se especificó la sala"));
-        }
-        let nombre_sala = argumentos.remove(0);
-        if let Some(nombre_anfitrion) = Servidor::obtener_nombre_cliente(&cliente, &mutex_clientes){
-            let mut salas = mutex_salas.lock().unwrap();
-            for sala in salas.iter_mut() {
-                if sala.get_nombre().eq(&nombre_sala) {
-                    if sala.es_propietario(cliente.get_direccion()) {
-                        let invitacion = format!("Invitación de unirse a la sala {} por {}",
-                        &nombre_sala, nombre_anfitrion);
-                        let mut invitados = Servidor::buscar_clientes(mutex_clientes, argumentos);
-                        for cliente_iter in invitados.iter_mut() {
-                            sala.invitar_miembro(cliente_iter.get_direccion(), cliente_iter.get_socket());
-                            cliente_iter.enviar_mensaje(&invitacion.to_owned())?;
-                        }
-                        let confirmacion = format!("Invitaciones de la sala {} enviadas", nombre_sala);
-                        return Ok(confirmacion);
-                    }
-                    else {
-                        return  Err(Error::new(ErrorKind::ConnectionRefused,
-                            "Debes ser propietario de la sala para invitar personas a unirse"));
-                        }
-                    }
-                }
-                Err(Error::new(ErrorKind::ConnectionRefused, "La sala no existe"))
-        }
-        else {
-            Err(Error::new(ErrorKind::ConnectionRefused,
-                format!("Debes identificarte para invitar usuarios")))
-        }
+    /// Elimina de memoria a un cliente creado en el servidor, anunciando su desconexión.
+    pub fn desconectar_cliente(cliente: &Cliente, clientes: &mut RegistroClientes, salas: &mut RegistroSalas,
+        escuchas: &Escuchas) {
+        let mut cliente = match clientes.eliminar(cliente.get_id()) {
+            Some(cliente) => cliente,
+            None => return,
+        };
+        salas.eliminar_cliente(cliente.get_id());
+        cliente.detener();
+        info!(target: "Servidor", "Se desconectó al cliente {}", cliente.get_direccion());
+        Servidor::anunciar(escuchas, EventoServidor::ClienteDesconectado {
+            id: cliente.get_id(),
+            nombre: cliente.get_nombre().clone(),
+        });
     }
 
-    /// Dado un vector de nombres, busca a los clientes con dichos nombres.
-    pub fn buscar_clientes(mutex_clientes: &MutexCliente, nombres_clientes: Vec<String>) -> Vec<Cliente> {
-        let clientes = mutex_clientes.lock().unwrap();
-        let mut encontrados: Vec<Cliente> = Vec::new();
-        for cliente in clientes.iter() {
-            if let Some(nombre) = cliente.get_nombre() {
-                if nombres_clientes.contains(&nombre) {
-                    encontrados.push(cliente.clone())
-                }
+    /// Define el formato de los mensajes de la conexión de un cliente. El formato sólo puede
+    /// cambiarse antes de que el cliente se identifique.
+    pub fn cambiar_formato(cliente: &mut Cliente, clientes: &mut RegistroClientes, formato: Formato)
+        -> Result<String, ChatError> {
+        Servidor::configurar_conexion(cliente, clientes, |cliente_iter: &mut Cliente| {
+            let mut capacidades = cliente_iter.get_capacidades().to_vec();
+            capacidades.retain(|capacidad| *capacidad != Capacidad::JSON);
+            if formato == Formato::JSON {
+                capacidades.push(Capacidad::JSON);
             }
-        }
-        encontrados
+            cliente_iter.set_capacidades(capacidades);
+            cliente_iter.set_formato(formato);
+        })?;
+        info!(target: "Servidor",
+            "El cliente con dirección {} cambió su formato a {}", cliente.get_direccion(), formato);
+        Ok(format!("Formato cambiado a: {}", formato))
     }
 
-    /// Permite que un cliente se una a una sala, notificando sobre su llegada al resto de los
-    /// miembros de la habitación.
-    /// Regresa un error si la sala no existe o no se tiene una invitación.
-    pub fn unirse_a_sala(cliente: &Cliente, mutex_clientes: &MutexCliente, mutex_salas: &MutexSala,
-        mut argumentos: Vec<String>) -> Result<String, Error> {
-        if argumentos.len() == 0 {
-            return Err(Error::new(ErrorKind::ConnectionRefused,
-                "No se especificó la sala"));
-        }
-        if let Some(nombre_cliente) = Servidor::obtener_nombre_cliente(&cliente, &mutex_clientes) {
-            let nombre_sala = argumentos.remove(0);
-            let mut salas = mutex_salas.lock().unwrap();
-            for sala in salas.iter_mut() {
-                if sala.get_nombre().eq(&nombre_sala) {
-                    if sala.cliente_es_invitado(cliente.get_direccion()) {
-                        let invitado = cliente.get_socket().try_clone().expect("Error al clonar socket");
-                        sala.agregar_miembro(cliente.get_direccion(), &invitado);
-                        info!(target: "Servidor", "{} se unió a la sala {}", nombre_cliente, nombre_sala);
-                        let mensaje = format!("{} se unió a la sala {}", nombre_cliente, nombre_sala);
-                        for (_, socket_miembro) in sala.get_miembros().iter_mut() {
-                            util::enviar_mensaje(socket_miembro, mensaje.clone())?;
-                        }
-                        return Ok(String::new());
-                    }
-                    else {
-                        return Err(Error::new(ErrorKind::ConnectionRefused,
-                                "No estás invitado para unirte"));
-                    }
-                }
-            }
-            return Err(Error::new(ErrorKind::ConnectionRefused, "La sala no existe"));
-        }
-        else {
-            Err(Error::new(ErrorKind::ConnectionRefused, "Debes identificarte primero"))
-        }
+    /// Negocia con un cliente la versión del protocolo y las capacidades de su conexión.
+    /// La versión acordada es la menor entre la del cliente y la del servidor, y las
+    /// capacidades acordadas son las solicitadas que el servidor soporta; si entre ellas
+    /// está [`JSON`](../capacidad/enum.Capacidad.html#variant.JSON), la conexión cambia a
+    /// dicho formato. Regresa la versión seguida de las capacidades acordadas.
+    /// El saludo sólo puede realizarse antes de que el cliente se identifique.
+    pub fn saludar(cliente: &mut Cliente, clientes: &mut RegistroClientes, version: u32,
+        solicitadas: &[String]) -> Result<String, ChatError> {
+        let version = cmp::min(version, VERSION_PROTOCOLO);
+        let capacidades = Capacidad::negociar(solicitadas);
+        let formato = if capacidades.contains(&Capacidad::JSON) { Formato::JSON } else { Formato::TEXT };
+        Servidor::configurar_conexion(cliente, clientes, |cliente_iter: &mut Cliente| {
+            cliente_iter.set_version(version);
+            cliente_iter.set_capacidades(capacidades.clone());
+            cliente_iter.set_formato(formato);
+        })?;
+        let mut acordado = vec![version.to_string()];
+        acordado.extend(capacidades.iter().map(|capacidad| capacidad.to_string()));
+        let acordado = acordado.join(" ");
+        info!(target: "Servidor",
+            "El cliente con dirección {} acordó el protocolo {}", cliente.get_direccion(), acordado);
+        Ok(acordado)
     }
 
-    /// Envía un mensaje a todos los miembros de una sala en específico.
-    /// Regresa un error si la sala no existe o no se es miembro de la sala.
-    pub fn envia_mensaje_sala(cliente: &Cliente, mutex_clientes: &MutexCliente, mutex_salas: &MutexSala,
-        mut argumentos: Vec<String>) -> Result<String, Error> {
-        if argumentos.len() == 0 {
-            return Err(Error::new(ErrorKind::ConnectionRefused,
-                "No se especificó la sala"));
-        }
-        let nombre_sala = argumentos.remove(0);
-        if let Some(remitente) = Servidor::obtener_nombre_cliente(&cliente, &mutex_clientes) {
-            let mut salas = mutex_salas.lock().unwrap();
-            for sala in salas.iter_mut() {
-                if sala.get_nombre().eq(&nombre_sala) {
-                    if sala.cliente_es_miembro(cliente.get_direccion()) {
-                        let mut mensaje = argumentos.join(" ");
-                        if mensaje.len() > 0 {
-                            let remitente = format!("{}-{}: ", &nombre_sala, &remitente);
-                            mensaje = remitente + &mensaje;
-                            for (_, socket_miembro) in sala.get_miembros().iter_mut() {
-                                util::enviar_mensaje(socket_miembro, mensaje.clone())?;
-                            }
-                            return Ok(String::new());
-                        }
-                        else {
-                            return Err(Error::new(ErrorKind::ConnectionRefused,
-                                format!("No se identificó el contenido del mensaje")));
-                        }
-                    }
-                    else {
-                        return  Err(Error::new(ErrorKind::ConnectionRefused,
-                            "No eres miembro de esa sala"));
-                    }
-                }
+    /// Aplica una configuración a la conexión de un cliente, tanto en la lista de clientes
+    /// del servidor como en la copia recibida.
+    /// Regresa un error si el cliente ya se identificó.
+    fn configurar_conexion<F>(cliente: &mut Cliente, clientes: &mut RegistroClientes, configurar: F)
+        -> Result<(), ChatError> where F: Fn(&mut Cliente) {
+        if let Some(cliente_registrado) = clientes.obtener_mut(cliente.get_id()) {
+            if cliente_registrado.get_nombre().is_some() {
+                return Err(ChatError::YaIdentificado);
             }
-            Err(Error::new(ErrorKind::ConnectionRefused, "La sala no existe"))
-        }
-        else {
-            Err(Error::new(ErrorKind::ConnectionRefused,
-                format!("Debes identificarte enviar mensajes a la sala")))
+            configurar(cliente_registrado);
         }
+        configurar(cliente);
+        Ok(())
     }
 
-    /// Elimina de memoria a un cliente creado en el servidor.
-    pub fn desconectar_cliente(cliente: &Cliente, mutex_clientes: &MutexCliente, mutex_salas: &MutexSala) {
-        let mut clientes = mutex_clientes.lock().unwrap();
-        let indice_cliente = clientes.iter().
-                position(|cliente_iter| cliente.eq(cliente_iter)).unwrap();
-        let mut cliente = clientes.remove(indice_cliente);
-        let mut salas = mutex_salas.lock().unwrap();
-        for mut sala in salas.iter_mut() {
-            if sala.cliente_es_invitado(cliente.get_direccion()) {
-                sala.elimina_invitado(cliente.get_direccion());
-            }
-            if sala.cliente_es_miembro(cliente.get_direccion()) {
-                sala.elimina_miembro(cliente.get_direccion());
-            }
+    /// Obtiene el formato de los mensajes de la conexión de un cliente existente en el servidor.
+    pub fn obtener_formato_cliente(cliente: &Cliente, clientes: &RegistroClientes) -> Formato {
+        match clientes.obtener(cliente.get_id()) {
+            Some(cliente) => cliente.get_formato(),
+            None => cliente.get_formato(),
         }
-        cliente.detener();
-        info!(target: "Servidor", "Se desconectó al cliente {}", cliente.get_direccion());
     }
 
     /// Obtiene el nombre de un cliente existente en el servidor.
-    pub fn obtener_nombre_cliente(cliente: &Cliente, mutex_clientes: &MutexCliente) -> Option<String> {
-        let clientes = mutex_clientes.lock().unwrap();
-        let indice_cliente = clientes.iter().
-                position(|cliente_iter| cliente.eq(cliente_iter)).unwrap();
-        let cliente = &clientes[indice_cliente];
-        match cliente.get_nombre() {
-            Some(nombre) => {
-                Some(nombre.to_owned())
-            },
-            None => {
-                None
-            },
-        }
+    pub fn obtener_nombre_cliente(cliente: &Cliente, clientes: &RegistroClientes) -> Option<String> {
+        clientes.nombre_de(cliente.get_id())
     }
 
-    /// Determina que acción llevar a cabo dependiendo de los mensajes enviados por un cliente.
+    /// Lee el siguiente mensaje de un cliente a través de su lector de mensajes y lo procesa
+    /// con [`procesar_mensaje`](#method.procesar_mensaje), con los registros de clientes y
+    /// salas bloqueados, anunciando los eventos del chat a los escuchas recibidos y
+    /// aplicando los filtros recibidos. Después entrega a los bots los mensajes que les
+    /// envió el comando.
     /// En caso de error o que el cliente especifique su desconexión, el servidor termina la
     /// comunicación con el cliente y lo elimina de memoria.
-    pub fn reaccionar(mut cliente: Cliente, mutex_clientes: &MutexCliente, mutex_salas: &MutexSala)
-        -> Result<(), Error> {
-        let (evento, argumentos) = util::obtener_mensaje_cliente(cliente.get_socket())?;
-        match evento {
-            EventoConexion::IDENTIFY => {
-                let mensaje = match Servidor::cambiar_nombre_usuario(&cliente, mutex_clientes, argumentos) {
-                    Ok(confirmacion) => confirmacion,
-                    Err(error) => error.to_string(),
-                };
-                cliente.enviar_mensaje(&mensaje[..])?;
-                Ok(())
-            },
-            EventoConexion::STATUS => {
-                let mensaje = match Servidor::cambiar_estado_usuario(&cliente, mutex_clientes, argumentos) {
-                    Ok(confirmacion) => confirmacion,
-                    Err(error) => error.to_string(),
-                };
-                cliente.enviar_mensaje(&mensaje)?;
-                Ok(())
-            },
-            EventoConexion::USERS => {
-                let usuarios = Servidor::obtener_usuarios(mutex_clientes);
-                cliente.enviar_mensaje(&usuarios.join(" "))?;
-                Ok(())
-            },
-            EventoConexion::MESSAGE => {
-                let mensaje = match Servidor::envia_mensaje_privado(&cliente, mutex_clientes, argumentos) {
-                    Ok(confirmacion) => confirmacion,
-                    Err(error) => error.to_string(),
-                };
-                cliente.enviar_mensaje(&mensaje[..])?;
-                Ok(())
-            },
-            EventoConexion::PUBLICMESSAGE => {
-                let mensaje = match Servidor::envia_mensaje_publico(&cliente, mutex_clientes, argumentos) {
-                    Ok(confirmacion) => confirmacion,
-                    Err(error) => error.to_string(),
-                };
-                cliente.enviar_mensaje(&mensaje[..])?;
-                Ok(())
-            },
-            EventoConexion::CREATEROOM => {
-                let mensaje = match Servidor::crear_sala(&cliente, mutex_clientes, mutex_salas, argumentos) {
-                    Ok(confirmacion) => confirmacion,
-                    Err(error) => error.to_string(),
-                };
-                cliente.enviar_mensaje(&mensaje[..])?;
-                Ok(())
-            },
-            EventoConexion::INVITE => {
-                let mensaje = match Servidor::enviar_invitacion(&cliente, mutex_clientes, mutex_salas, argumentos) {
-                    Ok(confirmacion) => confirmacion,
-                    Err(error) => error.to_string(),
+    pub fn reaccionar<R: Read>(cliente: &mut Cliente, lector: &mut LectorMensajes<R>,
+        mutex_clientes: &MutexCliente, mutex_salas: &MutexSala, escuchas: &Escuchas, filtros: &Filtros,
+        bots: &Bots) -> Result<(), ChatError> {
+        let mensaje = lector.siguiente_mensaje();
+        let mut clientes = mutex_clientes.lock().unwrap();
+        let mut salas = mutex_salas.lock().unwrap();
+        let resultado = Servidor::procesar_mensaje(cliente, &mut clientes, &mut salas, escuchas,
+            filtros, mensaje);
+        bots.atender(&mut clientes, &mut salas, escuchas, filtros);
+        resultado
+    }
+
+    /// Determina que acción llevar a cabo dependiendo del mensaje enviado por un cliente,
+    /// interpretado en el formato de su conexión, y la realiza con
+    /// [`ejecutar_comando`](#method.ejecutar_comando). Ambos núcleos del servidor procesan
+    /// los mensajes con este método.
+    /// Todo comando recibe exactamente una [`Respuesta`](../respuesta/struct.Respuesta.html)
+    /// con su código y el identificador de petición que adjuntó el cliente; si el mensaje
+    /// excede el tamaño máximo, no es UTF-8 válido o no es un comando válido, la respuesta
+    /// indica el error y el
+    /// mensaje se descarta.
+    /// Regresa un error de [`Transporte`](../error/enum.ChatError.html#variant.Transporte)
+    /// si la lectura del mensaje falló o el cliente especificó su desconexión, en cuyo caso
+    /// debe desconectarse.
+    pub fn procesar_mensaje(cliente: &mut Cliente, clientes: &mut RegistroClientes,
+        salas: &mut RegistroSalas, escuchas: &Escuchas, filtros: &Filtros,
+        mensaje: Result<String, Error>) -> Result<(), ChatError> {
+        if let Some(registrado) = clientes.obtener(cliente.get_id()) {
+            cliente.set_formato(registrado.get_formato());
+            cliente.set_capacidades(registrado.get_capacidades().to_vec());
+        }
+        let linea = match mensaje {
+            Ok(linea) => linea,
+            Err(ref error) if error.kind() == ErrorKind::InvalidData => {
+                let codigo = match ErrorMensaje::de_error(error) {
+                    Some(ErrorMensaje::CodificacionInvalida) => CodigoRespuesta::ERR_INVALID_ENCODING,
+                    _ => CodigoRespuesta::ERR_MESSAGE_TOO_LONG,
                 };
-                cliente.enviar_mensaje(&mensaje[..])?;
-                Ok(())
+                let respuesta = Respuesta::new(codigo, &error.to_string());
+                return Servidor::responder(cliente, None, respuesta, filtros);
             },
-            EventoConexion::JOINROOM => {
-                let mensaje = match Servidor::unirse_a_sala(&cliente, mutex_clientes, mutex_salas, argumentos) {
-                    Ok(confirmacion) => confirmacion,
-                    Err(error) => error.to_string(),
-                };
-                cliente.enviar_mensaje(&mensaje[..])?;
-                Ok(())
+            Err(error) => return Err(ChatError::Transporte(error)),
+        };
+        let (id, comando) = cliente.get_formato().analizar_peticion(&linea);
+        let comando = match comando {
+            Ok(comando) => comando,
+            Err(ErrorComando::EventoInvalido) => {
+                let mensaje = "Mensaje inválido, mensajes válidos: HELLO, PROTOCOL, IDENTIFY, STATUS, \
+                    USERS, MESSAGE, PUBLICMESSAGE, CREATEROOM, INVITE, JOINROOM, ROOMESSAGE, DISCONNECT";
+                let respuesta = Respuesta::new(CodigoRespuesta::ERR_INVALID_COMMAND, mensaje);
+                return Servidor::responder(cliente, id, respuesta, filtros);
             },
-            EventoConexion::ROOMESSAGE => {
-                let mensaje = match Servidor::envia_mensaje_sala(&cliente, mutex_clientes, mutex_salas, argumentos) {
-                    Ok(confirmacion) => confirmacion,
-                    Err(error) => error.to_string(),
-                };
-                cliente.enviar_mensaje(&mensaje[..])?;
-                Ok(())
+            Err(error) => {
+                let respuesta = Respuesta::from(&ChatError::from(error));
+                return Servidor::responder(cliente, id, respuesta, filtros);
             },
-            EventoConexion::DISCONNECT => {
-                Err(Error::new(ErrorKind::ConnectionAborted, "El cliente terminó la conexión"))
+        };
+        Servidor::ejecutar_comando(cliente, clientes, salas, escuchas, filtros, id, comando)
+    }
+
+    /// Realiza un comando de un cliente y le envía la respuesta, con el identificador de
+    /// petición recibido. Anuncia a los escuchas recibidos los eventos del chat que provoca
+    /// el comando. Los filtros recibidos revisan el comando antes de realizarlo, y cada
+    /// mensaje antes de entregarlo; si un filtro rechaza el comando, la respuesta es
+    /// [`ERR_REJECTED`](../respuesta/enum.CodigoRespuesta.html#variant.ERR_REJECTED).
+    /// Las notificaciones provocadas por el comando se envían antes que la respuesta.
+    /// Los errores del comando, incluidos los argumentos inválidos de los comandos que no se
+    /// obtuvieron de una línea del cliente, como los de los bots o los que reescribe un
+    /// filtro, se responden con su código, sin interrumpir la conexión.
+    /// Regresa un error de [`Transporte`](../error/enum.ChatError.html#variant.Transporte)
+    /// si el comando es una desconexión, falló la conexión del cliente o no se pudo enviar
+    /// la respuesta.
+    pub fn ejecutar_comando(cliente: &mut Cliente, clientes: &mut RegistroClientes,
+        salas: &mut RegistroSalas, escuchas: &Escuchas, filtros: &Filtros, id: Option<IdPeticion>,
+        comando: Comando) -> Result<(), ChatError> {
+        let comando = {
+            let remitente = clientes.obtener(cliente.get_id()).unwrap_or(cliente);
+            filtros.filtrar_comando(remitente, comando)
+        };
+        let comando = match comando {
+            Ok(comando) => comando,
+            Err(razon) => {
+                info!(target: "Servidor", "Se rechazó un comando del cliente {}: {}",
+                    cliente.get_id(), razon);
+                let respuesta = Respuesta::new(CodigoRespuesta::ERR_REJECTED, &razon);
+                return Servidor::responder(cliente, id, respuesta, filtros);
             },
-            EventoConexion::INVALID => {
-                let mut mensaje = String::new();
-                mensaje += "Mensaje inválido, lista de mensajes válidos:\n";
-                mensaje += "IDENTIFY nombre\n";
-                mensaje += "STATUS [ACTIVE, AWAY, BUSY]\n";
-                mensaje += "USERS\n";
-                mensaje += "MESSAGE destinatario mensaje\n";
-                mensaje += "PUBLICMESSAGE mensaje\n";
-                mensaje += "CREATEROOM nombre_sala\n";
-                mensaje += "INVITE nombre_sala usuarios...\n";
-                mensaje += "JOINROOM nombre_sala\n";
-                mensaje += "ROOMESSAGE nombre_sala mensaje\n";
-                mensaje += "DISCONNECT\n";
-                cliente.enviar_mensaje(&mensaje[..])?;
-                Ok(())
+        };
+        if let Err(error) = comando.validar() {
+            return Servidor::responder(cliente, id, Respuesta::from(&ChatError::from(error)), filtros);
+        }
+        let resultado = match comando {
+            Comando::Hello { version, capacidades } =>
+                Servidor::saludar(cliente, clientes, version, &capacidades),
+            Comando::Protocol { formato } =>
+                Servidor::cambiar_formato(cliente, clientes, formato),
+            Comando::Identify { nombre } =>
+                Servidor::cambiar_nombre_usuario(cliente, clientes, escuchas, nombre),
+            Comando::Status { estado } =>
+                Servidor::cambiar_estado_usuario(cliente, clientes, escuchas, estado),
+            Comando::Users => Ok(Servidor::obtener_usuarios(clientes).join(" ")),
+            Comando::Message { destinatario, texto } =>
+                Servidor::envia_mensaje_privado(cliente, clientes, escuchas, filtros, &destinatario, &texto),
+            Comando::PublicMessage { texto } =>
+                Servidor::envia_mensaje_publico(cliente, clientes, escuchas, filtros, &texto),
+            Comando::CreateRoom { sala } =>
+                Servidor::crear_sala(cliente, clientes, salas, escuchas, &sala),
+            Comando::Invite { sala, usuarios } =>
+                Servidor::enviar_invitacion(cliente, clientes, salas, escuchas, filtros, &sala, &usuarios),
+            Comando::JoinRoom { sala } =>
+                Servidor::unirse_a_sala(cliente, clientes, salas, escuchas, filtros, &sala),
+            Comando::RoomMessage { sala, texto } =>
+                Servidor::envia_mensaje_sala(cliente, clientes, salas, escuchas, filtros, &sala, &texto),
+            Comando::Disconnect => {
+                return Err(ChatError::Transporte(Error::new(ErrorKind::ConnectionAborted,
+                    "El cliente terminó la conexión")));
             },
-            EventoConexion::ERROR => {
-                Err(Error::new(ErrorKind::ConnectionAborted, "La conexión fue interrumpida"))
+        };
+        let respuesta = match resultado {
+            Ok(confirmacion) => Respuesta::ok(&confirmacion),
+            Err(error) if error.es_transporte() => return Err(error),
+            Err(error) => Respuesta::from(&error),
+        };
+        Servidor::responder(cliente, id, respuesta, filtros)
+    }
+
+    /// Envía al cliente la respuesta a su comando, con el identificador de petición que
+    /// adjuntó al comando, después de aplicarle los filtros recibidos; si un filtro la
+    /// rechaza, se descarta. Si la cola de salida del cliente está llena, la respuesta se
+    /// descarta sin interrumpir la conexión, a menos que haya excedido el plazo de saturación.
+    /// El identificador sólo se incluye si el cliente acordó la capacidad
+    /// [`IDS`](../capacidad/enum.Capacidad.html#variant.IDS) o usa el formato JSON, y en
+    /// formato de texto la respuesta sólo lleva su código si acordó
+    /// [`CODES`](../capacidad/enum.Capacidad.html#variant.CODES); si no, se envía como texto
+    /// plano, precedida del identificador de la forma `#<id>` si lo lleva.
+    fn responder(cliente: &mut Cliente, id: Option<IdPeticion>, mut respuesta: Respuesta,
+        filtros: &Filtros) -> Result<(), ChatError> {
+        let repite_id = cliente.tiene_capacidad(Capacidad::IDS) || cliente.get_formato() == Formato::JSON;
+        respuesta.set_id(id.filter(|_| repite_id));
+        let mensaje = match filtros.filtrar_mensaje(cliente, MensajeServidor::Respuesta(respuesta)) {
+            Ok(mensaje) => mensaje,
+            Err(razon) => {
+                info!(target: "Servidor", "Se descartó una respuesta para el cliente {}: {}",
+                    cliente.get_id(), razon);
+                return Ok(());
             },
+        };
+        match cliente.enviar(&mensaje) {
+            Err(ref error) if error.kind() == ErrorKind::WouldBlock => Ok(()),
+            resultado => resultado.map_err(ChatError::Transporte),
         }
     }
 }
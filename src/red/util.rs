@@ -1,80 +1,185 @@
-use super::{eventoconexion::EventoConexion};
-use std::net::{TcpStream};
+use super::{comando::{Comando, ErrorComando, IdPeticion}, formato::Formato, lector::LectorMensajes};
 use std::io::{Read, Write};
-use std::io::{Error, ErrorKind};
+use std::io::Error;
 
 /// Constante que representa al carácter nulo, presente cuando el buffer lee un mensaje
 /// por completo sin llenarse.
+#[deprecated(note = "Los mensajes se leen con `lector::LectorMensajes`")]
 pub const CHAR_NULL: u8 = 0;
 
 /// Constante que representa el carácter de salto de línea "\n".
 pub const SALTO_DE_LINEA: u8 = 10;
 
-/// Regresa una cadena extraída de un buffer de carácteres en UTF-8
+/// Carácter que, al inicio de un argumento, indica que éste abarca el resto de la línea.
+pub const PREFIJO_ARGUMENTO_FINAL: char = ':';
+
+/// Carácter que delimita los argumentos que contienen espacios.
+pub const COMILLAS: char = '"';
+
+/// Carácter que hace que el siguiente carácter se tome literalmente.
+pub const ESCAPE: char = '\\';
+
+/// Regresa una cadena extraída de un buffer de carácteres en UTF-8. Las secuencias
+/// inválidas, como un carácter de varios bytes cortado al final del buffer, se reemplazan
+/// por el carácter de reemplazo U+FFFD.
+#[deprecated(note = "Los mensajes se leen con `lector::LectorMensajes`")]
+#[allow(deprecated)]
 pub fn mensaje_de_buffer(buffer: &[u8; 180]) -> String {
-    let mut mensaje: Vec<u8> = buffer.to_vec().into_iter()
+    let mut mensaje: Vec<u8> = buffer.iter().copied()
         .filter(|&x| x != CHAR_NULL).collect();
-    if mensaje.len() > 1 {
-        if mensaje[mensaje.len() - 1] == SALTO_DE_LINEA {
-            mensaje.pop();
-        }
+    if mensaje.len() > 1 && mensaje[mensaje.len() - 1] == SALTO_DE_LINEA {
+        mensaje.pop();
+    }
+    String::from_utf8_lossy(&mensaje).into_owned()
+}
+
+/// Determina si un carácter no debe aparecer en nombres ni mensajes: los caracteres de
+/// control, como los saltos de línea o las secuencias de escape de las terminales, y los
+/// que alteran la dirección del texto, ya que permiten falsificar líneas en la vista de
+/// otros usuarios.
+pub fn es_caracter_prohibido(caracter: char) -> bool {
+    match caracter {
+        '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' => true,
+        _ => caracter.is_control(),
+    }
+}
+
+/// Dada una conexión de comunicación, como un socket, regresa una cadena con el siguiente
+/// mensaje leído. La conexión se lee un byte a la vez para no consumir datos de mensajes posteriores,
+/// por lo que para lecturas repetidas sobre una misma conexión es preferible usar un
+/// [`LectorMensajes`](../lector/struct.LectorMensajes.html).
+pub fn obtener_mensaje_conexion<R: Read>(conexion: R) -> Result<String, Error> {
+    LectorMensajes::new(ByteAByte(conexion)).siguiente_mensaje()
+}
+
+/// Dado un lector de mensajes de un cliente y el formato de su conexión, regresa el
+/// siguiente comando que especificó el cliente junto con su identificador de petición,
+/// si lo adjuntó. El resultado externo indica si la lectura fue exitosa, mientras que el
+/// interno indica si la línea leída es un comando válido del protocolo.
+pub fn obtener_mensaje_cliente<R: Read>(lector: &mut LectorMensajes<R>, formato: Formato)
+    -> Result<(Option<IdPeticion>, Result<Comando, ErrorComando>), Error> {
+    let mensaje = lector.siguiente_mensaje()?;
+    Ok(formato.analizar_peticion(&mensaje))
+}
+
+/// Separa una línea del protocolo en sus argumentos. Los argumentos se separan por uno o
+/// más espacios, y pueden contener espacios si se encierran entre comillas (`"sala 1"`).
+/// Una diagonal invertida hace que el siguiente carácter se tome literalmente, dentro o
+/// fuera de las comillas (`\"` o `\\`). Un argumento que comienza con `:` es el argumento
+/// final, y abarca el resto de la línea tal cual, sin interpretar comillas ni escapes.
+/// Regresa un error si unas comillas no se cierran.
+pub fn separar_argumentos(linea: &str) -> Result<Vec<String>, ErrorComando> {
+    separar_con_resto(linea, usize::MAX).map(|(argumentos, _)| argumentos)
+}
+
+/// Separa a lo más `fijos` argumentos del inicio de una línea del protocolo, como lo hace
+/// [`separar_argumentos`](fn.separar_argumentos.html), y regresa además el resto de la
+/// línea tal cual, sin los espacios que lo preceden. Si uno de los argumentos es el
+/// argumento final, el resto es vacío.
+/// Regresa un error si unas comillas de los argumentos separados no se cierran.
+pub fn separar_con_resto(linea: &str, fijos: usize) -> Result<(Vec<String>, &str), ErrorComando> {
+    let mut argumentos = Vec::new();
+    let mut posicion = saltar_espacios(linea, 0);
+    while argumentos.len() < fijos && posicion < linea.len() {
+        let (argumento, fin) = leer_argumento(linea, posicion)?;
+        argumentos.push(argumento);
+        posicion = saltar_espacios(linea, fin);
     }
-    let mensaje = String::from_utf8(mensaje).unwrap();
-    mensaje
+    Ok((argumentos, &linea[posicion..]))
 }
 
-/// Dado un socket de comunicación, regresa una cadena con el mensaje leído.
-pub fn obtener_mensaje_conexion(mut socket: &TcpStream) -> Result<String, Error> {
-    let mut buffer = [0; 180];
-    match socket.read(&mut buffer) {
-        Ok(count) => {
-            if count > 0 {
-                Ok(mensaje_de_buffer(&buffer))
-            }
-            else {
-                Err(Error::new(ErrorKind::ConnectionAborted, "El cliente terminó la conexión"))
-            }
-        },
-        _ => {
-            Err(Error::new(ErrorKind::ConnectionAborted, "El cliente terminó la conexión"))
+/// Regresa la posición del primer carácter de la línea distinto de un espacio a partir de
+/// `inicio`, o la longitud de la línea si no hay ninguno.
+fn saltar_espacios(linea: &str, inicio: usize) -> usize {
+    linea[inicio..].find(|caracter| caracter != ' ').map_or(linea.len(), |i| inicio + i)
+}
+
+/// Lee el argumento que comienza en la posición `inicio` de la línea, y regresa el
+/// argumento junto con la posición en la que termina.
+fn leer_argumento(linea: &str, inicio: usize) -> Result<(String, usize), ErrorComando> {
+    if linea[inicio..].starts_with(PREFIJO_ARGUMENTO_FINAL) {
+        let argumento = linea[inicio + PREFIJO_ARGUMENTO_FINAL.len_utf8()..].to_owned();
+        return Ok((argumento, linea.len()));
+    }
+    let mut argumento = String::new();
+    let mut entre_comillas = false;
+    let mut fin = linea.len();
+    let mut caracteres = linea[inicio..].char_indices();
+    while let Some((i, caracter)) = caracteres.next() {
+        match caracter {
+            ESCAPE => match caracteres.next() {
+                Some((_, escapado)) => argumento.push(escapado),
+                None => argumento.push(ESCAPE),
+            },
+            COMILLAS => entre_comillas = !entre_comillas,
+            ' ' if !entre_comillas => {
+                fin = inicio + i;
+                break;
+            },
+            _ => argumento.push(caracter),
         }
     }
+    if entre_comillas {
+        return Err(ErrorComando::ComillasSinCerrar);
+    }
+    Ok((argumento, fin))
 }
 
-/// Dado un socket de comunicación de un cliente, regresa una tupla que contiene el
-/// evento del protocolo que especificó el cliente y un vector con los argumentos de dicho evento.
-pub fn obtener_mensaje_cliente(mut socket: &TcpStream)
-    -> Result<(EventoConexion, Vec<String>), Error> {
-    let mut buffer = [0; 180];
-    match socket.read(&mut buffer) {
-        Ok(count) => {
-            if count > 0 {
-                let mensaje = mensaje_de_buffer(&buffer);
-                let mut argumentos: Vec<String> = mensaje.split(" ").map(|s| s.to_string()).collect();
-                let evento = argumentos.remove(0).parse::<EventoConexion>();
-                match evento {
-                    Ok(evento) => {
-                        Ok((evento, argumentos))
-                    },
-                    Err(_) => {
-                        Ok((EventoConexion::INVALID, Vec::new()))
-                    }
-                }
-            }
-            else {
-                Err(Error::new(ErrorKind::ConnectionAborted, "El cliente terminó la conexión"))
-            }
-        },
-        _ => {
-            Err(Error::new(ErrorKind::ConnectionAborted, "El cliente terminó la conexión"))
+/// Regresa un argumento tal que [`separar_argumentos`](fn.separar_argumentos.html) lo
+/// interprete como un solo argumento, encerrándolo entre comillas sólo si es necesario.
+pub fn citar(argumento: &str) -> String {
+    let requiere_comillas = argumento.is_empty() || argumento.starts_with(PREFIJO_ARGUMENTO_FINAL) ||
+        argumento.contains([' ', COMILLAS, ESCAPE]);
+    if !requiere_comillas {
+        return argumento.to_owned();
+    }
+    let mut citado = String::with_capacity(argumento.len() + 2);
+    citado.push(COMILLAS);
+    for caracter in argumento.chars() {
+        if caracter == COMILLAS || caracter == ESCAPE {
+            citado.push(ESCAPE);
         }
+        citado.push(caracter);
+    }
+    citado.push(COMILLAS);
+    citado
+}
+
+/// Regresa el contenido de un mensaje como el último argumento de una línea. El contenido
+/// se escribe tal cual si al separarlo en argumentos y unirlos con un espacio se obtiene
+/// el mismo texto, y en otro caso se escribe como argumento final, precedido de `:`.
+pub fn citar_final(texto: &str) -> String {
+    match separar_argumentos(texto) {
+        Ok(ref argumentos) if argumentos.join(" ") == texto => texto.to_owned(),
+        _ => format!("{}{}", PREFIJO_ARGUMENTO_FINAL, texto),
     }
 }
 
-/// Envía un mensaje por un socket de comunicación.
-pub fn enviar_mensaje(mut socket: &TcpStream, mensaje: String) -> Result<(), Error>{
-    let mensaje = mensaje.into_bytes();
-    socket.write(&mensaje[..])?;
-    socket.flush()?;
+/// Regresa los bytes a enviar por la red para un mensaje, terminados en un salto de línea.
+/// Un mensaje vacío no produce bytes.
+pub fn enmarcar_mensaje(mensaje: &str) -> Vec<u8> {
+    let mut bytes = mensaje.as_bytes().to_vec();
+    if !bytes.is_empty() && bytes[bytes.len() - 1] != SALTO_DE_LINEA {
+        bytes.push(SALTO_DE_LINEA);
+    }
+    bytes
+}
+
+/// Envía un mensaje por una conexión de comunicación, como un socket, terminado en un
+/// salto de línea.
+pub fn enviar_mensaje<W: Write>(mut conexion: W, mensaje: String) -> Result<(), Error>{
+    let mensaje = enmarcar_mensaje(&mensaje);
+    conexion.write_all(&mensaje[..])?;
+    conexion.flush()?;
     Ok(())
 }
+
+/// Adaptador que limita cada lectura a un solo byte.
+struct ByteAByte<R: Read>(R);
+
+impl<R: Read> Read for ByteAByte<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let limite = if !buffer.is_empty() { 1 } else { 0 };
+        self.0.read(&mut buffer[..limite])
+    }
+}
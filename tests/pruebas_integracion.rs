@@ -1,623 +1,1511 @@
 extern crate chat;
-
-use chat::red::{servidor::Servidor, eventoservidor::EventoServidor, util};
+extern crate futures;
+extern crate tokio;
+
+use chat::red::{servidor::Servidor, eventoservidor::{EventoServidor, TipoEvento}, util,
+    respuesta::{Respuesta, CodigoRespuesta}, notificacion::Notificacion,
+    mensajeservidor::MensajeServidor, formato::Formato, nucleo::Nucleo,
+    estadocliente::EstadoCliente, filtro::{Filtro, Decision}, cliente::Cliente,
+    comando::Comando, bot::Bot, conexion::{AceptadorMemoria, Conexion}, error::ChatError};
+use chat::cliente::{ClienteChat, ErrorCliente, TAMANO_MAXIMO_MENSAJE_SERVIDOR, asincrono::ClienteAsincrono};
+use futures::StreamExt;
+use tokio::runtime::{Builder, Runtime};
 use std::{time, thread};
-use std::net::TcpStream;
+use std::sync::{mpsc, Arc, Barrier};
+use std::net::{TcpListener, TcpStream, SocketAddr};
+use std::io::{self, ErrorKind, Read, Write};
+
+/// Filtro que rechaza los mensajes públicos con enlaces y censura una palabra en las
+/// notificaciones de mensajes públicos.
+struct FiltroPrueba;
+
+impl Filtro for FiltroPrueba {
+    fn filtrar_comando(&self, _cliente: &Cliente, comando: &Comando) -> Decision<Comando> {
+        match *comando {
+            Comando::PublicMessage { ref texto } if texto.contains("http://") =>
+                Decision::Rechazar("No se permiten enlaces".to_string()),
+            _ => Decision::Permitir,
+        }
+    }
+
+    fn filtrar_mensaje(&self, _destinatario: &Cliente, mensaje: &MensajeServidor)
+        -> Decision<MensajeServidor> {
+        match *mensaje {
+            MensajeServidor::Notificacion(Notificacion::PublicMessage { ref remitente, ref texto }) =>
+                Decision::Reescribir(MensajeServidor::Notificacion(Notificacion::PublicMessage {
+                    remitente: remitente.clone(),
+                    texto: texto.replace("tonto", "*****"),
+                })),
+            _ => Decision::Permitir,
+        }
+    }
+}
 
-#[test]
-fn t1_acepta_conexiones() {
-    let puerto = "9090";
-    let mut servidor = Servidor::new(puerto);
+/// Bot que repite los mensajes privados y de sala que recibe, y acepta toda invitación.
+/// Se desconecta al recibir el mensaje privado `adios`.
+struct BotEco;
+
+impl Bot for BotEco {
+    fn get_nombre(&self) -> &str {
+        "eco"
+    }
+
+    fn on_message(&mut self, remitente: &str, texto: &str) -> Vec<Comando> {
+        let eco = Comando::Message { destinatario: remitente.to_string(), texto: texto.to_string() };
+        if texto == "adios" {
+            return vec![Comando::Disconnect, eco];
+        }
+        vec![eco]
+    }
+
+    fn on_invite(&mut self, sala: &str, _anfitrion: &str) -> Vec<Comando> {
+        vec![Comando::JoinRoom { sala: sala.to_string() }]
+    }
+
+    fn on_room_message(&mut self, sala: &str, remitente: &str, texto: &str) -> Vec<Comando> {
+        if remitente == self.get_nombre() {
+            return Vec::new();
+        }
+        vec![Comando::RoomMessage { sala: sala.to_string(), texto: texto.to_string() }]
+    }
+}
+
+/// Eventos del arranque, las conexiones y la detención del servidor.
+const CICLO_DE_VIDA: [TipoEvento; 3] =
+    [TipoEvento::ServidorArriba, TipoEvento::NuevoCliente, TipoEvento::ServidorAbajo];
+
+/// Genera, para cada prueba que recibe el núcleo del servidor, una prueba con cada núcleo,
+/// en los módulos `hilos` y `reactor`.
+macro_rules! pruebas_con_nucleos {
+    ($($prueba:ident),*) => {
+        mod hilos {
+            use chat::red::nucleo::Nucleo;
+            $(#[test] fn $prueba() { super::$prueba(Nucleo::Hilos); })*
+        }
+
+        mod reactor {
+            use chat::red::nucleo::Nucleo;
+            $(#[test] fn $prueba() { super::$prueba(Nucleo::Reactor); })*
+        }
+    };
+}
+
+pruebas_con_nucleos!(t1_acepta_conexiones, t2_asignar_estado, t3_obtener_usuarios,
+    t4_manda_mensajes_publicos, t5_manda_mensajes_privados, t6_crea_salas, t7_enviar_invitaciones,
+    t8_aceptar_invitacion, t9_manda_mensaje_sala, t10_identificadores_de_peticion, t11_formato_json,
+    t12_saludo, t13_codificacion_invalida, t14_drenado, t15_rafaga_de_conexiones,
+    t16_drenado_con_varios_clientes, t17_direccion_ipv6, t18_eventos_del_servidor, t19_filtros,
+    t20_bots, t23_cliente_chat, t24_cliente_asincrono, t25_clientes_sin_saludo,
+    t27_cliente_que_no_lee, t28_nombres_simultaneos, t30_muchos_usuarios,
+    t32_propietario_desconectado);
+
+/// Dirección local con un puerto que asigna el sistema operativo, para que las pruebas
+/// puedan ejecutarse en paralelo.
+fn direccion_efimera() -> SocketAddr {
+    "127.0.0.1:0".parse().unwrap()
+}
+
+/// Solicita al servidor las respuestas con código e identificador de petición.
+fn saludar<C: Read + Write>(mut cliente: C) {
+    util::enviar_mensaje(&mut cliente, String::from("HELLO 1 CODES IDS")).unwrap();
+    assert_eq!(obtener_respuesta(&mut cliente).get_codigo(), CodigoRespuesta::OK);
+}
+
+/// Crea un servidor en una dirección efímera que atiende las conexiones con el núcleo recibido.
+fn servidor_con_nucleo(nucleo: Nucleo) -> Servidor {
+    let mut servidor = Servidor::con_direccion(direccion_efimera());
+    servidor.set_nucleo(nucleo);
+    servidor
+}
+
+/// Crea un runtime de tokio de un solo hilo con el temporizador y la red habilitados.
+fn runtime() -> Runtime {
+    Builder::new_current_thread().enable_all().build().expect("Error al crear el runtime")
+}
+
+/// Se conecta al servidor y le envía el saludo.
+fn conectar(direccion: SocketAddr) -> TcpStream {
+    let cliente = TcpStream::connect(direccion).expect("Error al conectar");
+    saludar(&cliente);
+    cliente
+}
+
+/// Lee la siguiente línea enviada por el servidor y la interpreta.
+fn obtener_mensaje_servidor<R: Read>(cliente: R) -> MensajeServidor {
+    let mensaje = util::obtener_mensaje_conexion(cliente).expect("Error al leer del servidor");
+    mensaje.parse::<MensajeServidor>().expect("El mensaje no sigue el protocolo")
+}
+
+/// Lee la siguiente línea enviada por el servidor, que debe ser una respuesta.
+fn obtener_respuesta<R: Read>(cliente: R) -> Respuesta {
+    match obtener_mensaje_servidor(cliente) {
+        MensajeServidor::Respuesta(respuesta) => respuesta,
+        mensaje => panic!("Se esperaba una respuesta: {}", mensaje),
+    }
+}
+
+/// Lee la siguiente línea enviada por el servidor, que debe ser una notificación.
+fn obtener_notificacion<R: Read>(cliente: R) -> Notificacion {
+    match obtener_mensaje_servidor(cliente) {
+        MensajeServidor::Notificacion(notificacion) => notificacion,
+        mensaje => panic!("Se esperaba una notificación: {}", mensaje),
+    }
+}
+
+fn t1_acepta_conexiones(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
     let escucha1 = servidor.nuevo_escucha();
     let escucha2 = servidor.nuevo_escucha();
 
-    let hilo_servidor = thread::Builder::new().name("t1-servidor".into());
     let hilo_cliente1 = thread::Builder::new().name("t1-cliente1".into());
     let hilo_cliente2 = thread::Builder::new().name("t1-cliente2".into());
 
-    hilo_servidor.spawn(move || {
-        servidor.comenzar();
-    }).unwrap();
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
 
-    hilo_cliente1.spawn(move || {
+    let hilo_cliente1 = hilo_cliente1.spawn(move || {
         let evento = escucha1.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "No se especificó el nombre".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::ERR_MISSING_ARGUMENT);
 
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
         thread::sleep(time::Duration::from_secs(2));
     }).unwrap();
 
     thread::sleep(time::Duration::from_secs(1));
 
-    hilo_cliente2.spawn(move || {
+    let hilo_cliente2 = hilo_cliente2.spawn(move || {
         let evento = escucha2.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Ya existe un usuario con ese nombre".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::ERR_NAME_IN_USE);
     }).unwrap();
 
-    thread::sleep(time::Duration::from_secs(3));
+    hilo_cliente1.join().unwrap();
+    hilo_cliente2.join().unwrap();
+    control.detener();
+    control.esperar();
 }
 
-#[test]
-fn t2_asignar_estado() {
-    let puerto = "9091";
-    let mut servidor = Servidor::new(puerto);
+fn t2_asignar_estado(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
     let escucha = servidor.nuevo_escucha();
 
-    let hilo_servidor = thread::Builder::new().name("t2-servidor".into());
     let hilo_cliente = thread::Builder::new().name("t2-cliente".into());
 
-    hilo_servidor.spawn(move || {
-        servidor.comenzar();
-    }).unwrap();
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
 
-    hilo_cliente.spawn(move || {
+    let hilo_cliente = hilo_cliente.spawn(move || {
         let evento = escucha.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         util::enviar_mensaje(&cliente, String::from("STATUS")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "No se especificó el estado".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::ERR_MISSING_ARGUMENT);
 
         util::enviar_mensaje(&cliente, String::from("STATUS ACTIVE")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Estado cambiado a: ACTIVE".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         util::enviar_mensaje(&cliente, String::from("STATUS AWAY")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Estado cambiado a: AWAY".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         util::enviar_mensaje(&cliente, String::from("STATUS BUSY")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Estado cambiado a: BUSY".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
     }).unwrap();
 
-    thread::sleep(time::Duration::from_secs(2));
+    hilo_cliente.join().unwrap();
+    control.detener();
+    control.esperar();
 }
 
-#[test]
-fn t3_obtener_usuarios() {
-    let puerto = "9092";
-    let mut servidor = Servidor::new(puerto);
+fn t3_obtener_usuarios(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
     let escucha1 = servidor.nuevo_escucha();
     let escucha2 = servidor.nuevo_escucha();
     let escucha3 = servidor.nuevo_escucha();
 
-    let hilo_servidor = thread::Builder::new().name("t3-servidor".into());
     let hilo_cliente1 = thread::Builder::new().name("t3-cliente1".into());
     let hilo_cliente2 = thread::Builder::new().name("t3-cliente2".into());
     let hilo_cliente3 = thread::Builder::new().name("t3-cliente3".into());
 
-    hilo_servidor.spawn(move || {
-        servidor.comenzar();
-    }).unwrap();
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
 
-    hilo_cliente1.spawn(move || {
+    let hilo_cliente1 = hilo_cliente1.spawn(move || {
         let evento = escucha1.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
 
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
         thread::sleep(time::Duration::from_secs(3));
     }).unwrap();
 
     thread::sleep(time::Duration::from_secs(1));
 
-    hilo_cliente2.spawn(move || {
+    let hilo_cliente2 = hilo_cliente2.spawn(move || {
         let evento = escucha2.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
 
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente2")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente2".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
         thread::sleep(time::Duration::from_secs(2));
     }).unwrap();
 
     thread::sleep(time::Duration::from_secs(1));
 
-    hilo_cliente3.spawn(move || {
+    let hilo_cliente3 = hilo_cliente3.spawn(move || {
         let evento = escucha3.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
 
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente3")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente3".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         util::enviar_mensaje(&cliente, String::from("USERS")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "cliente1 cliente2 cliente3".to_string()),
-            Err(_) => assert!(false),
-        };
+        let respuesta = obtener_respuesta(&cliente);
+        assert_eq!(respuesta.get_codigo(), CodigoRespuesta::OK);
+        assert_eq!(respuesta.get_texto(), "cliente1 cliente2 cliente3");
     }).unwrap();
 
-    thread::sleep(time::Duration::from_secs(4));
+    hilo_cliente1.join().unwrap();
+    hilo_cliente2.join().unwrap();
+    hilo_cliente3.join().unwrap();
+    control.detener();
+    control.esperar();
 }
 
-#[test]
-fn t4_manda_mensajes_publicos() {
-    let puerto = "9093";
-    let mut servidor = Servidor::new(puerto);
+fn t4_manda_mensajes_publicos(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
     let escucha1 = servidor.nuevo_escucha();
     let escucha2 = servidor.nuevo_escucha();
 
-    let hilo_servidor = thread::Builder::new().name("t4-servidor".into());
     let hilo_cliente1 = thread::Builder::new().name("t4-cliente1".into());
     let hilo_cliente2 = thread::Builder::new().name("t4-cliente2".into());
 
-    hilo_servidor.spawn(move || {
-        servidor.comenzar();
-    }).unwrap();
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
 
-    hilo_cliente1.spawn(move || {
+    let hilo_cliente1 = hilo_cliente1.spawn(move || {
         let evento = escucha1.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+        thread::sleep(time::Duration::from_secs(1));
 
         util::enviar_mensaje(&cliente, String::from("PUBLICMESSAGE mensaje cliente1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Público-cliente1: mensaje cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_notificacion(&cliente), Notificacion::PublicMessage {
+            remitente: "cliente1".to_string(),
+            texto: "mensaje cliente1".to_string(),
+        });
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
     }).unwrap();
 
-    hilo_cliente2.spawn(move || {
+    let hilo_cliente2 = hilo_cliente2.spawn(move || {
         let evento = escucha2.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente2")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente2".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Público-cliente1: mensaje cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_notificacion(&cliente), Notificacion::PublicMessage {
+            remitente: "cliente1".to_string(),
+            texto: "mensaje cliente1".to_string(),
+        });
     }).unwrap();
 
-    thread::sleep(time::Duration::from_secs(2));
+    hilo_cliente1.join().unwrap();
+    hilo_cliente2.join().unwrap();
+    control.detener();
+    control.esperar();
 }
 
-#[test]
-fn t5_manda_mensajes_privados() {
-    let puerto = "9094";
-    let mut servidor = Servidor::new(puerto);
+fn t5_manda_mensajes_privados(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
     let escucha1 = servidor.nuevo_escucha();
     let escucha2 = servidor.nuevo_escucha();
 
-    let hilo_servidor = thread::Builder::new().name("t5-servidor".into());
     let hilo_cliente1 = thread::Builder::new().name("t5-cliente1".into());
     let hilo_cliente2 = thread::Builder::new().name("t5-cliente2".into());
 
-    hilo_servidor.spawn(move || {
-        servidor.comenzar();
-    }).unwrap();
+    let (identificado, espera_identificado) = mpsc::channel::<()>();
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
 
-    hilo_cliente1.spawn(move || {
+    let hilo_cliente1 = hilo_cliente1.spawn(move || {
         let evento = escucha1.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
 
         thread::sleep(time::Duration::from_secs(2));
 
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+        identificado.send(()).unwrap();
 
         thread::sleep(time::Duration::from_secs(2));
 
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "cliente2: Mensaje del cliente2".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_notificacion(&cliente), Notificacion::Message {
+            remitente: "cliente2".to_string(),
+            texto: "Mensaje del cliente2".to_string(),
+        });
     }).unwrap();
 
-    hilo_cliente2.spawn(move || {
+    let hilo_cliente2 = hilo_cliente2.spawn(move || {
         let evento = escucha2.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente2")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente2".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         util::enviar_mensaje(&cliente, String::from("MESSAGE cliente1 Mensaje del cliente2")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "No se encontró al usuario cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::ERR_NO_SUCH_USER);
 
-        thread::sleep(time::Duration::from_secs(2));
+        espera_identificado.recv().unwrap();
 
         util::enviar_mensaje(&cliente, String::from("MESSAGE cliente1 Mensaje del cliente2")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "cliente2: Mensaje del cliente2".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
     }).unwrap();
 
-    thread::sleep(time::Duration::from_secs(5));
+    hilo_cliente1.join().unwrap();
+    hilo_cliente2.join().unwrap();
+    control.detener();
+    control.esperar();
 }
 
-#[test]
-fn t6_crea_salas() {
-    let puerto = "9095";
-    let mut servidor = Servidor::new(puerto);
+fn t6_crea_salas(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
     let escucha = servidor.nuevo_escucha();
 
-    let hilo_servidor = thread::Builder::new().name("t6-servidor".into());
     let hilo_cliente1 = thread::Builder::new().name("t6-cliente".into());
 
-    hilo_servidor.spawn(move || {
-        servidor.comenzar();
-    }).unwrap();
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
 
-    hilo_cliente1.spawn(move || {
+    let hilo_cliente1 = hilo_cliente1.spawn(move || {
         let evento = escucha.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         util::enviar_mensaje(&cliente, String::from("CREATEROOM")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "No se especificó el nombre de la sala".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::ERR_MISSING_ARGUMENT);
 
         util::enviar_mensaje(&cliente, String::from("CREATEROOM S1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Creación de la sala S1 exitosa".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         util::enviar_mensaje(&cliente, String::from("CREATEROOM S1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Ya existe una sala con ese nombre".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::ERR_ROOM_EXISTS);
     }).unwrap();
 
-    thread::sleep(time::Duration::from_secs(2));
+    hilo_cliente1.join().unwrap();
+    control.detener();
+    control.esperar();
 }
 
-#[test]
-fn t7_enviar_invitaciones() {
-    let puerto = "9096";
-    let mut servidor = Servidor::new(puerto);
+fn t7_enviar_invitaciones(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
     let escucha1 = servidor.nuevo_escucha();
     let escucha2 = servidor.nuevo_escucha();
 
-    let hilo_servidor = thread::Builder::new().name("t7-servidor".into());
     let hilo_cliente1 = thread::Builder::new().name("t7-cliente1".into());
     let hilo_cliente2 = thread::Builder::new().name("t7-cliente2".into());
 
-    hilo_servidor.spawn(move || {
-        servidor.comenzar();
-    }).unwrap();
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
 
-    hilo_cliente1.spawn(move || {
+    let hilo_cliente1 = hilo_cliente1.spawn(move || {
         let evento = escucha1.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         util::enviar_mensaje(&cliente, String::from("CREATEROOM S1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Creación de la sala S1 exitosa".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         thread::sleep(time::Duration::from_secs(1));
 
         util::enviar_mensaje(&cliente, String::from("INVITE S1 cliente2")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Invitaciones de la sala S1 enviadas".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
     }).unwrap();
 
-    hilo_cliente2.spawn(move || {
+    let hilo_cliente2 = hilo_cliente2.spawn(move || {
         let evento = escucha2.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente2")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente2".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         thread::sleep(time::Duration::from_secs(1));
 
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Invitación de unirse a la sala S1 por cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_notificacion(&cliente), Notificacion::Invite {
+            sala: "S1".to_string(),
+            anfitrion: "cliente1".to_string(),
+        });
     }).unwrap();
 
-    thread::sleep(time::Duration::from_secs(3));
+    hilo_cliente1.join().unwrap();
+    hilo_cliente2.join().unwrap();
+    control.detener();
+    control.esperar();
 }
 
-#[test]
-fn t8_aceptar_invitacion() {
-    let puerto = "9097";
-    let mut servidor = Servidor::new(puerto);
+fn t8_aceptar_invitacion(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
     let escucha1 = servidor.nuevo_escucha();
     let escucha2 = servidor.nuevo_escucha();
 
-    let hilo_servidor = thread::Builder::new().name("t8-servidor".into());
     let hilo_cliente1 = thread::Builder::new().name("t8-cliente1".into());
     let hilo_cliente2 = thread::Builder::new().name("t8-cliente2".into());
 
-    hilo_servidor.spawn(move || {
-        servidor.comenzar();
-    }).unwrap();
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
 
-    hilo_cliente1.spawn(move || {
+    let hilo_cliente1 = hilo_cliente1.spawn(move || {
         let evento = escucha1.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         util::enviar_mensaje(&cliente, String::from("CREATEROOM S1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Creación de la sala S1 exitosa".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         thread::sleep(time::Duration::from_secs(2));
 
         util::enviar_mensaje(&cliente, String::from("INVITE S1 cliente2")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Invitaciones de la sala S1 enviadas".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         thread::sleep(time::Duration::from_secs(1));
 
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "cliente2 se unió a la sala S1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_notificacion(&cliente), Notificacion::JoinRoom {
+            sala: "S1".to_string(),
+            usuario: "cliente2".to_string(),
+        });
     }).unwrap();
 
-    hilo_cliente2.spawn(move || {
+    let hilo_cliente2 = hilo_cliente2.spawn(move || {
         let evento = escucha2.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente2")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente2".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         thread::sleep(time::Duration::from_secs(1));
 
         util::enviar_mensaje(&cliente, String::from("JOINROOM S1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "No estás invitado para unirte".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::ERR_NOT_INVITED);
 
         thread::sleep(time::Duration::from_secs(2));
 
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Invitación de unirse a la sala S1 por cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_notificacion(&cliente), Notificacion::Invite {
+            sala: "S1".to_string(),
+            anfitrion: "cliente1".to_string(),
+        });
 
         util::enviar_mensaje(&cliente, String::from("JOINROOM S1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "cliente2 se unió a la sala S1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_notificacion(&cliente), Notificacion::JoinRoom {
+            sala: "S1".to_string(),
+            usuario: "cliente2".to_string(),
+        });
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
     }).unwrap();
 
-    thread::sleep(time::Duration::from_secs(4));
+    hilo_cliente1.join().unwrap();
+    hilo_cliente2.join().unwrap();
+    control.detener();
+    control.esperar();
 }
 
-#[test]
-fn t9_manda_mensaje_sala() {
-    let puerto = "9098";
-    let mut servidor = Servidor::new(puerto);
+fn t9_manda_mensaje_sala(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
     let escucha1 = servidor.nuevo_escucha();
     let escucha2 = servidor.nuevo_escucha();
     let escucha3 = servidor.nuevo_escucha();
 
-    let hilo_servidor = thread::Builder::new().name("t9-servidor".into());
     let hilo_cliente1 = thread::Builder::new().name("t9-cliente1".into());
     let hilo_cliente2 = thread::Builder::new().name("t9-cliente2".into());
     let hilo_cliente3 = thread::Builder::new().name("t9-cliente3".into());
 
-    hilo_servidor.spawn(move || {
-        servidor.comenzar();
-    }).unwrap();
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
 
-    hilo_cliente1.spawn(move || {
+    let hilo_cliente1 = hilo_cliente1.spawn(move || {
         let evento = escucha1.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         util::enviar_mensaje(&cliente, String::from("CREATEROOM S1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Creación de la sala S1 exitosa".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         thread::sleep(time::Duration::from_secs(3));
 
         util::enviar_mensaje(&cliente, String::from("INVITE S1 cliente2")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Invitaciones de la sala S1 enviadas".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         thread::sleep(time::Duration::from_secs(1));
 
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "cliente2 se unió a la sala S1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_notificacion(&cliente), Notificacion::JoinRoom {
+            sala: "S1".to_string(),
+            usuario: "cliente2".to_string(),
+        });
 
         util::enviar_mensaje(&cliente, String::from("ROOMESSAGE S1 Mensaje S1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "S1-cliente1: Mensaje S1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_notificacion(&cliente), Notificacion::RoomMessage {
+            sala: "S1".to_string(),
+            remitente: "cliente1".to_string(),
+            texto: "Mensaje S1".to_string(),
+        });
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
     }).unwrap();
 
-    hilo_cliente2.spawn(move || {
+    let hilo_cliente2 = hilo_cliente2.spawn(move || {
         let evento = escucha2.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente2")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente2".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
 
         thread::sleep(time::Duration::from_secs(2));
 
         util::enviar_mensaje(&cliente, String::from("JOINROOM S1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "No estás invitado para unirte".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::ERR_NOT_INVITED);
 
         thread::sleep(time::Duration::from_secs(1));
 
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Invitación de unirse a la sala S1 por cliente1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_notificacion(&cliente), Notificacion::Invite {
+            sala: "S1".to_string(),
+            anfitrion: "cliente1".to_string(),
+        });
 
         util::enviar_mensaje(&cliente, String::from("JOINROOM S1")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "cliente2 se unió a la sala S1".to_string()),
-            Err(_) => assert!(false),
-        };
-
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "S1-cliente1: Mensaje S1".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_notificacion(&cliente), Notificacion::JoinRoom {
+            sala: "S1".to_string(),
+            usuario: "cliente2".to_string(),
+        });
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+
+        assert_eq!(obtener_notificacion(&cliente), Notificacion::RoomMessage {
+            sala: "S1".to_string(),
+            remitente: "cliente1".to_string(),
+            texto: "Mensaje S1".to_string(),
+        });
     }).unwrap();
 
-    hilo_cliente3.spawn(move || {
+    let hilo_cliente3 = hilo_cliente3.spawn(move || {
         let evento = escucha3.recv();
         assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
 
-        let cliente = TcpStream::connect("127.0.0.1:".to_string() + puerto)
-            .expect("Error al conectar");
+        let cliente = conectar(direccion);
         util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente3")).unwrap();
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(mensaje) => assert_eq!(mensaje, "Nombre cambiado a: cliente3".to_string()),
-            Err(_) => assert!(false),
-        };
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+
+        assert!(util::obtener_mensaje_conexion(&cliente).is_err());
+    }).unwrap();
+
+    hilo_cliente1.join().unwrap();
+    hilo_cliente2.join().unwrap();
+    control.detener();
+    control.esperar();
+    hilo_cliente3.join().unwrap();
+}
+
+fn t10_identificadores_de_peticion(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
+    let escucha = servidor.nuevo_escucha();
+
+    let hilo_cliente = thread::Builder::new().name("t10-cliente".into());
+
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+
+    let hilo_cliente = hilo_cliente.spawn(move || {
+        let evento = escucha.recv();
+        assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
+
+        let cliente = conectar(direccion);
+        util::enviar_mensaje(&cliente,
+            String::from("#1 IDENTIFY cliente\n#2 CREATEROOM S1\n#3 CREATEROOM S1\nUSERS")).unwrap();
+
+        let respuesta = obtener_respuesta(&cliente);
+        assert_eq!(respuesta.get_id(), Some(1));
+        assert_eq!(respuesta.get_codigo(), CodigoRespuesta::OK);
+
+        let respuesta = obtener_respuesta(&cliente);
+        assert_eq!(respuesta.get_id(), Some(2));
+        assert_eq!(respuesta.get_codigo(), CodigoRespuesta::OK);
+
+        let respuesta = obtener_respuesta(&cliente);
+        assert_eq!(respuesta.get_id(), Some(3));
+        assert_eq!(respuesta.get_codigo(), CodigoRespuesta::ERR_ROOM_EXISTS);
+
+        let respuesta = obtener_respuesta(&cliente);
+        assert_eq!(respuesta.get_id(), None);
+        assert_eq!(respuesta.get_texto(), "cliente");
+
+        util::enviar_mensaje(&cliente, String::from("#x USERS")).unwrap();
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::ERR_INVALID_ARGUMENT);
+    }).unwrap();
+
+    hilo_cliente.join().unwrap();
+    control.detener();
+    control.esperar();
+}
+
+fn t11_formato_json(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
+    let escucha = servidor.nuevo_escucha();
+
+    let hilo_cliente = thread::Builder::new().name("t11-cliente".into());
+
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
 
-        match util::obtener_mensaje_conexion(&cliente) {
-            Ok(_) => assert!(false),
-            Err(_) => assert!(true),
+    let hilo_cliente = hilo_cliente.spawn(move || {
+        let evento = escucha.recv();
+        assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
+
+        let cliente_json = conectar(direccion);
+        let cliente_texto = conectar(direccion);
+        let obtener_json = |cliente: &TcpStream| {
+            let mensaje = util::obtener_mensaje_conexion(cliente).expect("Error al leer del servidor");
+            Formato::JSON.analizar_mensaje(&mensaje).expect("El mensaje no sigue el protocolo")
         };
+
+        util::enviar_mensaje(&cliente_json, String::from("PROTOCOL JSON")).unwrap();
+        match obtener_json(&cliente_json) {
+            MensajeServidor::Respuesta(respuesta) =>
+                assert_eq!(respuesta.get_codigo(), CodigoRespuesta::OK),
+            mensaje => panic!("Se esperaba una respuesta: {}", mensaje),
+        }
+
+        util::enviar_mensaje(&cliente_json,
+            String::from(r#"{"id":1,"comando":"IDENTIFY","nombre":"json"}"#)).unwrap();
+        match obtener_json(&cliente_json) {
+            MensajeServidor::Respuesta(respuesta) => {
+                assert_eq!(respuesta.get_id(), Some(1));
+                assert_eq!(respuesta.get_codigo(), CodigoRespuesta::OK);
+            },
+            mensaje => panic!("Se esperaba una respuesta: {}", mensaje),
+        }
+
+        util::enviar_mensaje(&cliente_json, String::from(r#"{"comando":"MESSAGE"}"#)).unwrap();
+        match obtener_json(&cliente_json) {
+            MensajeServidor::Respuesta(respuesta) =>
+                assert_eq!(respuesta.get_codigo(), CodigoRespuesta::ERR_INVALID_ARGUMENT),
+            mensaje => panic!("Se esperaba una respuesta: {}", mensaje),
+        }
+
+        let cliente_sin_saludo = TcpStream::connect(direccion).expect("Error al conectar");
+        util::enviar_mensaje(&cliente_sin_saludo, String::from("PROTOCOL JSON")).unwrap();
+        match obtener_json(&cliente_sin_saludo) {
+            MensajeServidor::Respuesta(respuesta) =>
+                assert_eq!(respuesta.get_codigo(), CodigoRespuesta::OK),
+            mensaje => panic!("Se esperaba una respuesta: {}", mensaje),
+        }
+        util::enviar_mensaje(&cliente_sin_saludo,
+            String::from(r#"{"id":7,"comando":"CREATEROOM","sala":"S1"}"#)).unwrap();
+        match obtener_json(&cliente_sin_saludo) {
+            MensajeServidor::Respuesta(respuesta) => {
+                assert_eq!(respuesta.get_id(), Some(7));
+                assert_eq!(respuesta.get_codigo(), CodigoRespuesta::OK);
+            },
+            mensaje => panic!("Se esperaba una respuesta: {}", mensaje),
+        }
+
+        util::enviar_mensaje(&cliente_texto, String::from("IDENTIFY texto")).unwrap();
+        assert_eq!(obtener_respuesta(&cliente_texto).get_codigo(), CodigoRespuesta::OK);
+        util::enviar_mensaje(&cliente_texto, String::from("PROTOCOL JSON")).unwrap();
+        assert_eq!(obtener_respuesta(&cliente_texto).get_codigo(),
+            CodigoRespuesta::ERR_ALREADY_IDENTIFIED);
+
+        util::enviar_mensaje(&cliente_texto, String::from("MESSAGE json hola")).unwrap();
+        assert_eq!(obtener_respuesta(&cliente_texto).get_codigo(), CodigoRespuesta::OK);
+        assert_eq!(obtener_json(&cliente_json), MensajeServidor::Notificacion(Notificacion::Message {
+            remitente: "texto".to_string(),
+            texto: "hola".to_string(),
+        }));
+    }).unwrap();
+
+    hilo_cliente.join().unwrap();
+    control.detener();
+    control.esperar();
+}
+
+fn t12_saludo(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
+    let escucha = servidor.nuevo_escucha();
+
+    let hilo_cliente = thread::Builder::new().name("t12-cliente".into());
+
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+
+    let hilo_cliente = hilo_cliente.spawn(move || {
+        let evento = escucha.recv();
+        assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
+
+        let cliente = TcpStream::connect(direccion)
+            .expect("Error al conectar");
+        util::enviar_mensaje(&cliente, String::from("HELLO 3 CODES HISTORY TLS")).unwrap();
+        let respuesta = obtener_respuesta(&cliente);
+        assert_eq!(respuesta.get_codigo(), CodigoRespuesta::OK);
+        assert_eq!(respuesta.get_texto(), "1 CODES");
+
+        util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente")).unwrap();
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+        util::enviar_mensaje(&cliente, String::from("HELLO 1 JSON")).unwrap();
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::ERR_ALREADY_IDENTIFIED);
+    }).unwrap();
+
+    hilo_cliente.join().unwrap();
+    control.detener();
+    control.esperar();
+}
+
+fn t13_codificacion_invalida(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
+    let escucha = servidor.nuevo_escucha();
+
+    let hilo_cliente = thread::Builder::new().name("t13-cliente".into());
+
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+
+    let hilo_cliente = hilo_cliente.spawn(move || {
+        let evento = escucha.recv();
+        assert_eq!(evento, Ok(EventoServidor::ServidorArriba));
+
+        let mut cliente = conectar(direccion);
+        cliente.write_all(b"IDENTIFY \xFF\xFE\n").unwrap();
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::ERR_INVALID_ENCODING);
+
+        util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente")).unwrap();
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+
+        util::enviar_mensaje(&cliente, String::from("PUBLICMESSAGE :hola\rREPLY * OK")).unwrap();
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::ERR_INVALID_ARGUMENT);
     }).unwrap();
 
-    thread::sleep(time::Duration::from_secs(5));
+    hilo_cliente.join().unwrap();
+    control.detener();
+    control.esperar();
+}
+
+fn t14_drenado(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
+    let escucha = servidor.nuevo_escucha_de(&CICLO_DE_VIDA);
+
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+    assert_eq!(escucha.recv(), Ok(EventoServidor::ServidorArriba));
+
+    let cliente = conectar(direccion);
+    assert_eq!(escucha.recv(), Ok(EventoServidor::NuevoCliente));
+    util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente")).unwrap();
+    assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+
+    let inicio = time::Instant::now();
+    control.clone().drenar(time::Duration::from_secs(10));
+    match obtener_notificacion(&cliente) {
+        Notificacion::Shutdown { .. } => {},
+        notificacion => panic!("Se esperaba el aviso de drenado: {}", notificacion),
+    }
+    assert!(!control.esta_terminado());
+    util::enviar_mensaje(&cliente, String::from("DISCONNECT")).unwrap();
+
+    control.esperar();
+    assert!(inicio.elapsed() < time::Duration::from_secs(10));
+    assert_eq!(escucha.recv(), Ok(EventoServidor::ServidorAbajo));
+    assert!(TcpStream::connect(direccion).is_err());
+}
+
+fn t15_rafaga_de_conexiones(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
+    let escucha = servidor.nuevo_escucha_de(&CICLO_DE_VIDA);
+
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+    assert_eq!(escucha.recv(), Ok(EventoServidor::ServidorArriba));
+
+    let inicio = time::Instant::now();
+    let clientes: Vec<TcpStream> = (0..200).map(|_| {
+        conectar(direccion)
+    }).collect();
+    for _ in 0..clientes.len() {
+        assert_eq!(escucha.recv_timeout(time::Duration::from_secs(5)), Ok(EventoServidor::NuevoCliente));
+    }
+    assert!(inicio.elapsed() < time::Duration::from_secs(5));
+
+    let ultimo = clientes.last().unwrap();
+    util::enviar_mensaje(ultimo, String::from("IDENTIFY cliente")).unwrap();
+    assert_eq!(obtener_respuesta(ultimo).get_codigo(), CodigoRespuesta::OK);
+
+    control.detener();
+    control.esperar();
+    assert_eq!(escucha.recv(), Ok(EventoServidor::ServidorAbajo));
+}
+
+fn t16_drenado_con_varios_clientes(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
+    let escucha = servidor.nuevo_escucha_de(&CICLO_DE_VIDA);
+
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+    assert_eq!(escucha.recv(), Ok(EventoServidor::ServidorArriba));
+
+    let clientes: Vec<TcpStream> = (0..2).map(|_| {
+        conectar(direccion)
+    }).collect();
+    for (i, cliente) in clientes.iter().enumerate() {
+        assert_eq!(escucha.recv(), Ok(EventoServidor::NuevoCliente));
+        util::enviar_mensaje(cliente, format!("IDENTIFY cliente{}", i)).unwrap();
+        assert_eq!(obtener_respuesta(cliente).get_codigo(), CodigoRespuesta::OK);
+    }
+
+    util::enviar_mensaje(&clientes[0], String::from("#1 PUBLICMESSAGE hola\n#2 USERS")).unwrap();
+    let publico = Notificacion::PublicMessage {
+        remitente: "cliente0".to_string(),
+        texto: "hola".to_string(),
+    };
+    assert_eq!(obtener_notificacion(&clientes[0]), publico);
+    assert_eq!(obtener_respuesta(&clientes[0]).get_id(), Some(1));
+    assert_eq!(obtener_respuesta(&clientes[0]).get_texto(), "cliente0 cliente1");
+    assert_eq!(obtener_notificacion(&clientes[1]), publico);
+
+    control.drenar(time::Duration::from_secs(10));
+    for cliente in clientes.iter() {
+        match obtener_notificacion(cliente) {
+            Notificacion::Shutdown { .. } => {},
+            notificacion => panic!("Se esperaba el aviso de drenado: {}", notificacion),
+        }
+        util::enviar_mensaje(cliente, String::from("DISCONNECT")).unwrap();
+    }
+    control.esperar();
+    assert_eq!(escucha.recv(), Ok(EventoServidor::ServidorAbajo));
+}
+
+fn t17_direccion_ipv6(nucleo: Nucleo) {
+    let mut servidor = Servidor::con_direccion("[::1]:0".parse().unwrap());
+    servidor.set_nucleo(nucleo);
+    let control = match servidor.iniciar() {
+        Ok(control) => control,
+        // El sistema no tiene IPv6 disponible.
+        Err(_) => return,
+    };
+    let direccion = control.get_direccion_local().unwrap();
+    assert!(direccion.is_ipv6());
+    assert_ne!(direccion.port(), 0);
+
+    let cliente = conectar(direccion);
+    util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente")).unwrap();
+    assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+
+    control.detener();
+    control.esperar();
+}
+
+fn t18_eventos_del_servidor(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
+    let escucha = servidor.nuevo_escucha();
+    let escucha_mensajes = servidor.nuevo_escucha_de(&[TipoEvento::MensajePublico,
+        TipoEvento::MensajeSala]);
+
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+    assert_eq!(escucha.recv(), Ok(EventoServidor::ServidorArriba));
+
+    let cliente = conectar(direccion);
+    assert_eq!(escucha.recv(), Ok(EventoServidor::NuevoCliente));
+    // Identificarse de nuevo con el mismo nombre no es un cambio de nombre.
+    let comandos = ["IDENTIFY ana", "IDENTIFY ana", "STATUS AWAY", "PUBLICMESSAGE hola", "MESSAGE ana privado",
+        "CREATEROOM sala", "ROOMESSAGE sala en la sala", "DISCONNECT"];
+    for comando in comandos.iter() {
+        util::enviar_mensaje(&cliente, comando.to_string()).unwrap();
+    }
+
+    let esperados = [EventoServidor::ClienteIdentificado { id: 0, nombre: "ana".to_string() },
+        EventoServidor::EstadoCambiado { nombre: "ana".to_string(), estado: EstadoCliente::AWAY },
+        EventoServidor::MensajePublico { remitente: "ana".to_string(), texto: "hola".to_string() },
+        EventoServidor::MensajePrivado {
+            remitente: "ana".to_string(),
+            destinatario: "ana".to_string(),
+            texto: "privado".to_string(),
+        },
+        EventoServidor::SalaCreada { sala: "sala".to_string(), propietario: 0 },
+        EventoServidor::MensajeSala {
+            sala: "sala".to_string(),
+            remitente: "ana".to_string(),
+            texto: "en la sala".to_string(),
+        },
+        EventoServidor::ClienteDesconectado { id: 0, nombre: Some("ana".to_string()) }];
+    for esperado in esperados.iter() {
+        assert_eq!(escucha.recv_timeout(time::Duration::from_secs(5)).as_ref(), Ok(esperado));
+    }
+
+    control.detener();
+    control.esperar();
+    assert_eq!(escucha.recv(), Ok(EventoServidor::ServidorAbajo));
+    assert_eq!(escucha_mensajes.recv(), Ok(esperados[2].clone()));
+    assert_eq!(escucha_mensajes.recv(), Ok(esperados[5].clone()));
+    assert!(escucha_mensajes.recv().is_err());
+}
+
+fn t19_filtros(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
+    let copia = servidor.get_filtros().clone();
+    match servidor.agregar_filtro(FiltroPrueba) {
+        Err(ref error) if error.kind() == ErrorKind::InvalidInput => {},
+        otro => panic!("Se esperaba un error por la copia de los filtros: {:?}", otro),
+    }
+    drop(copia);
+    servidor.agregar_filtro(FiltroPrueba).expect("Error al agregar el filtro");
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+
+    let cliente = conectar(direccion);
+    util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente")).unwrap();
+    assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+
+    util::enviar_mensaje(&cliente, String::from("#1 PUBLICMESSAGE visiten http://ejemplo.com")).unwrap();
+    let respuesta = obtener_respuesta(&cliente);
+    assert_eq!(respuesta.get_id(), Some(1));
+    assert_eq!(respuesta.get_codigo(), CodigoRespuesta::ERR_REJECTED);
+    assert_eq!(respuesta.get_texto(), "No se permiten enlaces");
+
+    util::enviar_mensaje(&cliente, String::from("#2 PUBLICMESSAGE no seas tonto")).unwrap();
+    assert_eq!(obtener_notificacion(&cliente), Notificacion::PublicMessage {
+        remitente: "cliente".to_string(),
+        texto: "no seas *****".to_string(),
+    });
+    assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+
+    control.detener();
+    control.esperar();
+}
+
+fn t20_bots(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
+    servidor.agregar_bot(BotEco);
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+
+    let cliente = conectar(direccion);
+    util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente")).unwrap();
+    assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+    util::enviar_mensaje(&cliente, String::from("USERS")).unwrap();
+    assert_eq!(obtener_respuesta(&cliente).get_texto(), "eco cliente");
+
+    util::enviar_mensaje(&cliente, String::from("MESSAGE eco hola")).unwrap();
+    assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+    assert_eq!(obtener_notificacion(&cliente), Notificacion::Message {
+        remitente: "eco".to_string(),
+        texto: "hola".to_string(),
+    });
+
+    util::enviar_mensaje(&cliente, String::from("CREATEROOM sala")).unwrap();
+    assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+    util::enviar_mensaje(&cliente, String::from("INVITE sala eco")).unwrap();
+    assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+    assert_eq!(obtener_notificacion(&cliente), Notificacion::JoinRoom {
+        sala: "sala".to_string(),
+        usuario: "eco".to_string(),
+    });
+
+    util::enviar_mensaje(&cliente, String::from("ROOMESSAGE sala hola sala")).unwrap();
+    let recibido = Notificacion::RoomMessage {
+        sala: "sala".to_string(),
+        remitente: "cliente".to_string(),
+        texto: "hola sala".to_string(),
+    };
+    assert_eq!(obtener_notificacion(&cliente), recibido);
+    assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+    assert_eq!(obtener_notificacion(&cliente), Notificacion::RoomMessage {
+        sala: "sala".to_string(),
+        remitente: "eco".to_string(),
+        texto: "hola sala".to_string(),
+    });
+
+    util::enviar_mensaje(&cliente, String::from("MESSAGE eco adios")).unwrap();
+    assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+    util::enviar_mensaje(&cliente, String::from("USERS")).unwrap();
+    assert_eq!(obtener_respuesta(&cliente).get_texto(), "cliente");
+
+    control.detener();
+    control.esperar();
+}
+
+#[test]
+fn t21_conexiones_en_memoria() {
+    let aceptador = AceptadorMemoria::new();
+    let conector = aceptador.get_conector();
+    let mut servidor = Servidor::con_aceptador(aceptador);
+    let escucha = servidor.nuevo_escucha_de(&CICLO_DE_VIDA);
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    assert_eq!(escucha.recv(), Ok(EventoServidor::ServidorArriba));
+    assert_eq!(control.get_direccion_local(), None);
+
+    let mut cliente1 = conector.conectar().expect("Error al conectar");
+    let mut cliente2 = conector.conectar().expect("Error al conectar");
+    saludar(&mut cliente1);
+    saludar(&mut cliente2);
+    util::enviar_mensaje(&mut cliente1, String::from("IDENTIFY uno")).unwrap();
+    assert_eq!(obtener_respuesta(&mut cliente1).get_codigo(), CodigoRespuesta::OK);
+    util::enviar_mensaje(&mut cliente2, String::from("IDENTIFY dos")).unwrap();
+    assert_eq!(obtener_respuesta(&mut cliente2).get_codigo(), CodigoRespuesta::OK);
+
+    util::enviar_mensaje(&mut cliente1, String::from("MESSAGE dos hola")).unwrap();
+    assert_eq!(obtener_respuesta(&mut cliente1).get_codigo(), CodigoRespuesta::OK);
+    assert_eq!(obtener_notificacion(&mut cliente2), Notificacion::Message {
+        remitente: "uno".to_string(),
+        texto: "hola".to_string(),
+    });
+
+    control.detener();
+    control.esperar();
+    assert_eq!(escucha.recv(), Ok(EventoServidor::NuevoCliente));
+    assert_eq!(escucha.recv(), Ok(EventoServidor::NuevoCliente));
+    assert_eq!(escucha.recv(), Ok(EventoServidor::ServidorAbajo));
+    let mut buffer = [0; 8];
+    assert_eq!(cliente1.read(&mut buffer).unwrap(), 0);
+    assert!(conector.conectar().is_err());
+
+    let mut servidor = Servidor::con_aceptador(AceptadorMemoria::new());
+    servidor.set_nucleo(Nucleo::Reactor);
+    assert!(servidor.iniciar().is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn t22_sockets_unix() {
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let ruta = std::env::temp_dir().join(format!("chat-t22-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&ruta);
+    let escucha_unix = UnixListener::bind(&ruta).expect("Error al enlazar el socket");
+    let control = Servidor::con_aceptador(escucha_unix).iniciar()
+        .expect("Error al iniciar el servidor");
+
+    let cliente = UnixStream::connect(&ruta).expect("Error al conectar");
+    saludar(&cliente);
+    util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente")).unwrap();
+    assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+    util::enviar_mensaje(&cliente, String::from("USERS")).unwrap();
+    assert_eq!(obtener_respuesta(&cliente).get_texto(), "cliente");
+
+    control.detener();
+    control.esperar();
+    let _ = std::fs::remove_file(&ruta);
+}
+
+fn t23_cliente_chat(nucleo: Nucleo) {
+    let control = servidor_con_nucleo(nucleo).iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+
+    let conectar = || ClienteChat::conectar(direccion).unwrap();
+    let uno = conectar();
+    let dos = conectar();
+    uno.identificar("uno").unwrap();
+    dos.identificar("dos").unwrap();
+    assert_eq!(uno.usuarios().unwrap(), vec!["uno".to_string(), "dos".to_string()]);
+    uno.estado(EstadoCliente::AWAY).unwrap();
+
+    uno.mensaje_privado("dos", "hola").unwrap();
+    let mut eventos = dos.eventos();
+    assert_eq!(eventos.next(), Some(Notificacion::Message {
+        remitente: "uno".to_string(),
+        texto: "hola".to_string(),
+    }));
+
+    uno.crear_sala("S1").unwrap();
+    uno.invitar("S1", &["dos"]).unwrap();
+    assert_eq!(eventos.next(), Some(Notificacion::Invite {
+        sala: "S1".to_string(),
+        anfitrion: "uno".to_string(),
+    }));
+    dos.unirse("S1").unwrap();
+    assert_eq!(eventos.next(), Some(Notificacion::JoinRoom {
+        sala: "S1".to_string(),
+        usuario: "dos".to_string(),
+    }));
+    uno.mensaje_sala("S1", "bienvenido").unwrap();
+    assert_eq!(eventos.next(), Some(Notificacion::RoomMessage {
+        sala: "S1".to_string(),
+        remitente: "uno".to_string(),
+        texto: "bienvenido".to_string(),
+    }));
+
+    match dos.mensaje_sala("S2", "hola") {
+        Err(ErrorCliente::Rechazo(respuesta)) =>
+            assert_eq!(respuesta.get_codigo(), CodigoRespuesta::ERR_NO_SUCH_ROOM),
+        otro => panic!("Se esperaba un rechazo: {:?}", otro),
+    }
+
+    uno.desconectar().unwrap();
+    match uno.usuarios() {
+        Err(ErrorCliente::Desconectado) | Err(ErrorCliente::Transporte(_)) => {},
+        otro => panic!("Se esperaba un error de conexión: {:?}", otro),
+    }
+
+    control.detener();
+    control.esperar();
+    assert_eq!(eventos.next(), None);
+}
+
+fn t24_cliente_asincrono(nucleo: Nucleo) {
+    let servidor = servidor_con_nucleo(nucleo);
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+
+    runtime().block_on(async move {
+        let (uno, _) = ClienteAsincrono::conectar(direccion).await.unwrap();
+        let (dos, mut eventos) = ClienteAsincrono::conectar(direccion).await.unwrap();
+        let (identificado_uno, identificado_dos) =
+            futures::join!(uno.identificar("uno"), dos.identificar("dos"));
+        identificado_uno.unwrap();
+        identificado_dos.unwrap();
+
+        let (privado, publico, usuarios) = futures::join!(uno.mensaje_privado("dos", "hola"),
+            uno.mensaje_publico("a todos"), uno.usuarios());
+        privado.unwrap();
+        publico.unwrap();
+        assert_eq!(usuarios.unwrap(), vec!["uno".to_string(), "dos".to_string()]);
+        let recibidas: Vec<Notificacion> = eventos.by_ref().take(2).collect().await;
+        assert_eq!(recibidas, vec![
+            Notificacion::Message { remitente: "uno".to_string(), texto: "hola".to_string() },
+            Notificacion::PublicMessage { remitente: "uno".to_string(), texto: "a todos".to_string() },
+        ]);
+
+        // Los comandos pueden esperarse desde otras tareas del runtime.
+        let copia = dos.clone();
+        let union = tokio::spawn(async move { copia.unirse("S1").await });
+        match union.await.unwrap() {
+            Err(ErrorCliente::Rechazo(respuesta)) =>
+                assert_eq!(respuesta.get_codigo(), CodigoRespuesta::ERR_NO_SUCH_ROOM),
+            otro => panic!("Se esperaba un rechazo: {:?}", otro),
+        }
+
+        dos.desconectar().unwrap();
+        match dos.usuarios().await {
+            Err(ErrorCliente::Desconectado) => {},
+            otro => panic!("Se esperaba un error de conexión: {:?}", otro),
+        }
+        assert_eq!(eventos.next().await, None);
+    });
+
+    control.detener();
+    control.esperar();
+}
+
+fn t25_clientes_sin_saludo(nucleo: Nucleo) {
+    let servidor = servidor_con_nucleo(nucleo);
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+    let obtener_linea = |cliente: &TcpStream| {
+        util::obtener_mensaje_conexion(cliente).expect("Error al leer del servidor")
+    };
+
+    let cliente = TcpStream::connect(direccion).expect("Error al conectar");
+    util::enviar_mensaje(&cliente, String::from("USERS")).unwrap();
+    assert_eq!(obtener_linea(&cliente), "OK");
+    util::enviar_mensaje(&cliente, String::from("IDENTIFY cliente")).unwrap();
+    assert_eq!(obtener_linea(&cliente), "Nombre cambiado a: cliente");
+    util::enviar_mensaje(&cliente, String::from("#2 USERS")).unwrap();
+    assert_eq!(obtener_linea(&cliente), "cliente");
+    util::enviar_mensaje(&cliente, String::from("JOINROOM S1")).unwrap();
+    assert_eq!(obtener_linea(&cliente), ChatError::SalaInexistente("S1".to_string()).to_string());
+
+    let con_ids = TcpStream::connect(direccion).expect("Error al conectar");
+    util::enviar_mensaje(&con_ids, String::from("HELLO 1 IDS")).unwrap();
+    assert_eq!(obtener_linea(&con_ids), "1 IDS");
+    util::enviar_mensaje(&con_ids, String::from("#4 USERS")).unwrap();
+    assert_eq!(obtener_linea(&con_ids), "#4 cliente");
+
+    util::enviar_mensaje(&con_ids, String::from("#5 IDENTIFY bob")).unwrap();
+    assert_eq!(obtener_linea(&con_ids), "#5 Nombre cambiado a: bob");
+    util::enviar_mensaje(&con_ids, String::from("#6 MESSAGE cliente hola")).unwrap();
+    obtener_linea(&con_ids);
+    assert_eq!(obtener_linea(&cliente), "bob: hola");
+    util::enviar_mensaje(&con_ids, String::from("#7 PUBLICMESSAGE hola a todos")).unwrap();
+    obtener_linea(&con_ids);
+    assert_eq!(obtener_linea(&cliente), "Público-bob: hola a todos");
+    util::enviar_mensaje(&con_ids, String::from(r#"#8 MESSAGE cliente C:\tmp "sin cerrar"#)).unwrap();
+    obtener_linea(&con_ids);
+    assert_eq!(obtener_linea(&cliente), r#"bob: C:\tmp "sin cerrar"#);
+
+    control.detener();
+    control.esperar();
+}
+
+#[test]
+fn t26_cliente_asincrono_sin_respuesta() {
+    let servidor = TcpListener::bind("127.0.0.1:0").expect("Error al crear el socket");
+    let direccion = servidor.local_addr().unwrap();
+    let falso = thread::spawn(move || {
+        let (callado, _) = servidor.accept().unwrap();
+        util::obtener_mensaje_conexion(&callado).unwrap();
+        let (mut excesivo, _) = servidor.accept().unwrap();
+        util::obtener_mensaje_conexion(&excesivo).unwrap();
+        util::obtener_mensaje_conexion(&excesivo).unwrap();
+        excesivo.write_all(&vec![b'a'; TAMANO_MAXIMO_MENSAJE_SERVIDOR + 1]).unwrap();
+        excesivo.write_all(b"\n").unwrap();
+        thread::sleep(time::Duration::from_millis(500));
+        drop(callado);
+    });
+
+    runtime().block_on(async move {
+        let (mut callado, _) = ClienteAsincrono::conectar(direccion).await.unwrap();
+        callado.set_plazo_respuesta(time::Duration::from_millis(200));
+        match callado.usuarios().await {
+            Err(ErrorCliente::Transporte(ref error)) if error.kind() == ErrorKind::TimedOut => {},
+            otro => panic!("Se esperaba que se agotara el plazo: {:?}", otro),
+        }
+
+        let (excesivo, _) = ClienteAsincrono::conectar(direccion).await.unwrap();
+        match excesivo.usuarios().await {
+            Err(ErrorCliente::Transporte(ref error)) if error.kind() == ErrorKind::InvalidData => {},
+            otro => panic!("Se esperaba un error por el tamaño del mensaje: {:?}", otro),
+        }
+    });
+
+    falso.join().unwrap();
+}
+
+fn t27_cliente_que_no_lee(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
+    servidor.set_capacidad_cola(100_000);
+    servidor.set_plazo_saturacion(time::Duration::from_secs(1));
+    let escucha = servidor.nuevo_escucha_de(&[TipoEvento::ClienteDesconectado]);
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+
+    let lento = conectar(direccion);
+    util::enviar_mensaje(&lento, String::from("IDENTIFY lento")).unwrap();
+    assert_eq!(obtener_respuesta(&lento).get_codigo(), CodigoRespuesta::OK);
+    let emisor = conectar(direccion);
+    util::enviar_mensaje(&emisor, String::from("IDENTIFY emisor")).unwrap();
+    assert_eq!(obtener_respuesta(&emisor).get_codigo(), CodigoRespuesta::OK);
+
+    // Las respuestas del emisor sólo se descartan, para que su propia conexión no se sature.
+    let mut respuestas = emisor.try_clone().unwrap();
+    let hilo_respuestas = thread::spawn(move || io::copy(&mut respuestas, &mut io::sink()));
+    // Suficientes datos para llenar los buffers del socket de quien no lee, pero no su cola.
+    let mensaje = format!("MESSAGE lento {}", "a".repeat(4000));
+    for _ in 0..1500 {
+        util::enviar_mensaje(&emisor, mensaje.clone()).unwrap();
+    }
+
+    match escucha.recv_timeout(time::Duration::from_secs(10)) {
+        Ok(EventoServidor::ClienteDesconectado { nombre: Some(ref nombre), .. }) if nombre == "lento" => {},
+        otro => panic!("Se esperaba la desconexión del cliente que no lee: {:?}", otro),
+    }
+
+    control.detener();
+    control.esperar();
+    assert!(hilo_respuestas.join().unwrap().is_ok());
+    drop(lento);
+}
+
+fn t28_nombres_simultaneos(nucleo: Nucleo) {
+    let servidor = servidor_con_nucleo(nucleo);
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+
+    let clientes = 16;
+    let barrera = Arc::new(Barrier::new(clientes));
+    let hilos: Vec<_> = (0..clientes).map(|_| {
+        let barrera = barrera.clone();
+        thread::spawn(move || {
+            let cliente = conectar(direccion);
+            barrera.wait();
+            util::enviar_mensaje(&cliente, String::from("IDENTIFY repetido")).unwrap();
+            let codigo = obtener_respuesta(&cliente).get_codigo();
+            // Las conexiones siguen abiertas hasta tener todas las respuestas, para que
+            // el nombre no se libere.
+            barrera.wait();
+            codigo
+        })
+    }).collect();
+    let codigos: Vec<CodigoRespuesta> = hilos.into_iter().map(|hilo| hilo.join().unwrap()).collect();
+
+    assert_eq!(codigos.iter().filter(|codigo| codigo.es_exito()).count(), 1);
+    assert!(codigos.iter().filter(|codigo| !codigo.es_exito())
+        .all(|codigo| *codigo == CodigoRespuesta::ERR_NAME_IN_USE));
+
+    control.detener();
+    control.esperar();
+}
+
+#[test]
+fn t29_cliente_en_memoria_que_no_lee() {
+    let aceptador = AceptadorMemoria::new();
+    let conector = aceptador.get_conector();
+    let mut servidor = Servidor::con_aceptador(aceptador);
+    servidor.set_capacidad_cola(100_000);
+    servidor.set_plazo_saturacion(time::Duration::from_secs(1));
+    let escucha = servidor.nuevo_escucha_de(&[TipoEvento::ClienteDesconectado]);
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+
+    let mut lento = conector.conectar().expect("Error al conectar");
+    saludar(&mut lento);
+    util::enviar_mensaje(&mut lento, String::from("IDENTIFY lento")).unwrap();
+    assert_eq!(obtener_respuesta(&mut lento).get_codigo(), CodigoRespuesta::OK);
+    let mut emisor = conector.conectar().expect("Error al conectar");
+    saludar(&mut emisor);
+    util::enviar_mensaje(&mut emisor, String::from("IDENTIFY emisor")).unwrap();
+    assert_eq!(obtener_respuesta(&mut emisor).get_codigo(), CodigoRespuesta::OK);
+
+    let mut respuestas = emisor.duplicar().unwrap();
+    let hilo_respuestas = thread::spawn(move || io::copy(&mut respuestas, &mut io::sink()));
+    // Suficientes datos para llenar la tubería de quien no lee, pero no su cola.
+    let mensaje = format!("MESSAGE lento {}", "a".repeat(4000));
+    for _ in 0..100 {
+        util::enviar_mensaje(&mut emisor, mensaje.clone()).unwrap();
+    }
+
+    match escucha.recv_timeout(time::Duration::from_secs(10)) {
+        Ok(EventoServidor::ClienteDesconectado { nombre: Some(ref nombre), .. }) if nombre == "lento" => {},
+        otro => panic!("Se esperaba la desconexión del cliente que no lee: {:?}", otro),
+    }
+
+    control.detener();
+    control.esperar();
+    assert!(hilo_respuestas.join().unwrap().is_ok());
+    drop(lento);
+}
+
+fn t30_muchos_usuarios(nucleo: Nucleo) {
+    let control = servidor_con_nucleo(nucleo).iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+
+    // Suficientes usuarios para que la respuesta a USERS exceda el tamaño máximo de los
+    // mensajes de los clientes.
+    let nombres: Vec<String> = (0..500).map(|i| format!("carga{}", i)).collect();
+    let conexiones: Vec<TcpStream> = nombres.iter().map(|nombre| {
+        let cliente = conectar(direccion);
+        util::enviar_mensaje(&cliente, format!("IDENTIFY {}", nombre)).unwrap();
+        assert_eq!(obtener_respuesta(&cliente).get_codigo(), CodigoRespuesta::OK);
+        cliente
+    }).collect();
+
+    let cliente = ClienteChat::conectar(direccion).unwrap();
+    let mut usuarios = cliente.usuarios().unwrap();
+    usuarios.sort();
+    let mut esperados = nombres.clone();
+    esperados.sort();
+    assert_eq!(usuarios, esperados);
+
+    control.detener();
+    control.esperar();
+    drop(conexiones);
+}
+
+#[test]
+fn t31_cliente_chat_con_mensaje_excesivo() {
+    let servidor = TcpListener::bind("127.0.0.1:0").expect("Error al crear el socket");
+    let direccion = servidor.local_addr().unwrap();
+    let falso = thread::spawn(move || {
+        let (mut excesivo, _) = servidor.accept().unwrap();
+        util::obtener_mensaje_conexion(&excesivo).unwrap();
+        util::obtener_mensaje_conexion(&excesivo).unwrap();
+        excesivo.write_all(&vec![b'a'; TAMANO_MAXIMO_MENSAJE_SERVIDOR + 1]).unwrap();
+        excesivo.write_all(b"\n").unwrap();
+        thread::sleep(time::Duration::from_millis(500));
+    });
+
+    let cliente = ClienteChat::conectar(direccion).unwrap();
+    let inicio = time::Instant::now();
+    match cliente.usuarios() {
+        Err(ErrorCliente::Transporte(ref error)) if error.kind() == ErrorKind::InvalidData => {},
+        otro => panic!("Se esperaba un error por el tamaño del mensaje: {:?}", otro),
+    }
+    assert!(inicio.elapsed() < cliente.get_plazo_respuesta());
+
+    falso.join().unwrap();
+}
+
+fn t32_propietario_desconectado(nucleo: Nucleo) {
+    let mut servidor = servidor_con_nucleo(nucleo);
+    let escucha = servidor.nuevo_escucha_de(&[TipoEvento::ClienteDesconectado]);
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+
+    let conectar = || ClienteChat::conectar(direccion).unwrap();
+    let (uno, dos, tres) = (conectar(), conectar(), conectar());
+    uno.identificar("uno").unwrap();
+    dos.identificar("dos").unwrap();
+    tres.identificar("tres").unwrap();
+    uno.crear_sala("S1").unwrap();
+    uno.crear_sala("S2").unwrap();
+    uno.invitar("S1", &["dos"]).unwrap();
+    dos.unirse("S1").unwrap();
+
+    uno.desconectar().unwrap();
+    match escucha.recv_timeout(time::Duration::from_secs(5)) {
+        Ok(EventoServidor::ClienteDesconectado { nombre: Some(ref nombre), .. }) if nombre == "uno" => {},
+        otro => panic!("Se esperaba la desconexión del propietario: {:?}", otro),
+    }
+
+    // El único miembro que queda en S1 la hereda, y S2 se queda sin miembros y se elimina.
+    dos.invitar("S1", &["tres"]).unwrap();
+    tres.unirse("S1").unwrap();
+    tres.crear_sala("S2").unwrap();
+
+    control.detener();
+    control.esperar();
+}
+
+#[test]
+fn t33_rafaga_de_comandos_en_reactor() {
+    let mut servidor = servidor_con_nucleo(Nucleo::Reactor);
+    servidor.set_capacidad_cola(100);
+    let control = servidor.iniciar().expect("Error al iniciar el servidor");
+    let direccion = control.get_direccion_local().unwrap();
+
+    // Muchos más comandos de los que caben en la cola de salida, en una sola escritura. El
+    // reactor los procesa por lotes y escribe las respuestas entre lotes, por lo que la cola
+    // no se llena; los que quedan tras cada lote se procesan aunque no lleguen datos nuevos.
+    let mut cliente = conectar(direccion);
+    cliente.set_read_timeout(Some(time::Duration::from_secs(5))).unwrap();
+    let comandos = 1000;
+    let rafaga: String = (0..comandos).map(|i| format!("#{} USERS\n", i + 1)).collect();
+    cliente.write_all(rafaga.as_bytes()).unwrap();
+    for i in 0..comandos {
+        let respuesta = obtener_respuesta(&cliente);
+        assert_eq!(respuesta.get_codigo(), CodigoRespuesta::OK);
+        assert_eq!(respuesta.get_id(), Some(i + 1));
+    }
+
+    control.detener();
+    control.esperar();
 }